@@ -0,0 +1,130 @@
+-- the schema as the create_table functions left it. everything is IF NOT EXISTS, so databases
+-- which were set up by those functions are taken over as they are
+
+CREATE TABLE IF NOT EXISTS auth_users (
+    uid BIGINT PRIMARY KEY,
+    email TEXT,
+    email_verified BOOLEAN DEFAULT FALSE,
+    username TEXT,
+    password_hash TEXT
+);
+
+CREATE TABLE IF NOT EXISTS auth_tokens (
+    jti TEXT PRIMARY KEY,
+    uid BIGINT NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS users (
+    uid BIGINT PRIMARY KEY,
+    email TEXT,
+    owner BOOLEAN DEFAULT FALSE,
+    email_verified BOOLEAN DEFAULT FALSE,
+    username TEXT,
+    locale TEXT
+);
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS locale TEXT;
+
+CREATE TABLE IF NOT EXISTS cloudthemes (
+    uid BIGINT PRIMARY KEY,
+    primary_color_text TEXT,
+    primary_color TEXT,
+    secondary_color TEXT,
+    background_color_primary TEXT,
+    background_color_secondary TEXT,
+    background_color_tertiary TEXT,
+    primary_grey TEXT,
+    secondary_grey TEXT,
+    font_size TEXT,
+    transparency BOOLEAN DEFAULT TRUE,
+    transparency_value FLOAT NOT NULL,
+    transparency_blur TEXT
+);
+
+CREATE TABLE IF NOT EXISTS cloudthemes_status (
+    uid BIGINT PRIMARY KEY,
+    enabled BOOLEAN DEFAULT FALSE
+);
+
+CREATE TABLE IF NOT EXISTS invites (
+    code TEXT PRIMARY KEY,
+    created_by BIGINT NOT NULL,
+    max_uses INT NOT NULL DEFAULT 1,
+    uses INT NOT NULL DEFAULT 0,
+    expires_at TIMESTAMPTZ,
+    role TEXT
+);
+
+CREATE TABLE IF NOT EXISTS invite_redemptions (
+    uid BIGINT PRIMARY KEY,
+    code TEXT NOT NULL,
+    invited_by BIGINT NOT NULL,
+    redeemed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS invite_quotas (
+    uid BIGINT PRIMARY KEY,
+    remaining INT NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS email_outbox (
+    id BIGINT PRIMARY KEY,
+    recipient TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    html TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INT NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    sent_at TIMESTAMPTZ,
+    text TEXT NOT NULL DEFAULT '',
+    unsubscribe_url TEXT
+);
+
+ALTER TABLE email_outbox
+    ADD COLUMN IF NOT EXISTS text TEXT NOT NULL DEFAULT '',
+    ADD COLUMN IF NOT EXISTS unsubscribe_url TEXT;
+
+CREATE TABLE IF NOT EXISTS notification_preferences (
+    uid BIGINT PRIMARY KEY,
+    reminders BOOLEAN NOT NULL DEFAULT TRUE,
+    login_alerts BOOLEAN NOT NULL DEFAULT TRUE,
+    announcements BOOLEAN NOT NULL DEFAULT TRUE
+);
+
+CREATE TABLE IF NOT EXISTS signup_cleanup_log (
+    id BIGINT PRIMARY KEY,
+    run_id BIGINT NOT NULL,
+    action TEXT NOT NULL,
+    uid BIGINT NOT NULL,
+    username TEXT NOT NULL,
+    reminder_day INT,
+    dry_run BOOLEAN NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS announcements (
+    id BIGINT PRIMARY KEY,
+    created_by BIGINT NOT NULL,
+    subject TEXT NOT NULL,
+    body TEXT NOT NULL,
+    audience TEXT NOT NULL,
+    send_email BOOLEAN NOT NULL,
+    email_cursor BIGINT NOT NULL DEFAULT 0,
+    email_done BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS notifications (
+    id BIGINT PRIMARY KEY,
+    uid BIGINT NOT NULL,
+    title TEXT NOT NULL,
+    body TEXT NOT NULL,
+    announcement_id BIGINT,
+    read_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS notifications_uid_idx ON notifications (uid, id);
@@ -0,0 +1,110 @@
+-- auth_users and users both stored the identity of an account. they are merged into accounts,
+-- the password hash moves into credentials. everything else which belongs to an account
+-- references it, so deleting the account removes it as well
+
+CREATE TABLE accounts (
+    uid BIGINT PRIMARY KEY,
+    username TEXT NOT NULL UNIQUE,
+    email TEXT NOT NULL UNIQUE,
+    email_verified BOOLEAN NOT NULL DEFAULT FALSE,
+    owner BOOLEAN NOT NULL DEFAULT FALSE,
+    locale TEXT
+);
+
+CREATE TABLE credentials (
+    uid BIGINT PRIMARY KEY REFERENCES accounts (uid) ON DELETE CASCADE,
+    password_hash TEXT NOT NULL
+);
+
+-- auth_users is the source of truth, users only adds owner and locale. the two tables were
+-- written one after the other, so an account counts as verified if either of them says so.
+-- rows without username or email can't log in anyway. a username or email which exists twice
+-- can't be merged without losing one of the accounts, so the migration stops and names them
+DO $$
+DECLARE
+    colliding TEXT;
+BEGIN
+    SELECT string_agg(a.uid::TEXT, ', ' ORDER BY a.uid) INTO colliding
+    FROM auth_users a
+    WHERE a.username IS NOT NULL AND a.email IS NOT NULL
+        AND EXISTS (
+            SELECT 1 FROM auth_users b
+            WHERE b.uid <> a.uid
+                AND b.username IS NOT NULL AND b.email IS NOT NULL
+                AND (b.username = a.username OR b.email = a.email)
+        );
+
+    IF colliding IS NOT NULL THEN
+        RAISE EXCEPTION 'these accounts share a username or email, rename them before migrating: %',
+            colliding;
+    END IF;
+END $$;
+
+INSERT INTO accounts (uid, username, email, email_verified, owner, locale)
+SELECT
+    a.uid,
+    a.username,
+    a.email,
+    COALESCE(a.email_verified, FALSE) OR COALESCE(u.email_verified, FALSE),
+    COALESCE(u.owner, FALSE),
+    u.locale
+FROM auth_users a
+LEFT JOIN users u ON u.uid = a.uid
+WHERE a.username IS NOT NULL AND a.email IS NOT NULL
+ORDER BY a.uid;
+
+INSERT INTO credentials (uid, password_hash)
+SELECT a.uid, a.password_hash
+FROM auth_users a
+JOIN accounts USING (uid)
+WHERE a.password_hash IS NOT NULL;
+
+-- rows of accounts which didn't make it over can't be reached anymore
+DELETE FROM auth_tokens WHERE uid NOT IN (SELECT uid FROM accounts);
+DELETE FROM cloudthemes WHERE uid NOT IN (SELECT uid FROM accounts);
+DELETE FROM cloudthemes_status WHERE uid NOT IN (SELECT uid FROM accounts);
+DELETE FROM invite_redemptions WHERE uid NOT IN (SELECT uid FROM accounts);
+DELETE FROM invite_quotas WHERE uid NOT IN (SELECT uid FROM accounts);
+DELETE FROM notification_preferences WHERE uid NOT IN (SELECT uid FROM accounts);
+DELETE FROM notifications WHERE uid NOT IN (SELECT uid FROM accounts);
+DELETE FROM announcements WHERE created_by NOT IN (SELECT uid FROM accounts);
+
+ALTER TABLE auth_tokens
+    ADD CONSTRAINT auth_tokens_uid_fkey
+    FOREIGN KEY (uid) REFERENCES accounts (uid) ON DELETE CASCADE;
+
+ALTER TABLE cloudthemes
+    ADD CONSTRAINT cloudthemes_uid_fkey
+    FOREIGN KEY (uid) REFERENCES accounts (uid) ON DELETE CASCADE;
+
+ALTER TABLE cloudthemes_status
+    ADD CONSTRAINT cloudthemes_status_uid_fkey
+    FOREIGN KEY (uid) REFERENCES accounts (uid) ON DELETE CASCADE;
+
+ALTER TABLE invite_redemptions
+    ADD CONSTRAINT invite_redemptions_uid_fkey
+    FOREIGN KEY (uid) REFERENCES accounts (uid) ON DELETE CASCADE;
+
+ALTER TABLE invite_quotas
+    ADD CONSTRAINT invite_quotas_uid_fkey
+    FOREIGN KEY (uid) REFERENCES accounts (uid) ON DELETE CASCADE;
+
+ALTER TABLE notification_preferences
+    ADD CONSTRAINT notification_preferences_uid_fkey
+    FOREIGN KEY (uid) REFERENCES accounts (uid) ON DELETE CASCADE;
+
+ALTER TABLE notifications
+    ADD CONSTRAINT notifications_uid_fkey
+    FOREIGN KEY (uid) REFERENCES accounts (uid) ON DELETE CASCADE;
+
+ALTER TABLE announcements
+    ADD CONSTRAINT announcements_created_by_fkey
+    FOREIGN KEY (created_by) REFERENCES accounts (uid) ON DELETE CASCADE;
+
+-- signup_cleanup_log gets no foreign key, it is the record of the accounts the cleanup deleted
+-- and has to outlive them
+
+CREATE INDEX auth_tokens_uid_idx ON auth_tokens (uid);
+
+DROP TABLE users;
+DROP TABLE auth_users;
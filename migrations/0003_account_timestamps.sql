@@ -0,0 +1,26 @@
+-- when an account was created, last changed, last logged in and verified its email. the
+-- repositories keep them up to date, see db::auth::auth and db::api::users
+
+ALTER TABLE accounts
+    ADD COLUMN created_at TIMESTAMPTZ,
+    ADD COLUMN updated_at TIMESTAMPTZ,
+    ADD COLUMN last_login_at TIMESTAMPTZ,
+    ADD COLUMN email_verified_at TIMESTAMPTZ;
+
+-- every uid is a snowflake which starts with the milliseconds since its epoch (see
+-- util::snowflake), that is the best guess for the existing accounts. when
+-- they logged in or verified their email wasn't recorded, verified accounts count as verified
+-- since they were created
+UPDATE accounts SET
+    created_at = to_timestamp(((uid >> 22) + 1704037200000) / 1000.0),
+    updated_at = to_timestamp(((uid >> 22) + 1704037200000) / 1000.0);
+
+UPDATE accounts SET email_verified_at = created_at WHERE email_verified;
+
+ALTER TABLE accounts
+    ALTER COLUMN created_at SET NOT NULL,
+    ALTER COLUMN created_at SET DEFAULT now(),
+    ALTER COLUMN updated_at SET NOT NULL,
+    ALTER COLUMN updated_at SET DEFAULT now();
+
+CREATE INDEX accounts_created_at_idx ON accounts (created_at);
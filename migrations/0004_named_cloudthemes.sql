@@ -0,0 +1,22 @@
+-- every user can keep several named themes, one of them is active. the theme a user had so far
+-- becomes their active theme called "Default"
+
+ALTER TABLE cloudthemes
+    ADD COLUMN id BIGINT,
+    ADD COLUMN name TEXT,
+    ADD COLUMN active BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+
+-- the uid is a snowflake of the same generator as the theme ids, every id generated from now on
+-- is younger than it and can't collide
+UPDATE cloudthemes SET id = uid, name = 'Default', active = TRUE;
+
+ALTER TABLE cloudthemes DROP CONSTRAINT cloudthemes_pkey;
+ALTER TABLE cloudthemes ADD PRIMARY KEY (id);
+ALTER TABLE cloudthemes ALTER COLUMN uid SET NOT NULL;
+ALTER TABLE cloudthemes ALTER COLUMN name SET NOT NULL;
+
+CREATE UNIQUE INDEX cloudthemes_uid_name_idx ON cloudthemes (uid, name);
+-- at most one active theme per user
+CREATE UNIQUE INDEX cloudthemes_active_idx ON cloudthemes (uid) WHERE active;
@@ -0,0 +1,49 @@
+-- the public theme gallery. an entry is a copy of a cloudtheme taken when it was published, later
+-- changes to the cloudtheme don't show up in it. every entry can be found by its share code,
+-- unlisted ones only by that
+
+CREATE TABLE theme_gallery (
+    id BIGINT PRIMARY KEY,
+    share_code TEXT NOT NULL UNIQUE,
+    uid BIGINT NOT NULL REFERENCES accounts (uid) ON DELETE CASCADE,
+    -- the cloudtheme the entry was copied from. no foreign key, the entry stays when the theme is
+    -- deleted, but while a moderator keeps an entry down its theme can't be published again
+    source_id BIGINT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    listed BOOLEAN NOT NULL DEFAULT TRUE,
+    primary_color_text TEXT NOT NULL,
+    primary_color TEXT NOT NULL,
+    secondary_color TEXT NOT NULL,
+    background_color_primary TEXT NOT NULL,
+    background_color_secondary TEXT NOT NULL,
+    background_color_tertiary TEXT NOT NULL,
+    primary_grey TEXT NOT NULL,
+    secondary_grey TEXT NOT NULL,
+    font_size TEXT NOT NULL,
+    transparency BOOLEAN NOT NULL,
+    transparency_value FLOAT NOT NULL,
+    transparency_blur TEXT NOT NULL,
+    installs BIGINT NOT NULL DEFAULT 0,
+    published BOOLEAN NOT NULL DEFAULT TRUE,
+    -- who took the entry down, the author or an owner, and why
+    unpublished_by BIGINT REFERENCES accounts (uid) ON DELETE SET NULL,
+    unpublish_reason TEXT,
+    unpublished_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX theme_gallery_uid_idx ON theme_gallery (uid, source_id);
+-- the two orders of the gallery
+CREATE INDEX theme_gallery_newest_idx ON theme_gallery (created_at DESC, id DESC)
+    WHERE published AND listed;
+CREATE INDEX theme_gallery_installs_idx ON theme_gallery (installs DESC, created_at DESC, id DESC)
+    WHERE published AND listed;
+
+-- installing the same entry again doesn't count twice
+CREATE TABLE theme_gallery_installs (
+    gallery_id BIGINT NOT NULL REFERENCES theme_gallery (id) ON DELETE CASCADE,
+    uid BIGINT NOT NULL REFERENCES accounts (uid) ON DELETE CASCADE,
+    installed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (gallery_id, uid)
+);
@@ -0,0 +1,125 @@
+-- the sqlite version of ../0001_initial_schema.sql. there are no sqlite databases from before the
+-- migrations, so the tables are created as they are. timestamps are text in the format of
+-- db::sqlite::timestamp, the defaults write the same format
+
+CREATE TABLE auth_users (
+    uid BIGINT PRIMARY KEY,
+    email TEXT,
+    email_verified BOOLEAN DEFAULT FALSE,
+    username TEXT,
+    password_hash TEXT
+);
+
+CREATE TABLE auth_tokens (
+    jti TEXT PRIMARY KEY,
+    uid BIGINT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+
+CREATE TABLE users (
+    uid BIGINT PRIMARY KEY,
+    email TEXT,
+    owner BOOLEAN DEFAULT FALSE,
+    email_verified BOOLEAN DEFAULT FALSE,
+    username TEXT,
+    locale TEXT
+);
+
+CREATE TABLE cloudthemes (
+    uid BIGINT PRIMARY KEY,
+    primary_color_text TEXT,
+    primary_color TEXT,
+    secondary_color TEXT,
+    background_color_primary TEXT,
+    background_color_secondary TEXT,
+    background_color_tertiary TEXT,
+    primary_grey TEXT,
+    secondary_grey TEXT,
+    font_size TEXT,
+    transparency BOOLEAN DEFAULT TRUE,
+    transparency_value REAL NOT NULL,
+    transparency_blur TEXT
+);
+
+CREATE TABLE cloudthemes_status (
+    uid BIGINT PRIMARY KEY,
+    enabled BOOLEAN DEFAULT FALSE
+);
+
+CREATE TABLE invites (
+    code TEXT PRIMARY KEY,
+    created_by BIGINT NOT NULL,
+    max_uses INT NOT NULL DEFAULT 1,
+    uses INT NOT NULL DEFAULT 0,
+    expires_at TEXT,
+    role TEXT
+);
+
+CREATE TABLE invite_redemptions (
+    uid BIGINT PRIMARY KEY,
+    code TEXT NOT NULL,
+    invited_by BIGINT NOT NULL,
+    redeemed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE TABLE invite_quotas (
+    uid BIGINT PRIMARY KEY,
+    remaining INT NOT NULL DEFAULT 0
+);
+
+CREATE TABLE email_outbox (
+    id BIGINT PRIMARY KEY,
+    recipient TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    html TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INT NOT NULL DEFAULT 0,
+    next_attempt_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    last_error TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    sent_at TEXT,
+    text TEXT NOT NULL DEFAULT '',
+    unsubscribe_url TEXT
+);
+
+CREATE TABLE notification_preferences (
+    uid BIGINT PRIMARY KEY,
+    reminders BOOLEAN NOT NULL DEFAULT TRUE,
+    login_alerts BOOLEAN NOT NULL DEFAULT TRUE,
+    announcements BOOLEAN NOT NULL DEFAULT TRUE
+);
+
+CREATE TABLE signup_cleanup_log (
+    id BIGINT PRIMARY KEY,
+    run_id BIGINT NOT NULL,
+    action TEXT NOT NULL,
+    uid BIGINT NOT NULL,
+    username TEXT NOT NULL,
+    reminder_day INT,
+    dry_run BOOLEAN NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE TABLE announcements (
+    id BIGINT PRIMARY KEY,
+    created_by BIGINT NOT NULL,
+    subject TEXT NOT NULL,
+    body TEXT NOT NULL,
+    audience TEXT NOT NULL,
+    send_email BOOLEAN NOT NULL,
+    email_cursor BIGINT NOT NULL DEFAULT 0,
+    email_done BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE TABLE notifications (
+    id BIGINT PRIMARY KEY,
+    uid BIGINT NOT NULL,
+    title TEXT NOT NULL,
+    body TEXT NOT NULL,
+    announcement_id BIGINT,
+    read_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX notifications_uid_idx ON notifications (uid, id);
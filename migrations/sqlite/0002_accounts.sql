@@ -0,0 +1,162 @@
+-- the sqlite version of ../0002_accounts.sql. sqlite can't add a foreign key to an existing
+-- table, so every table which references accounts is copied into a new table which has it
+
+CREATE TABLE accounts (
+    uid BIGINT PRIMARY KEY,
+    username TEXT NOT NULL UNIQUE,
+    email TEXT NOT NULL UNIQUE,
+    email_verified BOOLEAN NOT NULL DEFAULT FALSE,
+    owner BOOLEAN NOT NULL DEFAULT FALSE,
+    locale TEXT
+);
+
+CREATE TABLE credentials (
+    uid BIGINT PRIMARY KEY REFERENCES accounts (uid) ON DELETE CASCADE,
+    password_hash TEXT NOT NULL
+);
+
+-- same rules as in postgres. sqlite can't name the accounts which share a username or email,
+-- the insert fails on the unique constraint instead
+INSERT INTO accounts (uid, username, email, email_verified, owner, locale)
+SELECT
+    a.uid,
+    a.username,
+    a.email,
+    COALESCE(a.email_verified, FALSE) OR COALESCE(u.email_verified, FALSE),
+    COALESCE(u.owner, FALSE),
+    u.locale
+FROM auth_users a
+LEFT JOIN users u ON u.uid = a.uid
+WHERE a.username IS NOT NULL AND a.email IS NOT NULL
+ORDER BY a.uid;
+
+INSERT INTO credentials (uid, password_hash)
+SELECT a.uid, a.password_hash
+FROM auth_users a
+JOIN accounts USING (uid)
+WHERE a.password_hash IS NOT NULL;
+
+-- rows of accounts which didn't make it over are left behind
+CREATE TABLE auth_tokens_new (
+    jti TEXT PRIMARY KEY,
+    uid BIGINT NOT NULL REFERENCES accounts (uid) ON DELETE CASCADE,
+    expires_at TEXT NOT NULL
+);
+
+INSERT INTO auth_tokens_new
+SELECT * FROM auth_tokens WHERE uid IN (SELECT uid FROM accounts);
+
+DROP TABLE auth_tokens;
+ALTER TABLE auth_tokens_new RENAME TO auth_tokens;
+
+CREATE TABLE cloudthemes_new (
+    uid BIGINT PRIMARY KEY REFERENCES accounts (uid) ON DELETE CASCADE,
+    primary_color_text TEXT,
+    primary_color TEXT,
+    secondary_color TEXT,
+    background_color_primary TEXT,
+    background_color_secondary TEXT,
+    background_color_tertiary TEXT,
+    primary_grey TEXT,
+    secondary_grey TEXT,
+    font_size TEXT,
+    transparency BOOLEAN DEFAULT TRUE,
+    transparency_value REAL NOT NULL,
+    transparency_blur TEXT
+);
+
+INSERT INTO cloudthemes_new
+SELECT * FROM cloudthemes WHERE uid IN (SELECT uid FROM accounts);
+
+DROP TABLE cloudthemes;
+ALTER TABLE cloudthemes_new RENAME TO cloudthemes;
+
+CREATE TABLE cloudthemes_status_new (
+    uid BIGINT PRIMARY KEY REFERENCES accounts (uid) ON DELETE CASCADE,
+    enabled BOOLEAN DEFAULT FALSE
+);
+
+INSERT INTO cloudthemes_status_new
+SELECT * FROM cloudthemes_status WHERE uid IN (SELECT uid FROM accounts);
+
+DROP TABLE cloudthemes_status;
+ALTER TABLE cloudthemes_status_new RENAME TO cloudthemes_status;
+
+CREATE TABLE invite_redemptions_new (
+    uid BIGINT PRIMARY KEY REFERENCES accounts (uid) ON DELETE CASCADE,
+    code TEXT NOT NULL,
+    invited_by BIGINT NOT NULL,
+    redeemed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+INSERT INTO invite_redemptions_new
+SELECT * FROM invite_redemptions WHERE uid IN (SELECT uid FROM accounts);
+
+DROP TABLE invite_redemptions;
+ALTER TABLE invite_redemptions_new RENAME TO invite_redemptions;
+
+CREATE TABLE invite_quotas_new (
+    uid BIGINT PRIMARY KEY REFERENCES accounts (uid) ON DELETE CASCADE,
+    remaining INT NOT NULL DEFAULT 0
+);
+
+INSERT INTO invite_quotas_new
+SELECT * FROM invite_quotas WHERE uid IN (SELECT uid FROM accounts);
+
+DROP TABLE invite_quotas;
+ALTER TABLE invite_quotas_new RENAME TO invite_quotas;
+
+CREATE TABLE notification_preferences_new (
+    uid BIGINT PRIMARY KEY REFERENCES accounts (uid) ON DELETE CASCADE,
+    reminders BOOLEAN NOT NULL DEFAULT TRUE,
+    login_alerts BOOLEAN NOT NULL DEFAULT TRUE,
+    announcements BOOLEAN NOT NULL DEFAULT TRUE
+);
+
+INSERT INTO notification_preferences_new
+SELECT * FROM notification_preferences WHERE uid IN (SELECT uid FROM accounts);
+
+DROP TABLE notification_preferences;
+ALTER TABLE notification_preferences_new RENAME TO notification_preferences;
+
+CREATE TABLE notifications_new (
+    id BIGINT PRIMARY KEY,
+    uid BIGINT NOT NULL REFERENCES accounts (uid) ON DELETE CASCADE,
+    title TEXT NOT NULL,
+    body TEXT NOT NULL,
+    announcement_id BIGINT,
+    read_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+INSERT INTO notifications_new
+SELECT * FROM notifications WHERE uid IN (SELECT uid FROM accounts);
+
+DROP TABLE notifications;
+ALTER TABLE notifications_new RENAME TO notifications;
+
+CREATE TABLE announcements_new (
+    id BIGINT PRIMARY KEY,
+    created_by BIGINT NOT NULL REFERENCES accounts (uid) ON DELETE CASCADE,
+    subject TEXT NOT NULL,
+    body TEXT NOT NULL,
+    audience TEXT NOT NULL,
+    send_email BOOLEAN NOT NULL,
+    email_cursor BIGINT NOT NULL DEFAULT 0,
+    email_done BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+INSERT INTO announcements_new
+SELECT * FROM announcements WHERE created_by IN (SELECT uid FROM accounts);
+
+DROP TABLE announcements;
+ALTER TABLE announcements_new RENAME TO announcements;
+
+-- signup_cleanup_log keeps no foreign key, see postgres
+
+CREATE INDEX auth_tokens_uid_idx ON auth_tokens (uid);
+CREATE INDEX notifications_uid_idx ON notifications (uid, id);
+
+DROP TABLE users;
+DROP TABLE auth_users;
@@ -0,0 +1,17 @@
+-- the sqlite version of ../0003_account_timestamps.sql. sqlite can only add columns without
+-- NOT NULL and an expression as default, so the columns stay nullable here. the repositories
+-- always write created_at and updated_at
+
+ALTER TABLE accounts ADD COLUMN created_at TEXT;
+ALTER TABLE accounts ADD COLUMN updated_at TEXT;
+ALTER TABLE accounts ADD COLUMN last_login_at TEXT;
+ALTER TABLE accounts ADD COLUMN email_verified_at TEXT;
+
+-- the timestamp in the snowflake uid, see util::snowflake
+UPDATE accounts SET
+    created_at = strftime('%Y-%m-%dT%H:%M:%fZ', ((uid >> 22) + 1704037200000) / 1000.0, 'unixepoch'),
+    updated_at = strftime('%Y-%m-%dT%H:%M:%fZ', ((uid >> 22) + 1704037200000) / 1000.0, 'unixepoch');
+
+UPDATE accounts SET email_verified_at = created_at WHERE email_verified;
+
+CREATE INDEX accounts_created_at_idx ON accounts (created_at);
@@ -0,0 +1,71 @@
+-- the sqlite version of ../0004_named_cloudthemes.sql. sqlite can't change the primary key of a
+-- table, so the themes are copied into a new one
+
+CREATE TABLE cloudthemes_new (
+    id BIGINT PRIMARY KEY,
+    uid BIGINT NOT NULL REFERENCES accounts (uid) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT FALSE,
+    primary_color_text TEXT,
+    primary_color TEXT,
+    secondary_color TEXT,
+    background_color_primary TEXT,
+    background_color_secondary TEXT,
+    background_color_tertiary TEXT,
+    primary_grey TEXT,
+    secondary_grey TEXT,
+    font_size TEXT,
+    transparency BOOLEAN DEFAULT TRUE,
+    transparency_value REAL NOT NULL,
+    transparency_blur TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+INSERT INTO cloudthemes_new (
+    id,
+    uid,
+    name,
+    active,
+    primary_color_text,
+    primary_color,
+    secondary_color,
+    background_color_primary,
+    background_color_secondary,
+    background_color_tertiary,
+    primary_grey,
+    secondary_grey,
+    font_size,
+    transparency,
+    transparency_value,
+    transparency_blur,
+    created_at,
+    updated_at
+)
+SELECT
+    uid,
+    uid,
+    'Default',
+    TRUE,
+    primary_color_text,
+    primary_color,
+    secondary_color,
+    background_color_primary,
+    background_color_secondary,
+    background_color_tertiary,
+    primary_grey,
+    secondary_grey,
+    font_size,
+    transparency,
+    transparency_value,
+    transparency_blur,
+    strftime('%Y-%m-%dT%H:%M:%fZ', 'now'),
+    strftime('%Y-%m-%dT%H:%M:%fZ', 'now')
+FROM cloudthemes;
+
+DROP TABLE cloudthemes;
+ALTER TABLE cloudthemes_new RENAME TO cloudthemes;
+
+CREATE UNIQUE INDEX cloudthemes_uid_name_idx ON cloudthemes (uid, name);
+-- at most one active theme per user
+CREATE UNIQUE INDEX cloudthemes_active_idx ON cloudthemes (uid) WHERE active;
@@ -0,0 +1,42 @@
+-- the sqlite version of ../0005_theme_gallery.sql
+
+CREATE TABLE theme_gallery (
+    id BIGINT PRIMARY KEY,
+    share_code TEXT NOT NULL UNIQUE,
+    uid BIGINT NOT NULL REFERENCES accounts (uid) ON DELETE CASCADE,
+    source_id BIGINT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    listed BOOLEAN NOT NULL DEFAULT TRUE,
+    primary_color_text TEXT NOT NULL,
+    primary_color TEXT NOT NULL,
+    secondary_color TEXT NOT NULL,
+    background_color_primary TEXT NOT NULL,
+    background_color_secondary TEXT NOT NULL,
+    background_color_tertiary TEXT NOT NULL,
+    primary_grey TEXT NOT NULL,
+    secondary_grey TEXT NOT NULL,
+    font_size TEXT NOT NULL,
+    transparency BOOLEAN NOT NULL,
+    transparency_value REAL NOT NULL,
+    transparency_blur TEXT NOT NULL,
+    installs BIGINT NOT NULL DEFAULT 0,
+    published BOOLEAN NOT NULL DEFAULT TRUE,
+    unpublished_by BIGINT REFERENCES accounts (uid) ON DELETE SET NULL,
+    unpublish_reason TEXT,
+    unpublished_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+);
+
+CREATE INDEX theme_gallery_uid_idx ON theme_gallery (uid, source_id);
+CREATE INDEX theme_gallery_newest_idx ON theme_gallery (created_at DESC, id DESC)
+    WHERE published AND listed;
+CREATE INDEX theme_gallery_installs_idx ON theme_gallery (installs DESC, created_at DESC, id DESC)
+    WHERE published AND listed;
+
+CREATE TABLE theme_gallery_installs (
+    gallery_id BIGINT NOT NULL REFERENCES theme_gallery (id) ON DELETE CASCADE,
+    uid BIGINT NOT NULL REFERENCES accounts (uid) ON DELETE CASCADE,
+    installed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    PRIMARY KEY (gallery_id, uid)
+);
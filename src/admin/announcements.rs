@@ -0,0 +1,118 @@
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    db::api::{
+        announcements::{AnnouncementDatabase, AnnouncementDb},
+        notifications::{NotificationDatabase, NotificationDb},
+    },
+    error_response,
+    models::api::announcements::Audience,
+};
+
+use super::require_owner;
+
+const MAX_SUBJECT_LENGTH: usize = 200;
+const MAX_BODY_LENGTH: usize = 10_000;
+
+// puts the announcement into the inbox of everyone in the audience right away, the emails are
+// sent in batches by the send_announcement_emails job
+#[post("/announcements")]
+pub async fn create_announcement(
+    req: HttpRequest,
+    req_body: String,
+    db: AnnouncementDatabase,
+    notification_db: NotificationDatabase,
+) -> HttpResponse {
+    #[derive(Debug, Deserialize)]
+    struct CreateAnnouncement {
+        subject: String,
+        body: String,
+        #[serde(default = "default_audience")]
+        audience: Audience,
+        #[serde(default = "default_email")]
+        email: bool,
+    }
+
+    fn default_audience() -> Audience {
+        Audience::All
+    }
+
+    fn default_email() -> bool {
+        true
+    }
+
+    let user_id = match require_owner(&req).await {
+        Ok(uid) => uid,
+        Err(res) => return res,
+    };
+
+    let json_content: CreateAnnouncement = match serde_json::from_str(&req_body) {
+        Ok(content) => content,
+        Err(e) => return error_response!(400, e.to_string()),
+    };
+
+    let subject = json_content.subject.trim();
+    let body = json_content.body.trim();
+
+    if subject.is_empty() || body.is_empty() {
+        return error_response!(400, "subject and body can't be empty");
+    }
+
+    if subject.chars().count() > MAX_SUBJECT_LENGTH || body.chars().count() > MAX_BODY_LENGTH {
+        return error_response!(
+            400,
+            format!(
+                "the subject can be {} and the body {} characters long at most",
+                MAX_SUBJECT_LENGTH, MAX_BODY_LENGTH
+            )
+        );
+    }
+
+    let announcement = match db
+        .insert(
+            user_id,
+            subject,
+            body,
+            json_content.audience,
+            json_content.email,
+        )
+        .await
+    {
+        Ok(announcement) => announcement,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    let recipients = match notification_db.insert_announcement(&announcement).await {
+        Ok(recipients) => recipients,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    HttpResponse::Ok().json(json!({
+        "announcement": announcement,
+        "recipients": recipients,
+    }))
+}
+
+#[derive(Deserialize)]
+struct QueryParams {
+    limit: Option<i64>,
+}
+
+// the announcements newest first, email_cursor and email_done show how far the emails got
+#[get("/announcements")]
+pub async fn get_announcements(
+    req: HttpRequest,
+    query: web::Query<QueryParams>,
+    db: AnnouncementDatabase,
+) -> HttpResponse {
+    if let Err(res) = require_owner(&req).await {
+        return res;
+    }
+
+    match db.read_all(query.limit.unwrap_or(50).clamp(1, 500)).await {
+        Ok(announcements) => HttpResponse::Ok().json(announcements),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
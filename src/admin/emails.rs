@@ -0,0 +1,57 @@
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    db::api::outbox::{OutboxDatabase, OutboxDb},
+    error_response,
+    models::api::outbox::{STATUS_DEAD, STATUS_PENDING},
+};
+
+use super::require_owner;
+
+#[derive(Deserialize)]
+struct QueryParams {
+    status: Option<String>,
+    limit: Option<i64>,
+}
+
+// lists the emails which couldn't be delivered, `dead` (the default) gave up for good and `failed`
+// are still retried. only who they went to and why they failed, never what they said
+#[get("/emails")]
+pub async fn get_emails(
+    req: HttpRequest,
+    query: web::Query<QueryParams>,
+    db: OutboxDatabase,
+) -> HttpResponse {
+    if let Err(res) = require_owner(&req).await {
+        return res;
+    }
+
+    let status = match query.status.as_deref() {
+        None | Some("dead") => STATUS_DEAD,
+        Some("failed") => STATUS_PENDING,
+        Some(_) => return error_response!(400, "the status has to be dead or failed"),
+    };
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+
+    match db.read_failed(status, limit).await {
+        Ok(messages) => HttpResponse::Ok().json(messages),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
+
+#[post("/emails/{id}/retry")]
+pub async fn retry_email(req: HttpRequest, id: web::Path<i64>, db: OutboxDatabase) -> HttpResponse {
+    if let Err(res) = require_owner(&req).await {
+        return res;
+    }
+
+    match db.retry(*id).await {
+        Ok(true) => {
+            HttpResponse::Ok().json(json!({"message": "email queued for another attempt."}))
+        }
+        Ok(false) => error_response!(404, "couldnt find an unsent email with this id"),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
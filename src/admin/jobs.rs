@@ -0,0 +1,16 @@
+use actix_web::{get, HttpRequest, HttpResponse};
+
+use crate::scheduler::runner::JOB_METRICS;
+
+use super::require_owner;
+
+#[get("/jobs")]
+pub async fn get_jobs(req: HttpRequest) -> HttpResponse {
+    if let Err(res) = require_owner(&req).await {
+        return res;
+    }
+
+    let metrics = JOB_METRICS.lock().unwrap().clone();
+
+    HttpResponse::Ok().json(metrics)
+}
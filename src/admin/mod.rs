@@ -0,0 +1,30 @@
+use actix_web::{HttpMessage, HttpRequest, HttpResponse};
+
+use crate::{auth::utils::Claims, db::api::users::UserDb, error_response, state::AppState};
+
+pub mod announcements;
+pub mod emails;
+pub mod jobs;
+pub mod signups;
+pub mod themes;
+
+// returns the uid of the caller if they are an owner, otherwise the response to send back
+pub async fn require_owner(req: &HttpRequest) -> Result<i64, HttpResponse> {
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return Err(error_response!(500, e.to_string())),
+    };
+
+    let db = match AppState::of(req) {
+        Ok(state) => state.users(),
+        Err(e) => return Err(error_response!(500, e.to_string())),
+    };
+
+    match db.read_by_uid(user_id).await {
+        Ok(Some(user)) if user.owner => Ok(user_id),
+        Ok(_) => Err(error_response!(403, "only owners can access this endpoint")),
+        Err(e) => Err(error_response!(500, e.to_string())),
+    }
+}
@@ -0,0 +1,58 @@
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+
+use crate::{
+    db::api::signup_cleanup::{SignupCleanupDatabase, SignupCleanupDb},
+    error_response,
+    scheduler::signup_cleanup,
+    state::AppState,
+};
+
+use super::require_owner;
+
+// what the next cleanup run would do right now, nothing is sent, deleted or logged
+#[get("/signups/dry_run")]
+pub async fn get_signup_cleanup_dry_run(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> HttpResponse {
+    if let Err(res) = require_owner(&req).await {
+        return res;
+    }
+
+    match signup_cleanup::plan(&state, true).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
+
+#[derive(Deserialize)]
+struct QueryParams {
+    run_id: Option<i64>,
+    limit: Option<i64>,
+}
+
+// the audit log of the cleanup runs, newest first or a single run with `run_id`
+#[get("/signups/log")]
+pub async fn get_signup_cleanup_log(
+    req: HttpRequest,
+    query: web::Query<QueryParams>,
+    db: SignupCleanupDatabase,
+) -> HttpResponse {
+    if let Err(res) = require_owner(&req).await {
+        return res;
+    }
+
+    let entries = match query.run_id {
+        Some(run_id) => db.read_by_run(run_id).await,
+        None => {
+            db.read_recent(query.limit.unwrap_or(100).clamp(1, 1000))
+                .await
+        }
+    };
+
+    match entries {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
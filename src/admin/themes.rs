@@ -0,0 +1,131 @@
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+
+use crate::{
+    api::cloudthemes::gallery::normalize_share_code,
+    db::api::theme_gallery::{ThemeGalleryDatabase, ThemeGalleryDb},
+    error_response, message_response,
+};
+
+use super::require_owner;
+
+const MAX_REASON_LENGTH: usize = 500;
+
+#[derive(Deserialize)]
+struct QueryParams {
+    limit: Option<i64>,
+}
+
+// every gallery entry newest first, unpublished and unlisted ones too
+#[get("/themes")]
+pub async fn get_gallery_themes(
+    req: HttpRequest,
+    query: web::Query<QueryParams>,
+    gallery: ThemeGalleryDatabase,
+) -> HttpResponse {
+    if let Err(res) = require_owner(&req).await {
+        return res;
+    }
+
+    match gallery
+        .read_all(query.limit.unwrap_or(50).clamp(1, 500))
+        .await
+    {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
+
+// takes an entry out of the gallery, the author sees the reason in their list of published themes
+#[post("/themes/{share_code}/unpublish")]
+pub async fn unpublish_gallery_theme(
+    req: HttpRequest,
+    path: web::Path<String>,
+    req_body: String,
+    gallery: ThemeGalleryDatabase,
+) -> HttpResponse {
+    #[derive(Deserialize, Default)]
+    struct Unpublish {
+        reason: Option<String>,
+    }
+
+    let user_id = match require_owner(&req).await {
+        Ok(uid) => uid,
+        Err(res) => return res,
+    };
+
+    let body: Unpublish = if req_body.trim().is_empty() {
+        Unpublish::default()
+    } else {
+        match serde_json::from_str(&req_body) {
+            Ok(body) => body,
+            Err(e) => return error_response!(400, e.to_string()),
+        }
+    };
+
+    let reason = body
+        .reason
+        .as_deref()
+        .map(str::trim)
+        .filter(|reason| !reason.is_empty());
+
+    if reason.is_some_and(|reason| reason.chars().count() > MAX_REASON_LENGTH) {
+        return error_response!(
+            400,
+            format!(
+                "the reason can be {} characters long at most",
+                MAX_REASON_LENGTH
+            )
+        );
+    }
+
+    let entry = match gallery
+        .read_by_share_code(&normalize_share_code(&path.into_inner()))
+        .await
+    {
+        Ok(Some(entry)) => entry,
+        Ok(None) => return error_response!(404, "couldnt find a theme with this share code"),
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    match gallery.unpublish(entry.id, user_id, reason).await {
+        Ok(true) => message_response!("theme unpublished."),
+        Ok(false) => error_response!(409, "this theme isn't published"),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
+
+// puts an entry an owner took down back into the gallery. what the author took down themselves
+// stays down
+#[post("/themes/{share_code}/republish")]
+pub async fn republish_gallery_theme(
+    req: HttpRequest,
+    path: web::Path<String>,
+    gallery: ThemeGalleryDatabase,
+) -> HttpResponse {
+    if let Err(res) = require_owner(&req).await {
+        return res;
+    }
+
+    let entry = match gallery
+        .read_by_share_code(&normalize_share_code(&path.into_inner()))
+        .await
+    {
+        Ok(Some(entry)) => entry,
+        Ok(None) => return error_response!(404, "couldnt find a theme with this share code"),
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    if entry.published {
+        return error_response!(409, "this theme is published already");
+    }
+    if entry.unpublished_by == Some(entry.uid) {
+        return error_response!(409, "the author unpublished this theme themselves");
+    }
+
+    match gallery.republish(entry.id).await {
+        Ok(true) => message_response!("theme published again."),
+        Ok(false) => error_response!(404, "couldnt find a theme with this share code"),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
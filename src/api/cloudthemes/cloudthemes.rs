@@ -1,15 +1,65 @@
-use actix_web::{get, post, web, HttpMessage, HttpRequest, HttpResponse};
+use actix_web::{delete, get, post, web, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+use serde::Deserialize;
 
+use super::util::validate_theme;
 use crate::{
     auth::utils::Claims,
     cache::init_caches::USER_CLOUDTHEMES,
-    db::api::cloudthemes::cloudthemes::{CloudThemeDatabase, CloudThemeDb},
-    error_response,
-    models::api::cloudtheme::{CloudTheme, Theme},
+    db::api::cloudthemes::cloudthemes::{CloudThemeConflict, CloudThemeDatabase, CloudThemeDb},
+    error_response, message_response,
+    models::api::cloudtheme::Theme,
 };
 
+pub const MAX_THEMES_PER_USER: usize = 20;
+const MAX_NAME_LENGTH: usize = 50;
+// what the theme saved through POST /cloudthemes is called if the user had none yet
+const DEFAULT_THEME_NAME: &str = "Default";
+
+#[derive(Deserialize)]
+struct NewTheme {
+    name: String,
+    theme: Theme,
+    #[serde(default)]
+    active: bool,
+}
+
+#[derive(Deserialize)]
+struct ThemeName {
+    name: String,
+}
+
+// the trimmed name, or why it can't be used. if another theme has it already the database says so
+pub fn theme_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+
+    if name.is_empty() {
+        return Err("the name of a theme can't be empty".to_string());
+    }
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Err(format!(
+            "the name of a theme can be at most {} characters long",
+            MAX_NAME_LENGTH
+        ));
+    }
+
+    Ok(name.to_string())
+}
+
+// a taken name or too many themes are the user's to fix, anything else is on us
+pub fn write_error(e: anyhow::Error) -> HttpResponse {
+    match e.downcast_ref::<CloudThemeConflict>() {
+        Some(conflict) => error_response!(409, conflict.to_string()),
+        None => error_response!(500, e.to_string()),
+    }
+}
+
+// saves the values of the active theme, creates it if the user has no theme yet
 #[post("/cloudthemes")]
-pub async fn set_cloudtheme(req: HttpRequest, body: web::Bytes) -> HttpResponse {
+pub async fn set_cloudtheme(
+    req: HttpRequest,
+    body: web::Bytes,
+    db: CloudThemeDatabase,
+) -> HttpResponse {
     let claims = req.extensions().get::<Claims>().cloned().unwrap();
 
     let user_id = match claims.user_id.parse::<i64>() {
@@ -27,37 +77,41 @@ pub async fn set_cloudtheme(req: HttpRequest, body: web::Bytes) -> HttpResponse
         Err(e) => return error_response!(400, format!("Failed to parse JSON: {}", e)),
     };
 
-    println!("theme: {:?}", theme);
+    let theme = match validate_theme(&theme) {
+        Ok(theme) => theme,
+        Err(invalid) => return invalid.error_response(),
+    };
 
-    let db = match CloudThemeDatabase::new().await {
-        Ok(db) => db,
-        Err(e) => return error_response!(500, e.to_string()),
+    let result = match db.read_active(user_id).await {
+        Ok(Some(active)) => db
+            .update_theme(user_id, active.id, &theme)
+            .await
+            .map(|_| ()),
+        Ok(None) => db
+            .insert(
+                user_id,
+                DEFAULT_THEME_NAME,
+                &theme,
+                true,
+                MAX_THEMES_PER_USER,
+            )
+            .await
+            .map(|_| ()),
+        Err(e) => Err(e),
     };
 
-    match db.create_table().await {
-        Ok(()) => (),
-        Err(e) => return error_response!(500, e.to_string()),
+    if let Err(e) = result {
+        return write_error(e);
     }
 
-    match db.insert(user_id, theme.clone()).await {
-        Ok(()) => (),
-        Err(e) => return error_response!(500, e.to_string()),
-    };
-
-    let cache = &*USER_CLOUDTHEMES;
-    cache.insert(
-        user_id,
-        CloudTheme {
-            uid: user_id,
-            theme,
-        },
-    );
+    USER_CLOUDTHEMES.remove(&user_id);
 
     HttpResponse::Ok().finish()
 }
 
+// the active theme
 #[get("/cloudthemes")]
-pub async fn get_cloudthemes(req: HttpRequest) -> HttpResponse {
+pub async fn get_cloudthemes(req: HttpRequest, db: CloudThemeDatabase) -> HttpResponse {
     let claims = req.extensions().get::<Claims>().cloned().unwrap();
 
     let user_id = match claims.user_id.parse::<i64>() {
@@ -67,19 +121,9 @@ pub async fn get_cloudthemes(req: HttpRequest) -> HttpResponse {
 
     let cache = &*USER_CLOUDTHEMES;
     if let Some(cloudtheme) = cache.get(&user_id) {
-        return HttpResponse::Ok().json(cloudtheme);
+        HttpResponse::Ok().json(cloudtheme)
     } else {
-        let db = match CloudThemeDatabase::new().await {
-            Ok(db) => db,
-            Err(e) => return error_response!(500, e.to_string()),
-        };
-
-        match db.create_table().await {
-            Ok(()) => (),
-            Err(e) => return error_response!(500, e.to_string()),
-        }
-
-        let theme = match db.read_by_uid(user_id).await {
+        let theme = match db.read_active(user_id).await {
             Ok(theme) => theme,
             Err(e) => return error_response!(500, e.to_string()),
         };
@@ -87,9 +131,252 @@ pub async fn get_cloudthemes(req: HttpRequest) -> HttpResponse {
         if let Some(theme) = theme {
             cache.insert(user_id, theme.clone());
 
-            return HttpResponse::Ok().json(theme);
+            HttpResponse::Ok().json(theme)
         } else {
-            return error_response!(404, "no theme found for this uid");
+            error_response!(404, "no theme found for this uid")
+        }
+    }
+}
+
+// every theme of the user, oldest first
+#[get("/cloudthemes/themes")]
+pub async fn list_cloudthemes(req: HttpRequest, db: CloudThemeDatabase) -> HttpResponse {
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    match db.read_all(user_id).await {
+        Ok(themes) => HttpResponse::Ok().json(themes),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
+
+// the first theme of a user is always active, later ones only with `"active": true`
+#[post("/cloudthemes/themes")]
+pub async fn create_cloudtheme(
+    req: HttpRequest,
+    req_body: String,
+    db: CloudThemeDatabase,
+) -> HttpResponse {
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    let new_theme: NewTheme = match serde_json::from_str(&req_body) {
+        Ok(new_theme) => new_theme,
+        Err(e) => return error_response!(400, format!("Failed to parse JSON: {}", e)),
+    };
+
+    let theme = match validate_theme(&new_theme.theme) {
+        Ok(theme) => theme,
+        Err(invalid) => return invalid.error_response(),
+    };
+
+    let name = match theme_name(&new_theme.name) {
+        Ok(name) => name,
+        Err(e) => return error_response!(400, e),
+    };
+
+    // a user without an active theme has no theme at all
+    let active = match db.read_active(user_id).await {
+        Ok(current) => new_theme.active || current.is_none(),
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    match db
+        .insert(user_id, &name, &theme, active, MAX_THEMES_PER_USER)
+        .await
+    {
+        Ok(cloudtheme) => {
+            USER_CLOUDTHEMES.remove(&user_id);
+            HttpResponse::Ok().json(cloudtheme)
+        }
+        Err(e) => write_error(e),
+    }
+}
+
+#[get("/cloudthemes/themes/{id}")]
+pub async fn get_cloudtheme(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    db: CloudThemeDatabase,
+) -> HttpResponse {
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    match db.read_by_id(user_id, path.into_inner()).await {
+        Ok(Some(cloudtheme)) => HttpResponse::Ok().json(cloudtheme),
+        Ok(None) => error_response!(404, "couldnt find a theme with this id"),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
+
+// replaces the values of a theme, the name stays
+#[post("/cloudthemes/themes/{id}")]
+pub async fn update_cloudtheme(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    req_body: String,
+    db: CloudThemeDatabase,
+) -> HttpResponse {
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    let theme: Theme = match serde_json::from_str(&req_body) {
+        Ok(theme) => theme,
+        Err(e) => return error_response!(400, format!("Failed to parse JSON: {}", e)),
+    };
+
+    let theme = match validate_theme(&theme) {
+        Ok(theme) => theme,
+        Err(invalid) => return invalid.error_response(),
+    };
+
+    match db.update_theme(user_id, path.into_inner(), &theme).await {
+        Ok(true) => {
+            USER_CLOUDTHEMES.remove(&user_id);
+            message_response!("theme saved.")
+        }
+        Ok(false) => error_response!(404, "couldnt find a theme with this id"),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
+
+#[post("/cloudthemes/themes/{id}/rename")]
+pub async fn rename_cloudtheme(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    req_body: String,
+    db: CloudThemeDatabase,
+) -> HttpResponse {
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    let id = path.into_inner();
+
+    let body: ThemeName = match serde_json::from_str(&req_body) {
+        Ok(body) => body,
+        Err(e) => return error_response!(400, format!("Failed to parse JSON: {}", e)),
+    };
+
+    let name = match theme_name(&body.name) {
+        Ok(name) => name,
+        Err(e) => return error_response!(400, e),
+    };
+
+    match db.rename(user_id, id, &name).await {
+        Ok(true) => {
+            USER_CLOUDTHEMES.remove(&user_id);
+            message_response!("theme renamed.")
+        }
+        Ok(false) => error_response!(404, "couldnt find a theme with this id"),
+        Err(e) => write_error(e),
+    }
+}
+
+// a copy of the theme under a new name, the copy isn't active
+#[post("/cloudthemes/themes/{id}/duplicate")]
+pub async fn duplicate_cloudtheme(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    req_body: String,
+    db: CloudThemeDatabase,
+) -> HttpResponse {
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    let id = path.into_inner();
+
+    let body: ThemeName = match serde_json::from_str(&req_body) {
+        Ok(body) => body,
+        Err(e) => return error_response!(400, format!("Failed to parse JSON: {}", e)),
+    };
+
+    let original = match db.read_by_id(user_id, id).await {
+        Ok(Some(original)) => original,
+        Ok(None) => return error_response!(404, "couldnt find a theme with this id"),
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    let name = match theme_name(&body.name) {
+        Ok(name) => name,
+        Err(e) => return error_response!(400, e),
+    };
+
+    match db
+        .insert(user_id, &name, &original.theme, false, MAX_THEMES_PER_USER)
+        .await
+    {
+        Ok(cloudtheme) => HttpResponse::Ok().json(cloudtheme),
+        Err(e) => write_error(e),
+    }
+}
+
+#[post("/cloudthemes/themes/{id}/activate")]
+pub async fn activate_cloudtheme(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    db: CloudThemeDatabase,
+) -> HttpResponse {
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    match db.activate(user_id, path.into_inner()).await {
+        Ok(true) => {
+            USER_CLOUDTHEMES.remove(&user_id);
+            message_response!("theme activated.")
+        }
+        Ok(false) => error_response!(404, "couldnt find a theme with this id"),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
+
+// deleting the active theme activates the most recently changed one of the rest
+#[delete("/cloudthemes/themes/{id}")]
+pub async fn delete_cloudtheme(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    db: CloudThemeDatabase,
+) -> HttpResponse {
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    match db.delete(user_id, path.into_inner()).await {
+        Ok(true) => {
+            USER_CLOUDTHEMES.remove(&user_id);
+            message_response!("theme deleted.")
         }
+        Ok(false) => error_response!(404, "couldnt find a theme with this id"),
+        Err(e) => error_response!(500, e.to_string()),
     }
 }
@@ -0,0 +1,245 @@
+use actix_web::{delete, get, post, web, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+use rand::Rng;
+use serde::Deserialize;
+
+use super::{
+    cloudthemes::{theme_name, write_error, MAX_THEMES_PER_USER},
+    util::validate_theme,
+};
+use crate::{
+    auth::utils::Claims,
+    cache::init_caches::USER_CLOUDTHEMES,
+    db::api::{
+        cloudthemes::cloudthemes::{CloudThemeDatabase, CloudThemeDb},
+        theme_gallery::{GalleryConflict, NewGalleryEntry, ThemeGalleryDatabase, ThemeGalleryDb},
+    },
+    error_response, message_response,
+};
+
+// published entries at the same time, unpublished ones don't count
+const MAX_PUBLISHED_PER_USER: usize = 20;
+const MAX_TITLE_LENGTH: usize = 60;
+const MAX_DESCRIPTION_LENGTH: usize = 500;
+
+// no 0/O and 1/I, share codes get read out and typed in
+const SHARE_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const SHARE_CODE_LENGTH: usize = 8;
+
+fn generate_share_code() -> String {
+    let mut rng = rand::thread_rng();
+
+    (0..SHARE_CODE_LENGTH)
+        .map(|_| SHARE_CODE_ALPHABET[rng.gen_range(0..SHARE_CODE_ALPHABET.len())] as char)
+        .collect()
+}
+
+// share codes are stored uppercase, whoever types one in doesn't have to
+pub fn normalize_share_code(share_code: &str) -> String {
+    share_code.trim().to_ascii_uppercase()
+}
+
+// copies a theme into the gallery, later changes to the theme don't change the entry
+#[post("/cloudthemes/themes/{id}/publish")]
+pub async fn publish_cloudtheme(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    req_body: String,
+    db: CloudThemeDatabase,
+    gallery: ThemeGalleryDatabase,
+) -> HttpResponse {
+    #[derive(Deserialize)]
+    struct PublishTheme {
+        title: String,
+        #[serde(default)]
+        description: String,
+        #[serde(default = "default_listed")]
+        listed: bool,
+    }
+
+    fn default_listed() -> bool {
+        true
+    }
+
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    let body: PublishTheme = match serde_json::from_str(&req_body) {
+        Ok(body) => body,
+        Err(e) => return error_response!(400, format!("Failed to parse JSON: {}", e)),
+    };
+
+    let title = body.title.trim();
+    let description = body.description.trim();
+
+    if title.is_empty() {
+        return error_response!(400, "the title can't be empty");
+    }
+    if title.chars().count() > MAX_TITLE_LENGTH
+        || description.chars().count() > MAX_DESCRIPTION_LENGTH
+    {
+        return error_response!(
+            400,
+            format!(
+                "the title can be {} and the description {} characters long at most",
+                MAX_TITLE_LENGTH, MAX_DESCRIPTION_LENGTH
+            )
+        );
+    }
+
+    let cloudtheme = match db.read_by_id(user_id, path.into_inner()).await {
+        Ok(Some(cloudtheme)) => cloudtheme,
+        Ok(None) => return error_response!(404, "couldnt find a theme with this id"),
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    // themes saved before the values were checked could still hold anything
+    let theme = match validate_theme(&cloudtheme.theme) {
+        Ok(theme) => theme,
+        Err(invalid) => return invalid.error_response(),
+    };
+
+    let entry = NewGalleryEntry {
+        uid: user_id,
+        source_id: cloudtheme.id,
+        share_code: &generate_share_code(),
+        title,
+        description,
+        listed: body.listed,
+        theme: &theme,
+    };
+
+    // what a moderator took down stays down, changing the theme doesn't get around that. entries
+    // the author unpublished themselves can be published again
+    match gallery.insert(&entry, MAX_PUBLISHED_PER_USER).await {
+        Ok(entry) => HttpResponse::Ok().json(entry),
+        Err(e) => match e.downcast_ref::<GalleryConflict>() {
+            Some(conflict) => error_response!(409, conflict.to_string()),
+            None => error_response!(500, e.to_string()),
+        },
+    }
+}
+
+// every entry the user published, newest first, with why it was taken down if it was
+#[get("/cloudthemes/gallery")]
+pub async fn get_published_themes(req: HttpRequest, gallery: ThemeGalleryDatabase) -> HttpResponse {
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    match gallery.read_by_uid(user_id).await {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
+
+// takes an own entry out of the gallery, its share code stops working as well
+#[delete("/cloudthemes/gallery/{share_code}")]
+pub async fn unpublish_own_theme(
+    req: HttpRequest,
+    path: web::Path<String>,
+    gallery: ThemeGalleryDatabase,
+) -> HttpResponse {
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    let entry = match gallery
+        .read_by_share_code(&normalize_share_code(&path.into_inner()))
+        .await
+    {
+        Ok(Some(entry)) if entry.uid == user_id => entry,
+        Ok(_) => return error_response!(404, "couldnt find a theme with this share code"),
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    match gallery.unpublish(entry.id, user_id, None).await {
+        Ok(true) => message_response!("theme unpublished."),
+        Ok(false) => error_response!(409, "this theme isn't published"),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
+
+// copies a gallery entry into the themes of the user, named after the entry unless a name is
+// given. installing the same entry again adds another copy but doesn't count as another install
+#[post("/cloudthemes/gallery/{share_code}/install")]
+pub async fn install_gallery_theme(
+    req: HttpRequest,
+    path: web::Path<String>,
+    req_body: String,
+    db: CloudThemeDatabase,
+    gallery: ThemeGalleryDatabase,
+) -> HttpResponse {
+    #[derive(Deserialize, Default)]
+    struct InstallTheme {
+        name: Option<String>,
+        #[serde(default)]
+        active: bool,
+    }
+
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    let body: InstallTheme = if req_body.trim().is_empty() {
+        InstallTheme::default()
+    } else {
+        match serde_json::from_str(&req_body) {
+            Ok(body) => body,
+            Err(e) => return error_response!(400, format!("Failed to parse JSON: {}", e)),
+        }
+    };
+
+    let entry = match gallery
+        .read_by_share_code(&normalize_share_code(&path.into_inner()))
+        .await
+    {
+        Ok(Some(entry)) if entry.published => entry,
+        Ok(_) => return error_response!(404, "couldnt find a theme with this share code"),
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    let name = match theme_name(body.name.as_deref().unwrap_or(&entry.title)) {
+        Ok(name) => name,
+        Err(e) => return error_response!(400, e),
+    };
+
+    let active = match db.read_active(user_id).await {
+        Ok(current) => body.active || current.is_none(),
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    let cloudtheme = match db
+        .insert(user_id, &name, &entry.theme, active, MAX_THEMES_PER_USER)
+        .await
+    {
+        Ok(cloudtheme) => cloudtheme,
+        Err(e) => return write_error(e),
+    };
+
+    if active {
+        USER_CLOUDTHEMES.remove(&user_id);
+    }
+
+    // the copy is made either way, a missed install only leaves the count one short
+    if let Err(e) = gallery.record_install(entry.id, user_id).await {
+        println!(
+            "failed to record the install of {} by {}: {}",
+            entry.share_code, user_id, e
+        );
+    }
+
+    HttpResponse::Ok().json(cloudtheme)
+}
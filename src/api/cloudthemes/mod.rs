@@ -1,3 +1,4 @@
 pub mod cloudthemes;
+pub mod gallery;
 pub mod status;
 pub mod util;
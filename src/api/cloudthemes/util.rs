@@ -0,0 +1,442 @@
+use std::collections::BTreeMap;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use thiserror::Error;
+
+use crate::models::api::cloudtheme::Theme;
+
+// every client applies the stored values as css, so only plain colors and lengths are accepted.
+// they are stored in one spelling: lowercase, hex with 6 or 8 digits, rgb()/hsl() with commas
+
+// nothing valid is longer, checked before any parsing
+const MAX_VALUE_LENGTH: usize = 64;
+
+static NUMBER: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[+-]?(\d+(\.\d*)?|\.\d+)$").unwrap());
+
+const NAMED_COLORS: &[&str] = &[
+    "aliceblue",
+    "antiquewhite",
+    "aqua",
+    "aquamarine",
+    "azure",
+    "beige",
+    "bisque",
+    "black",
+    "blanchedalmond",
+    "blue",
+    "blueviolet",
+    "brown",
+    "burlywood",
+    "cadetblue",
+    "chartreuse",
+    "chocolate",
+    "coral",
+    "cornflowerblue",
+    "cornsilk",
+    "crimson",
+    "cyan",
+    "darkblue",
+    "darkcyan",
+    "darkgoldenrod",
+    "darkgray",
+    "darkgreen",
+    "darkgrey",
+    "darkkhaki",
+    "darkmagenta",
+    "darkolivegreen",
+    "darkorange",
+    "darkorchid",
+    "darkred",
+    "darksalmon",
+    "darkseagreen",
+    "darkslateblue",
+    "darkslategray",
+    "darkslategrey",
+    "darkturquoise",
+    "darkviolet",
+    "deeppink",
+    "deepskyblue",
+    "dimgray",
+    "dimgrey",
+    "dodgerblue",
+    "firebrick",
+    "floralwhite",
+    "forestgreen",
+    "fuchsia",
+    "gainsboro",
+    "ghostwhite",
+    "gold",
+    "goldenrod",
+    "gray",
+    "green",
+    "greenyellow",
+    "grey",
+    "honeydew",
+    "hotpink",
+    "indianred",
+    "indigo",
+    "ivory",
+    "khaki",
+    "lavender",
+    "lavenderblush",
+    "lawngreen",
+    "lemonchiffon",
+    "lightblue",
+    "lightcoral",
+    "lightcyan",
+    "lightgoldenrodyellow",
+    "lightgray",
+    "lightgreen",
+    "lightgrey",
+    "lightpink",
+    "lightsalmon",
+    "lightseagreen",
+    "lightskyblue",
+    "lightslategray",
+    "lightslategrey",
+    "lightsteelblue",
+    "lightyellow",
+    "lime",
+    "limegreen",
+    "linen",
+    "magenta",
+    "maroon",
+    "mediumaquamarine",
+    "mediumblue",
+    "mediumorchid",
+    "mediumpurple",
+    "mediumseagreen",
+    "mediumslateblue",
+    "mediumspringgreen",
+    "mediumturquoise",
+    "mediumvioletred",
+    "midnightblue",
+    "mintcream",
+    "mistyrose",
+    "moccasin",
+    "navajowhite",
+    "navy",
+    "oldlace",
+    "olive",
+    "olivedrab",
+    "orange",
+    "orangered",
+    "orchid",
+    "palegoldenrod",
+    "palegreen",
+    "paleturquoise",
+    "palevioletred",
+    "papayawhip",
+    "peachpuff",
+    "peru",
+    "pink",
+    "plum",
+    "powderblue",
+    "purple",
+    "rebeccapurple",
+    "red",
+    "rosybrown",
+    "royalblue",
+    "saddlebrown",
+    "salmon",
+    "sandybrown",
+    "seagreen",
+    "seashell",
+    "sienna",
+    "silver",
+    "skyblue",
+    "slateblue",
+    "slategray",
+    "slategrey",
+    "snow",
+    "springgreen",
+    "steelblue",
+    "tan",
+    "teal",
+    "thistle",
+    "tomato",
+    "transparent",
+    "turquoise",
+    "violet",
+    "wheat",
+    "white",
+    "whitesmoke",
+    "yellow",
+    "yellowgreen",
+];
+
+struct LengthBounds {
+    units: &'static [&'static str],
+    // in px, rem and em count as 16px and pt as 4/3px
+    min: f64,
+    max: f64,
+    example: &'static str,
+}
+
+const FONT_SIZE: LengthBounds = LengthBounds {
+    units: &["px", "rem", "em", "pt", "%"],
+    min: 8.0,
+    max: 72.0,
+    example: "16px or 1rem",
+};
+
+const TRANSPARENCY_BLUR: LengthBounds = LengthBounds {
+    units: &["px", "rem", "em"],
+    min: 0.0,
+    max: 100.0,
+    example: "4px",
+};
+
+// every field which failed, by name, with what is wrong with it
+#[derive(Debug, Error)]
+#[error("the theme is invalid")]
+pub struct InvalidTheme {
+    pub fields: BTreeMap<&'static str, String>,
+}
+
+// the theme as it is stored, or every field which isn't valid
+pub fn validate_theme(theme: &Theme) -> Result<Theme, InvalidTheme> {
+    let mut fields = BTreeMap::new();
+
+    let mut color = |field: &'static str, value: &str| match normalize_color(value) {
+        Some(color) => color,
+        None => {
+            fields.insert(
+                field,
+                "must be a css color: hex, rgb(), rgba(), hsl(), hsla() or a color name"
+                    .to_string(),
+            );
+            String::new()
+        }
+    };
+
+    let primary_color_text = color("primary_color_text", &theme.primary_color_text);
+    let primary_color = color("primary_color", &theme.primary_color);
+    let secondary_color = color("secondary_color", &theme.secondary_color);
+    let background_color_primary =
+        color("background_color_primary", &theme.background_color_primary);
+    let background_color_secondary = color(
+        "background_color_secondary",
+        &theme.background_color_secondary,
+    );
+    let background_color_tertiary = color(
+        "background_color_tertiary",
+        &theme.background_color_tertiary,
+    );
+    let primary_grey = color("primary_grey", &theme.primary_grey);
+    let secondary_grey = color("secondary_grey", &theme.secondary_grey);
+
+    let mut length =
+        |field: &'static str, value: &str, bounds: &LengthBounds| match normalize_length(
+            value, bounds,
+        ) {
+            Some(length) => length,
+            None => {
+                fields.insert(
+                    field,
+                    format!(
+                        "must be a length between {}px and {}px in {}, e.g. {}",
+                        bounds.min,
+                        bounds.max,
+                        bounds.units.join(", "),
+                        bounds.example
+                    ),
+                );
+                String::new()
+            }
+        };
+
+    let font_size = length("font_size", &theme.font_size, &FONT_SIZE);
+    let transparency_blur = length(
+        "transparency_blur",
+        &theme.transparency_blur,
+        &TRANSPARENCY_BLUR,
+    );
+
+    if !(0.0..=1.0).contains(&theme.transparency_value) {
+        fields.insert(
+            "transparency_value",
+            "must be a number between 0 and 1".to_string(),
+        );
+    }
+
+    if !fields.is_empty() {
+        return Err(InvalidTheme { fields });
+    }
+
+    Ok(Theme {
+        primary_color_text,
+        primary_color,
+        secondary_color,
+        background_color_primary,
+        background_color_secondary,
+        background_color_tertiary,
+        primary_grey,
+        secondary_grey,
+        font_size,
+        transparency: theme.transparency,
+        transparency_value: theme.transparency_value,
+        transparency_blur,
+    })
+}
+
+fn normalize_color(value: &str) -> Option<String> {
+    if value.len() > MAX_VALUE_LENGTH {
+        return None;
+    }
+
+    let value = value.trim().to_ascii_lowercase();
+
+    if let Some(hex) = value.strip_prefix('#') {
+        return normalize_hex(hex);
+    }
+    if NAMED_COLORS.contains(&value.as_str()) {
+        return Some(value);
+    }
+
+    let (function, arguments) = value.strip_suffix(')')?.split_once('(')?;
+    let arguments = color_arguments(arguments)?;
+
+    match function.trim_end() {
+        "rgb" | "rgba" => normalize_rgb(&arguments),
+        "hsl" | "hsla" => normalize_hsl(&arguments),
+        _ => None,
+    }
+}
+
+// #rgb and #rgba are written out to #rrggbb and #rrggbbaa
+fn normalize_hex(hex: &str) -> Option<String> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    match hex.len() {
+        3 | 4 => Some(format!(
+            "#{}",
+            hex.chars().flat_map(|c| [c, c]).collect::<String>()
+        )),
+        6 | 8 => Some(format!("#{}", hex)),
+        _ => None,
+    }
+}
+
+// `1, 2, 3`, `1, 2, 3, 0.5`, `1 2 3` or `1 2 3 / 0.5`
+fn color_arguments(arguments: &str) -> Option<Vec<&str>> {
+    let arguments: Vec<&str> = if arguments.contains(',') {
+        arguments.split(',').map(str::trim).collect()
+    } else {
+        let (channels, alpha) = match arguments.split_once('/') {
+            Some((channels, alpha)) => (channels, Some(alpha.trim())),
+            None => (arguments, None),
+        };
+
+        let mut arguments: Vec<&str> = channels.split_whitespace().collect();
+        if arguments.len() != 3 {
+            return None;
+        }
+        arguments.extend(alpha);
+        arguments
+    };
+
+    if !(3..=4).contains(&arguments.len()) || arguments.iter().any(|argument| argument.is_empty()) {
+        return None;
+    }
+
+    Some(arguments)
+}
+
+fn normalize_rgb(arguments: &[&str]) -> Option<String> {
+    let mut channels = Vec::with_capacity(3);
+
+    for channel in &arguments[..3] {
+        let channel = match channel.strip_suffix('%') {
+            Some(percentage) => bounded(percentage, 0.0, 100.0)? * 2.55,
+            None => bounded(channel, 0.0, 255.0)?,
+        };
+        channels.push(format_number(channel.round()));
+    }
+
+    match arguments.get(3) {
+        Some(alpha) => Some(format!(
+            "rgba({}, {})",
+            channels.join(", "),
+            normalize_alpha(alpha)?
+        )),
+        None => Some(format!("rgb({})", channels.join(", "))),
+    }
+}
+
+fn normalize_hsl(arguments: &[&str]) -> Option<String> {
+    let hue = number(arguments[0].strip_suffix("deg").unwrap_or(arguments[0]))?.rem_euclid(360.0);
+    let saturation = bounded(arguments[1].strip_suffix('%')?, 0.0, 100.0)?;
+    let lightness = bounded(arguments[2].strip_suffix('%')?, 0.0, 100.0)?;
+
+    let channels = format!(
+        "{}, {}%, {}%",
+        format_number(hue),
+        format_number(saturation),
+        format_number(lightness)
+    );
+
+    match arguments.get(3) {
+        Some(alpha) => Some(format!("hsla({}, {})", channels, normalize_alpha(alpha)?)),
+        None => Some(format!("hsl({})", channels)),
+    }
+}
+
+fn normalize_alpha(alpha: &str) -> Option<String> {
+    let alpha = match alpha.strip_suffix('%') {
+        Some(percentage) => bounded(percentage, 0.0, 100.0)? / 100.0,
+        None => bounded(alpha, 0.0, 1.0)?,
+    };
+
+    Some(format_number(alpha))
+}
+
+fn normalize_length(value: &str, bounds: &LengthBounds) -> Option<String> {
+    if value.len() > MAX_VALUE_LENGTH {
+        return None;
+    }
+
+    let value = value.trim().to_ascii_lowercase();
+
+    // a zero doesn't need a unit
+    if number(&value) == Some(0.0) && bounds.min <= 0.0 {
+        return Some("0px".to_string());
+    }
+
+    let unit = bounds.units.iter().find(|unit| value.ends_with(**unit))?;
+    let amount = number(&value[..value.len() - unit.len()])?;
+
+    let px = match *unit {
+        "rem" | "em" => amount * 16.0,
+        "pt" => amount * 4.0 / 3.0,
+        "%" => amount / 100.0 * 16.0,
+        _ => amount,
+    };
+    if !(bounds.min..=bounds.max).contains(&px) {
+        return None;
+    }
+
+    Some(format!("{}{}", format_number(amount), unit))
+}
+
+// plain decimal numbers only, no exponents, infinities or units
+fn number(value: &str) -> Option<f64> {
+    if !NUMBER.is_match(value) {
+        return None;
+    }
+
+    value.parse().ok()
+}
+
+fn bounded(value: &str, min: f64, max: f64) -> Option<f64> {
+    number(value).filter(|number| (min..=max).contains(number))
+}
+
+// at most 3 decimals and no trailing zeros, 16.0 becomes 16
+fn format_number(value: f64) -> String {
+    // adding 0.0 turns -0 into 0
+    format!("{}", (value * 1000.0).round() / 1000.0 + 0.0)
+}
@@ -0,0 +1,259 @@
+use actix_web::{delete, get, post, web, HttpMessage, HttpRequest, HttpResponse};
+use chrono::Duration;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    auth::utils::Claims,
+    db::api::{
+        invites::{InviteDatabase, InviteDb},
+        users::{UserDatabase, UserDb},
+    },
+    error_response,
+    models::api::{
+        invites::{Invite, InviteQuota},
+        users::User,
+    },
+    state::AppState,
+};
+
+// roles an invite is allowed to grant on registration
+const INVITE_ROLES: [&str; 1] = ["owner"];
+// an invite expires within a year at the latest
+const MAX_EXPIRES_IN_HOURS: i64 = 24 * 365;
+
+fn generate_invite_code() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(12)
+        .map(char::from)
+        .collect()
+}
+
+async fn read_user(db: &UserDatabase, user_id: i64) -> Result<User, HttpResponse> {
+    match db.read_by_uid(user_id).await {
+        Ok(Some(user)) => Ok(user),
+        Ok(None) => Err(error_response!(404, "couldnt find a user with this uid")),
+        Err(e) => Err(error_response!(500, e.to_string())),
+    }
+}
+
+#[post("/invites")]
+pub async fn create_invite(
+    req: HttpRequest,
+    body: web::Bytes,
+    state: web::Data<AppState>,
+    users: UserDatabase,
+    db: InviteDatabase,
+) -> HttpResponse {
+    #[derive(Debug, Deserialize)]
+    struct CreateInvite {
+        max_uses: Option<i32>,
+        expires_in_hours: Option<i64>,
+        role: Option<String>,
+    }
+
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    let request: CreateInvite = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => return error_response!(400, format!("Failed to parse JSON: {}", e)),
+    };
+
+    let user = match read_user(&users, user_id).await {
+        Ok(user) => user,
+        Err(res) => return res,
+    };
+
+    if let Some(role) = &request.role {
+        if !INVITE_ROLES.contains(&role.as_str()) {
+            return error_response!(400, format!("unknown role '{}'", role));
+        }
+    }
+
+    let max_uses = request.max_uses.unwrap_or(1);
+
+    if max_uses < 1 {
+        return error_response!(400, "max_uses must be at least 1");
+    }
+
+    if let Some(hours) = request.expires_in_hours {
+        if !(1..=MAX_EXPIRES_IN_HOURS).contains(&hours) {
+            return error_response!(
+                400,
+                format!(
+                    "expires_in_hours must be between 1 and {}",
+                    MAX_EXPIRES_IN_HOURS
+                )
+            );
+        }
+    }
+
+    if !user.owner {
+        // users without owner rights can only hand out single use invites from their quota
+        if request.role.is_some() || max_uses != 1 {
+            return error_response!(
+                403,
+                "only owners can create invites with a role or multiple uses"
+            );
+        }
+
+        match db.take_quota(user_id).await {
+            Ok(true) => (),
+            Ok(false) => return error_response!(403, "you have no invites left"),
+            Err(e) => return error_response!(500, e.to_string()),
+        }
+    }
+
+    let invite = Invite {
+        code: generate_invite_code(),
+        created_by: user_id,
+        max_uses,
+        uses: 0,
+        expires_at: request
+            .expires_in_hours
+            .map(|hours| state.clock.now() + Duration::hours(hours)),
+        role: request.role,
+    };
+
+    if let Err(e) = db.insert(&invite).await {
+        if !user.owner {
+            if let Err(e) = db.give_back_quota(user_id).await {
+                println!("failed to give the invite quota of {} back: {}", user_id, e);
+            }
+        }
+        return error_response!(500, e.to_string());
+    }
+
+    HttpResponse::Ok().json(invite)
+}
+
+#[get("/invites")]
+pub async fn get_invites(
+    req: HttpRequest,
+    users: UserDatabase,
+    db: InviteDatabase,
+) -> HttpResponse {
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    let user = match read_user(&users, user_id).await {
+        Ok(user) => user,
+        Err(res) => return res,
+    };
+
+    let invites = if user.owner {
+        db.read_all().await
+    } else {
+        db.read_by_creator(user_id).await
+    };
+
+    match invites {
+        Ok(invites) => HttpResponse::Ok().json(invites),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
+
+#[delete("/invites/{code}")]
+pub async fn delete_invite(
+    req: HttpRequest,
+    code: web::Path<String>,
+    users: UserDatabase,
+    db: InviteDatabase,
+) -> HttpResponse {
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    let user = match read_user(&users, user_id).await {
+        Ok(user) => user,
+        Err(res) => return res,
+    };
+
+    if !user.owner {
+        return error_response!(403, "only owners can revoke invites");
+    }
+
+    match db.delete(&code).await {
+        Ok(true) => HttpResponse::Ok().json(json!({"message": "invite revoked."})),
+        Ok(false) => error_response!(404, "couldnt find this invite"),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
+
+#[get("/invites/quota")]
+pub async fn get_invite_quota(req: HttpRequest, db: InviteDatabase) -> HttpResponse {
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    match db.read_quota(user_id).await {
+        Ok(remaining) => HttpResponse::Ok().json(InviteQuota { remaining }),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
+
+#[post("/invites/quota")]
+pub async fn set_invite_quota(
+    req: HttpRequest,
+    body: web::Bytes,
+    users: UserDatabase,
+    db: InviteDatabase,
+) -> HttpResponse {
+    #[derive(Debug, Deserialize)]
+    struct SetQuota {
+        uid: i64,
+        remaining: i32,
+    }
+
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    let SetQuota { uid, remaining } = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => return error_response!(400, format!("Failed to parse JSON: {}", e)),
+    };
+
+    let user = match read_user(&users, user_id).await {
+        Ok(user) => user,
+        Err(res) => return res,
+    };
+
+    if !user.owner {
+        return error_response!(403, "only owners can hand out invite quotas");
+    }
+
+    if remaining < 0 {
+        return error_response!(400, "remaining cannot be negative");
+    }
+
+    // the quota belongs to the account, it can't be handed out ahead of it
+    if let Err(res) = read_user(&users, uid).await {
+        return res;
+    }
+
+    match db.set_quota(uid, remaining).await {
+        Ok(()) => HttpResponse::Ok().json(InviteQuota { remaining }),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
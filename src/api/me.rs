@@ -1,13 +1,20 @@
 use crate::{
     auth::utils::Claims,
     cache::init_caches::USER_ME_CACHE,
-    db::api::users::{UserDatabase, UserDb},
+    db::api::{
+        notification_preferences::{NotificationPreferencesDatabase, NotificationPreferencesDb},
+        users::{UserDatabase, UserDb},
+    },
     error_response,
+    mailer::locale::Locale,
+    message_response,
+    models::api::notification_preferences::NotificationCategory,
 };
-use actix_web::{get, HttpMessage, HttpRequest, HttpResponse};
+use actix_web::{get, post, HttpMessage, HttpRequest, HttpResponse};
+use serde::Deserialize;
 
 #[get("/me")]
-pub async fn me(req: HttpRequest) -> HttpResponse {
+pub async fn me(req: HttpRequest, db: UserDatabase) -> HttpResponse {
     let claims = req.extensions().get::<Claims>().cloned().unwrap();
 
     let user_id = match claims.user_id.parse::<i64>() {
@@ -17,27 +24,132 @@ pub async fn me(req: HttpRequest) -> HttpResponse {
 
     let cache = &*USER_ME_CACHE;
     if let Some(user) = cache.get(&user_id) {
-        return HttpResponse::Ok().json(user);
+        HttpResponse::Ok().json(user)
     } else {
-        let db = match UserDatabase::new().await {
-            Ok(db) => db,
-            Err(e) => return error_response!(500, e.to_string()),
-        };
-
-        match db.create_table().await {
-            Ok(()) => (),
-            Err(e) => return error_response!(500, e.to_string()),
-        }
-
-        let user_details = match db.read_by_uid(claims.user_id.parse().unwrap()).await {
+        let user_details = match db.read_by_uid(user_id).await {
             Ok(user) => user,
             Err(e) => return error_response!(500, e.to_string()),
         };
 
         if let Some(usr_details) = user_details {
-            return HttpResponse::Ok().json(usr_details);
+            HttpResponse::Ok().json(usr_details)
         } else {
-            return error_response!(404, "couldnt find a user with this uid");
+            error_response!(404, "couldnt find a user with this uid")
+        }
+    }
+}
+
+#[post("/me/locale")]
+pub async fn set_locale(req: HttpRequest, req_body: String, db: UserDatabase) -> HttpResponse {
+    #[derive(Debug, Deserialize)]
+    struct SetLocale {
+        // None resets to the Accept-Language of each request
+        locale: Option<String>,
+    }
+
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    let json_content: SetLocale = match serde_json::from_str(&req_body) {
+        Ok(content) => content,
+        Err(e) => return error_response!(400, e.to_string()),
+    };
+
+    let locale = match json_content.locale.as_deref() {
+        Some(tag) => match Locale::parse(tag) {
+            Some(locale) => Some(locale),
+            None => return error_response!(400, format!("unsupported locale '{}'", tag)),
+        },
+        None => None,
+    };
+
+    match db
+        .update_locale(user_id, locale.map(|locale| locale.code()))
+        .await
+    {
+        Ok(()) => (),
+        Err(e) => return error_response!(500, e.to_string()),
+    }
+
+    let cache = &*USER_ME_CACHE;
+    let _ = cache.remove(&user_id);
+
+    message_response!("locale updated.")
+}
+
+#[get("/me/notifications")]
+pub async fn get_notification_preferences(
+    req: HttpRequest,
+    db: NotificationPreferencesDatabase,
+) -> HttpResponse {
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    match db.read_by_uid(user_id).await {
+        Ok(preferences) => HttpResponse::Ok().json(preferences),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
+
+#[post("/me/notifications")]
+pub async fn set_notification_preferences(
+    req: HttpRequest,
+    req_body: String,
+    db: NotificationPreferencesDatabase,
+) -> HttpResponse {
+    // only the categories in the body are changed
+    #[derive(Debug, Deserialize)]
+    struct SetNotificationPreferences {
+        security: Option<bool>,
+        reminders: Option<bool>,
+        login_alerts: Option<bool>,
+        announcements: Option<bool>,
+    }
+
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    let json_content: SetNotificationPreferences = match serde_json::from_str(&req_body) {
+        Ok(content) => content,
+        Err(e) => return error_response!(400, e.to_string()),
+    };
+
+    if json_content.security == Some(false) {
+        return error_response!(400, "security emails can't be turned off.");
+    }
+
+    let mut preferences = match db.read_by_uid(user_id).await {
+        Ok(preferences) => preferences,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    for (category, enabled) in [
+        (NotificationCategory::Reminders, json_content.reminders),
+        (NotificationCategory::LoginAlerts, json_content.login_alerts),
+        (
+            NotificationCategory::Announcements,
+            json_content.announcements,
+        ),
+    ] {
+        if let Some(enabled) = enabled {
+            preferences.set(category, enabled);
         }
     }
+
+    match db.upsert(user_id, &preferences).await {
+        Ok(()) => HttpResponse::Ok().json(preferences),
+        Err(e) => error_response!(500, e.to_string()),
+    }
 }
@@ -1,2 +1,4 @@
+pub mod cloudthemes;
+pub mod invites;
 pub mod me;
-pub mod cloudthemes;
\ No newline at end of file
+pub mod notifications;
\ No newline at end of file
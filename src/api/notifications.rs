@@ -0,0 +1,80 @@
+use actix_web::{get, post, web, HttpMessage, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    auth::utils::Claims,
+    db::api::notifications::{NotificationDatabase, NotificationDb},
+    error_response, message_response,
+};
+
+#[derive(Deserialize)]
+struct QueryParams {
+    unread: Option<bool>,
+    limit: Option<i64>,
+}
+
+// the in-app inbox, newest first
+#[get("/notifications")]
+pub async fn get_notifications(
+    req: HttpRequest,
+    query: web::Query<QueryParams>,
+    db: NotificationDatabase,
+) -> HttpResponse {
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    match db
+        .read_by_uid(
+            user_id,
+            query.unread.unwrap_or(false),
+            query.limit.unwrap_or(50).clamp(1, 200),
+        )
+        .await
+    {
+        Ok(notifications) => HttpResponse::Ok().json(notifications),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
+
+#[post("/notifications/{id}/read")]
+pub async fn mark_notification_read(
+    req: HttpRequest,
+    path: web::Path<i64>,
+    db: NotificationDatabase,
+) -> HttpResponse {
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    match db.mark_read(user_id, path.into_inner()).await {
+        Ok(true) => message_response!("notification marked as read."),
+        Ok(false) => error_response!(404, "couldnt find a notification with this id"),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
+
+#[post("/notifications/read_all")]
+pub async fn mark_all_notifications_read(
+    req: HttpRequest,
+    db: NotificationDatabase,
+) -> HttpResponse {
+    let claims = req.extensions().get::<Claims>().cloned().unwrap();
+
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    match db.mark_all_read(user_id).await {
+        Ok(marked) => HttpResponse::Ok().json(json!({ "marked": marked })),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
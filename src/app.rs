@@ -0,0 +1,164 @@
+use actix_cors::Cors;
+use actix_files as fs;
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceFactory, ServiceRequest, ServiceResponse},
+    get, web, App, HttpResponse, Responder,
+};
+use actix_web_lab::middleware::from_fn;
+
+use crate::admin::{
+    announcements::{create_announcement, get_announcements},
+    emails::{get_emails, retry_email},
+    jobs::get_jobs,
+    signups::{get_signup_cleanup_dry_run, get_signup_cleanup_log},
+    themes::{get_gallery_themes, republish_gallery_theme, unpublish_gallery_theme},
+};
+use crate::api::{
+    cloudthemes::{
+        cloudthemes::{
+            activate_cloudtheme, create_cloudtheme, delete_cloudtheme, duplicate_cloudtheme,
+            get_cloudtheme, get_cloudthemes, list_cloudthemes, rename_cloudtheme, set_cloudtheme,
+            update_cloudtheme,
+        },
+        gallery::{
+            get_published_themes, install_gallery_theme, publish_cloudtheme, unpublish_own_theme,
+        },
+        status::{get_cloudthemes_status, post_cloudthemes_status},
+    },
+    invites::{create_invite, delete_invite, get_invite_quota, get_invites, set_invite_quota},
+    me::{get_notification_preferences, me, set_locale, set_notification_preferences},
+    notifications::{get_notifications, mark_all_notifications_read, mark_notification_read},
+};
+use crate::auth::{
+    auth_middleware::check_auth_mw,
+    links::verify_link,
+    login,
+    magic_link::{magic_link_login, request_magic_link},
+    password_reset::{request_reset_password, reset_password},
+    pow::challenge,
+    register, send_verifiaction_email, verify_email,
+};
+use crate::health::{database_available_mw, health, ready};
+use crate::pub_api::{
+    faith::book::faith_book,
+    github::get_repo_,
+    themes::{get_gallery, get_gallery_theme},
+    unsubscribe::{unsubscribe, unsubscribe_page},
+};
+use crate::state::AppState;
+
+#[get("/")]
+async fn index() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/html")
+        .body(include_str!("../static/index.html"))
+}
+
+async fn nested_hello() -> impl Responder {
+    HttpResponse::Ok().body("Hello from the nested route!")
+}
+
+// every route of the backend, main serves it and the tests call it with `actix_web::test`
+pub fn build_app(
+    state: web::Data<AppState>,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    let cors = Cors::default()
+        .allow_any_header()
+        .allow_any_origin()
+        .allow_any_method();
+
+    App::new()
+        .app_data(state)
+        .wrap(cors)
+        .service(fs::Files::new("/static", "static").show_files_listing())
+        .service(fs::Files::new("/assets", "assets").show_files_listing())
+        .service(
+            web::scope("/api")
+                .wrap(from_fn(check_auth_mw))
+                .wrap(from_fn(database_available_mw))
+                .route("/nested", web::get().to(nested_hello))
+                .service(me)
+                .service(set_locale)
+                .service(get_notification_preferences)
+                .service(set_notification_preferences)
+                .service(get_notifications)
+                .service(mark_all_notifications_read)
+                .service(mark_notification_read)
+                .service(set_cloudtheme)
+                .service(get_cloudthemes)
+                .service(get_cloudthemes_status)
+                .service(post_cloudthemes_status)
+                .service(list_cloudthemes)
+                .service(create_cloudtheme)
+                .service(get_cloudtheme)
+                .service(update_cloudtheme)
+                .service(rename_cloudtheme)
+                .service(duplicate_cloudtheme)
+                .service(activate_cloudtheme)
+                .service(delete_cloudtheme)
+                .service(publish_cloudtheme)
+                .service(get_published_themes)
+                .service(unpublish_own_theme)
+                .service(install_gallery_theme)
+                .service(get_invite_quota)
+                .service(set_invite_quota)
+                .service(create_invite)
+                .service(get_invites)
+                .service(delete_invite),
+        )
+        .service(
+            web::scope("/admin")
+                .wrap(from_fn(check_auth_mw))
+                .wrap(from_fn(database_available_mw))
+                .service(get_jobs)
+                .service(get_emails)
+                .service(retry_email)
+                .service(get_signup_cleanup_dry_run)
+                .service(get_signup_cleanup_log)
+                .service(create_announcement)
+                .service(get_announcements)
+                .service(get_gallery_themes)
+                .service(unpublish_gallery_theme)
+                .service(republish_gallery_theme),
+        )
+        .service(
+            web::scope("/pub_api")
+                .service(get_repo_)
+                .service(faith_book)
+                // everything below reads the database, the routes above keep working without it
+                .service(
+                    web::scope("")
+                        .wrap(from_fn(database_available_mw))
+                        .service(unsubscribe_page)
+                        .service(unsubscribe)
+                        .service(get_gallery)
+                        .service(get_gallery_theme),
+                ),
+        )
+        .service(
+            web::scope("/auth")
+                .wrap(from_fn(database_available_mw))
+                .service(challenge)
+                .service(register)
+                .service(login)
+                .service(send_verifiaction_email)
+                .service(verify_email)
+                .service(verify_link)
+                .service(request_magic_link)
+                .service(magic_link_login)
+                .service(request_reset_password)
+                .service(reset_password),
+        )
+        .service(health)
+        .service(ready)
+        .service(index)
+}
@@ -1,12 +1,10 @@
 use actix_web::{
-    body::{BoxBody, MessageBody}, dev::{ServiceRequest, ServiceResponse}, http::header::AUTHORIZATION, Error, HttpMessage, HttpResponse
+    body::{BoxBody, MessageBody}, dev::{ServiceRequest, ServiceResponse}, http::header::AUTHORIZATION, Error, HttpMessage, HttpResponse, ResponseError
 };
 use actix_web_lab::middleware::Next;
 use serde_json::json;
 
-use crate::{cache::init_caches::USER_CACHE, db::auth::auth::Database};
-
-use super::utils::TokenHandler;
+use crate::{cache::init_caches::USER_CACHE, db::{auth::auth::AuthDb, resilience::DatabaseUnavailable}, state::AppState};
 
 use crate::error_response;
 
@@ -22,9 +20,17 @@ where
     if let Some(auth_header) = req.headers().get(AUTHORIZATION) {
         let auth_header = auth_header.to_str().unwrap();
 
-        let token_handler = TokenHandler::new().await;
+        let state = match AppState::of(req.request()) {
+            Ok(state) => state.clone(),
+            Err(e) => {
+                let http_res = error_response!(500, e.to_string()).map_into_boxed_body();
+                let (req, _pl) = req.into_parts();
+                let service_res = ServiceResponse::new(req, http_res);
+                return Ok(service_res);
+            }
+        };
 
-        match token_handler.verify_token(auth_header).await {
+        match state.tokens().verify_token(auth_header).await {
             Ok(claims) => {
                 let uid: i64 = match claims.user_id.parse() {
                     Ok(uid) => uid,
@@ -45,25 +51,7 @@ where
                         return Ok(service_res);
                     }
                 } else {
-                    let db = match Database::new().await {
-                        Ok(db) => db,
-                        Err(e) => {
-                            let http_res = error_response!(500, e.to_string()).map_into_boxed_body();
-                            let (req, _pl) = req.into_parts();
-                            let service_res = ServiceResponse::new(req, http_res);
-                            return Ok(service_res);
-                        }
-                    };
-
-                    match db.create_table().await {
-                        Ok(()) => (),
-                        Err(e) => {
-                            let http_res = error_response!(500, e.to_string()).map_into_boxed_body();
-                            let (req, _pl) = req.into_parts();
-                            let service_res = ServiceResponse::new(req, http_res);
-                            return Ok(service_res);
-                        }
-                    }
+                    let db = state.auth_users();
 
                     let user_details = match db.read_by_uid(uid).await {
                         Ok(Some(details)) => details,
@@ -93,6 +81,16 @@ where
                 req.extensions_mut().insert(claims);
 
             },
+            // the token couldn't be checked because of the database, that isn't the token's fault
+            Err(e) if e.downcast_ref::<sqlx::Error>().is_some() || e.is::<DatabaseUnavailable>() => {
+                let http_res = match e.downcast_ref::<DatabaseUnavailable>() {
+                    Some(unavailable) => unavailable.error_response(),
+                    None => error_response!(500, e.to_string()).map_into_boxed_body(),
+                };
+                let (req, _pl) = req.into_parts();
+                let service_res = ServiceResponse::new(req, http_res);
+                return Ok(service_res);
+            },
             Err(e) => {
                 println!("{:?}", e);
 
@@ -0,0 +1,250 @@
+use actix_web::{get, web, HttpResponse};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    cache::init_caches::{USER_CACHE, USER_ME_CACHE},
+    db::auth::auth::{AuthDb, Database},
+    secrets::SECRETS,
+    state::AppState,
+    util::clock::Clock,
+};
+
+use super::utils::{CodeError, CodeStorage};
+
+// signed single use links which are sent together with the codes from CodeStorage.
+// a link carries a digest of its code, so it expires together with the code, is used up together
+// with it and wrong links count as wrong attempts on the code. it also carries a digest of the
+// password hash, so every link stops working once the password changes.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkPurpose {
+    VerifyEmail,
+    ResetPassword,
+    MagicLogin,
+}
+
+impl LinkPurpose {
+    fn as_str(&self) -> &'static str {
+        match self {
+            LinkPurpose::VerifyEmail => "verify_email",
+            LinkPurpose::ResetPassword => "reset_password",
+            LinkPurpose::MagicLogin => "magic_login",
+        }
+    }
+
+    fn code_storage(&self) -> CodeStorage {
+        match self {
+            LinkPurpose::VerifyEmail => CodeStorage::EmailVerificationCodes,
+            LinkPurpose::ResetPassword => CodeStorage::PasswordResetCodes,
+            LinkPurpose::MagicLogin => CodeStorage::MagicLinkCodes,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LinkClaims {
+    purpose: String,
+    uid: String,
+    password: String,
+    code: String,
+    exp: usize,
+}
+
+fn get_secret_key() -> Vec<u8> {
+    SECRETS
+        .get("SECRET_KEY")
+        .expect("SECRET_KEY not found")
+        .as_bytes()
+        .to_vec()
+}
+
+// where this backend is reachable from the outside, used for links pointing to the backend
+pub fn public_url() -> String {
+    SECRETS
+        .get("PUBLIC_URL")
+        .map(|url| url.trim_end_matches('/').to_string())
+        .unwrap_or_else(|| String::from("http://127.0.0.1:8080"))
+}
+
+pub fn frontend_url() -> String {
+    SECRETS
+        .get("FRONTEND_URL")
+        .map(|url| url.trim_end_matches('/').to_string())
+        .unwrap_or_else(|| String::from("https://acid4sigmas.systems"))
+}
+
+pub fn digest(value: &str) -> String {
+    format!("{:x}", Sha256::digest(value.as_bytes()))
+}
+
+fn create_token(purpose: LinkPurpose, uid: i64, password_hash: &str, code: &str) -> String {
+    let exp = purpose
+        .code_storage()
+        .expires_at(&uid.to_string())
+        .unwrap_or_default();
+
+    let claims = LinkClaims {
+        purpose: purpose.as_str().to_string(),
+        uid: uid.to_string(),
+        password: digest(password_hash),
+        code: digest(code),
+        exp,
+    };
+
+    encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(&get_secret_key()),
+    )
+    .expect("Failed to generate link token")
+}
+
+fn decode_token(clock: &dyn Clock, purpose: LinkPurpose, token: &str) -> Option<LinkClaims> {
+    // the expiry is checked against the clock below instead
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.validate_exp = false;
+
+    let claims = decode::<LinkClaims>(
+        token,
+        &DecodingKey::from_secret(&get_secret_key()),
+        &validation,
+    )
+    .ok()?
+    .claims;
+
+    if claims.purpose != purpose.as_str() || claims.exp < clock.now().timestamp() as usize {
+        return None;
+    }
+
+    Some(claims)
+}
+
+// the link from the verification email, opens GET /auth/verify on this backend
+pub fn verify_email_link(uid: i64, password_hash: &str, code: &str) -> String {
+    format!(
+        "{}/auth/verify?token={}",
+        public_url(),
+        create_token(LinkPurpose::VerifyEmail, uid, password_hash, code)
+    )
+}
+
+// the link from the password reset email, opens the reset form of the frontend with the email and token filled in
+pub fn reset_password_link(uid: i64, email: &str, password_hash: &str, code: &str) -> String {
+    format!(
+        "{}/reset_password?email={}&token={}",
+        frontend_url(),
+        urlencoding::encode(email),
+        create_token(LinkPurpose::ResetPassword, uid, password_hash, code)
+    )
+}
+
+// the link from the magic link email, the frontend sends the token to POST /auth/magic_link/login
+pub fn magic_login_link(uid: i64, password_hash: &str, code: &str) -> String {
+    format!(
+        "{}/magic_link?token={}",
+        frontend_url(),
+        create_token(LinkPurpose::MagicLogin, uid, password_hash, code)
+    )
+}
+
+// the account a link was sent to, None if the token isn't a valid link for `purpose`
+pub fn link_uid(clock: &dyn Clock, purpose: LinkPurpose, token: &str) -> Option<i64> {
+    decode_token(clock, purpose, token).and_then(|claims| claims.uid.parse().ok())
+}
+
+// checks a link token for the user with `uid` the same way `CodeStorage::check_code` checks a code.
+// like a code the link is only used up once the caller deletes the code
+pub fn check_link(
+    clock: &dyn Clock,
+    purpose: LinkPurpose,
+    token: &str,
+    uid: i64,
+    password_hash: &str,
+) -> Result<(), CodeError> {
+    let storage = purpose.code_storage();
+
+    let claims = match decode_token(clock, purpose, token) {
+        Some(claims) if claims.uid == uid.to_string() => claims,
+        // not signed by us or for someone else, nothing to account this on
+        _ => return Err(CodeError::Wrong),
+    };
+
+    if claims.password != digest(password_hash) {
+        return Err(storage.record_failed_attempt(clock, &claims.uid));
+    }
+
+    storage.check_code_digest(clock, &claims.uid, &claims.code)
+}
+
+fn frontend_redirect(path: &str, result: Result<(), String>) -> HttpResponse {
+    let query = match result {
+        Ok(()) => String::from("status=success"),
+        Err(e) => format!("status=error&error={}", urlencoding::encode(&e)),
+    };
+
+    HttpResponse::Found()
+        .append_header(("Location", format!("{}/{}?{}", frontend_url(), path, query)))
+        .finish()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyLinkQuery {
+    token: String,
+}
+
+#[get("/verify")]
+pub async fn verify_link(
+    query: web::Query<VerifyLinkQuery>,
+    state: web::Data<AppState>,
+    auth_user_db: Database,
+) -> HttpResponse {
+    frontend_redirect(
+        "verify_email",
+        verify_email_by_link(state.clock.as_ref(), &auth_user_db, &query.token).await,
+    )
+}
+
+async fn verify_email_by_link(
+    clock: &dyn Clock,
+    auth_user_db: &Database,
+    token: &str,
+) -> Result<(), String> {
+    let uid = link_uid(clock, LinkPurpose::VerifyEmail, token)
+        .ok_or_else(|| String::from("this link is invalid or expired"))?;
+
+    let user = auth_user_db
+        .read_by_uid(uid)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| String::from("this link is invalid or expired"))?;
+
+    if user.email_verified {
+        return Ok(());
+    }
+
+    check_link(
+        clock,
+        LinkPurpose::VerifyEmail,
+        token,
+        uid,
+        &user.password_hash,
+    )
+    .map_err(|e| match e {
+        CodeError::TooManyAttempts => e.to_string(),
+        _ => String::from("this link is invalid or expired"),
+    })?;
+
+    auth_user_db
+        .update_email_verification(uid, true)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    CodeStorage::EmailVerificationCodes.delete_code(&uid.to_string());
+
+    let _ = USER_CACHE.remove(&uid);
+    let _ = USER_ME_CACHE.remove(&uid);
+
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use minijinja::context;
+use serde::Deserialize;
+
+use crate::{
+    cache::init_caches::{USER_CACHE, USER_ME_CACHE},
+    db::auth::auth::{AuthDb, Database},
+    error_response,
+    mailer::{locale::Locale, queue, templates},
+    message_response,
+    state::AppState,
+    token_response,
+};
+
+use super::{
+    links::{self, LinkPurpose},
+    pow::{verify_proof_of_work, ProofOfWork},
+    record_login, send_login_alert,
+    utils::{hardened_auth, magic_link_enabled, CodeError, CodeStorage, TokenHandler},
+};
+
+const MAGIC_LINK_SENT: &str = "if an account exists for this email we sent a sign in link.";
+
+#[post("/magic_link")]
+pub async fn request_magic_link(
+    req: HttpRequest,
+    req_body: String,
+    state: web::Data<AppState>,
+    auth_user_db: Database,
+) -> HttpResponse {
+    #[derive(Debug, Deserialize)]
+    struct MagicLinkRequest {
+        email: String,
+        pow: Option<ProofOfWork>,
+    }
+
+    if !magic_link_enabled() {
+        return error_response!(403, "magic link login is disabled.");
+    }
+
+    let MagicLinkRequest { email, pow } = match serde_json::from_str(&req_body) {
+        Ok(json) => json,
+        Err(e) => return error_response!(400, e.to_string()),
+    };
+
+    match verify_proof_of_work(state.clock.as_ref(), pow.as_ref()) {
+        Ok(()) => (),
+        Err(e) => return error_response!(403, e),
+    }
+
+    let auth_user = match auth_user_db.read_by_email(&email).await {
+        Ok(user) => user,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    let hardened = hardened_auth();
+
+    let user = match auth_user {
+        Some(user) => user,
+        None if hardened => return message_response!(MAGIC_LINK_SENT),
+        None => {
+            return error_response!(
+                404,
+                format!("couldnt find an user associated with the email '{}'", email)
+            )
+        }
+    };
+
+    // same resend cooldown as the verification and reset codes
+    let code = match CodeStorage::MagicLinkCodes.create(state.clock.as_ref(), &user.uid.to_string())
+    {
+        Ok(code) => code,
+        Err(_) if hardened => return message_response!(MAGIC_LINK_SENT),
+        Err(e) => return error_response!(429, e),
+    };
+
+    let locale = Locale::for_user(&state, user.uid, &req).await;
+    let link = links::magic_login_link(user.uid, &user.password_hash, &code);
+
+    match send_magic_link_email(&state, &link, &user.email, locale).await {
+        Ok(()) => (),
+        Err(e) if hardened => println!("failed to queue magic link email: {}", e),
+        Err(e) => return error_response!(500, e.to_string()),
+    }
+
+    if hardened {
+        message_response!(MAGIC_LINK_SENT)
+    } else {
+        message_response!("sign in link sent.")
+    }
+}
+
+async fn send_magic_link_email(
+    state: &AppState,
+    link: &str,
+    email: &str,
+    locale: Locale,
+) -> anyhow::Result<()> {
+    let email = templates::render(email, "magic_link", locale, context! { link })?;
+
+    queue::enqueue(state, email).await
+}
+
+#[post("/magic_link/login")]
+pub async fn magic_link_login(
+    req: HttpRequest,
+    req_body: String,
+    state: web::Data<AppState>,
+    auth_user_db: Database,
+    tokens: TokenHandler,
+) -> HttpResponse {
+    #[derive(Debug, Deserialize)]
+    struct MagicLinkLogin {
+        token: String,
+    }
+
+    if !magic_link_enabled() {
+        return error_response!(403, "magic link login is disabled.");
+    }
+
+    let MagicLinkLogin { token } = match serde_json::from_str(&req_body) {
+        Ok(json) => json,
+        Err(e) => return error_response!(400, e.to_string()),
+    };
+
+    let uid = match links::link_uid(state.clock.as_ref(), LinkPurpose::MagicLogin, &token) {
+        Some(uid) => uid,
+        None => return error_response!(403, "this link is invalid or expired."),
+    };
+
+    let user = match auth_user_db.read_by_uid(uid).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return error_response!(403, "this link is invalid or expired."),
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    match links::check_link(
+        state.clock.as_ref(),
+        LinkPurpose::MagicLogin,
+        &token,
+        uid,
+        &user.password_hash,
+    ) {
+        Ok(()) => (),
+        Err(CodeError::TooManyAttempts) => {
+            return error_response!(403, CodeError::TooManyAttempts.to_string())
+        }
+        Err(_) => return error_response!(403, "this link is invalid or expired."),
+    }
+
+    CodeStorage::MagicLinkCodes.delete_code(&uid.to_string());
+
+    // the link could only be opened from the inbox, so the email is verified now
+    if !user.email_verified {
+        match auth_user_db.update_email_verification(uid, true).await {
+            Ok(()) => (),
+            Err(e) => return error_response!(500, e.to_string()),
+        }
+
+        let _ = USER_CACHE.remove(&uid);
+        let _ = USER_ME_CACHE.remove(&uid);
+    }
+
+    let token = match tokens.generate_token(uid).await {
+        Ok(token) => token,
+        Err(e) => return error_response!(403, e.to_string()),
+    };
+
+    record_login(&auth_user_db, uid).await;
+
+    send_login_alert(&state, &user, &req).await;
+
+    token_response!(token)
+}
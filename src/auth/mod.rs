@@ -1,34 +1,54 @@
-use actix_web::{post, HttpResponse};
+use actix_web::{post, web, HttpRequest, HttpResponse};
 
 use bcrypt::{hash, verify, DEFAULT_COST};
-use lettre::{
-    message::SinglePart, transport::smtp::authentication::Credentials, Message, SmtpTransport,
-    Transport,
-};
 use serde::Deserialize;
 use utils::{
-    generate_uid, validate_email, validate_password, validate_username, CodeStorage, TokenHandler,
-    UsernameOrEmail,
+    dummy_verify, hardened_auth, validate_email, validate_password, validate_username, CodeError,
+    CodeStorage, RegistrationMode, TokenHandler, UsernameOrEmail,
 };
 
 pub mod auth_middleware;
+pub mod links;
+pub mod magic_link;
 pub mod password_reset;
+pub mod pow;
 pub mod utils;
 
 use crate::cache::init_caches::{USER_CACHE, USER_ME_CACHE};
-use crate::{db::auth::auth::Database, secrets::SECRETS};
+use crate::db::auth::auth::AuthUser;
+use crate::db::auth::auth::{AuthDb, Database, NewAccount};
+use crate::mailer::{locale::Locale, notifications::send_notification, queue, templates};
+use crate::models::api::notification_preferences::NotificationCategory;
+use minijinja::context;
+
+use pow::{verify_proof_of_work, ProofOfWork};
+
+use crate::db::api::invites::{InviteDatabase, InviteDb};
+use crate::util::snowflake::generate_uid;
 
-use crate::db::api::users::{UserDatabase, UserDb};
+use crate::{error_response, message_response, state::AppState, token_response};
 
-use crate::{error_response, message_response, token_response};
+const REGISTRATION_RECEIVED: &str =
+    "registration received. check your emails for the next steps, then login to continue.";
 
 #[post("/register")]
-pub async fn register(req_body: String) -> HttpResponse {
+pub async fn register(
+    req: HttpRequest,
+    req_body: String,
+    state: web::Data<AppState>,
+    auth_user_db: Database,
+    invite_db: InviteDatabase,
+    tokens: TokenHandler,
+) -> HttpResponse {
     #[derive(Debug, Deserialize)]
     struct RegisterRequest {
         username: String,
         password: String,
         email: String,
+        invite_code: Option<String>,
+        // stored as the preferred email language, the Accept-Language header is used without it
+        locale: Option<String>,
+        pow: Option<ProofOfWork>,
     }
 
     let json_content: RegisterRequest = match serde_json::from_str(&req_body) {
@@ -36,29 +56,62 @@ pub async fn register(req_body: String) -> HttpResponse {
         Err(e) => return error_response!(400, e.to_string()),
     };
 
-    let auth_user_db = match Database::new().await {
-        Ok(db) => db,
-        Err(e) => return error_response!(500, e.to_string()),
+    let preferred_locale = match json_content.locale.as_deref() {
+        Some(tag) => match Locale::parse(tag) {
+            Some(locale) => Some(locale),
+            None => return error_response!(400, format!("unsupported locale '{}'", tag)),
+        },
+        None => None,
     };
 
-    match auth_user_db.create_table().await {
+    match verify_proof_of_work(state.clock.as_ref(), json_content.pow.as_ref()) {
         Ok(()) => (),
-        Err(e) => return error_response!(500, e.to_string()),
+        Err(e) => return error_response!(403, e),
+    }
+
+    let registration_mode = RegistrationMode::current();
+
+    match registration_mode {
+        RegistrationMode::Open => (),
+        RegistrationMode::Closed => {
+            return error_response!(403, "registration is currently closed.")
+        }
+        RegistrationMode::InviteOnly => {
+            let Some(code) = &json_content.invite_code else {
+                return error_response!(403, "an invite code is required to register.");
+            };
+
+            // checked before the email is looked up, in hardened mode an existing email must not
+            // get past a code a new one would be stopped by. the use is only taken further down
+            match invite_db.is_redeemable(code).await {
+                Ok(true) => (),
+                Ok(false) => {
+                    return error_response!(403, "this invite code is invalid, used up or expired.")
+                }
+                Err(e) => return error_response!(500, e.to_string()),
+            }
+        }
     }
 
-    if let Some(user) = match auth_user_db.read_by_email(&json_content.email).await {
+    let hardened = hardened_auth();
+
+    let existing_user = match auth_user_db.read_by_email(&json_content.email).await {
         Ok(user) => user,
         Err(e) => return error_response!(500, e.to_string()),
-    } {
-        return error_response!(
-            409,
-            format!(
-                "email '{}' is already registered, try to login instead!",
-                user.email
-            )
-        );
     };
 
+    if let Some(user) = &existing_user {
+        if !hardened {
+            return error_response!(
+                409,
+                format!(
+                    "email '{}' is already registered, try to login instead!",
+                    user.email
+                )
+            );
+        }
+    }
+
     if let Some(user) = match auth_user_db.read_by_username(&json_content.username).await {
         Ok(user) => user,
         Err(e) => return error_response!(500, e.to_string()),
@@ -87,36 +140,79 @@ pub async fn register(req_body: String) -> HttpResponse {
     let hashed = hash(&json_content.password, DEFAULT_COST).unwrap();
     let verified = verify(&json_content.password, &hashed).unwrap();
 
+    // hardened mode: the caller gets the same answer as for a new account, the owner of the email gets notified instead
+    if let Some(user) = existing_user {
+        let locale = Locale::for_user(&state, user.uid, &req).await;
+
+        if let Err(e) = send_registration_attempt_email(&state, &user.email, locale).await {
+            println!("failed to queue registration attempt email: {}", e);
+        }
+
+        return message_response!(REGISTRATION_RECEIVED);
+    }
+
     if verified {
         let uid = generate_uid();
 
-        match auth_user_db
-            .insert(uid, &json_content.username, &hashed, &json_content.email)
-            .await
-        {
-            Ok(()) => (),
-            Err(e) => return error_response!(500, e.to_string()),
-        }
+        // invite codes are only consumed in invite only mode, in open mode they are ignored
+        let invite = match (&registration_mode, &json_content.invite_code) {
+            (RegistrationMode::InviteOnly, Some(code)) => match invite_db.redeem(code).await {
+                Ok(Some(invite)) => Some(invite),
+                Ok(None) => {
+                    return error_response!(403, "this invite code is invalid, used up or expired.")
+                }
+                Err(e) => return error_response!(500, e.to_string()),
+            },
+            _ => None,
+        };
 
-        let user_db = match UserDatabase::new().await {
-            Ok(db) => db,
-            Err(e) => return error_response!(500, e.to_string()),
+        let account = NewAccount {
+            uid,
+            username: &json_content.username,
+            email: &json_content.email,
+            password_hash: &hashed,
+            owner: invite.as_ref().and_then(|invite| invite.role.as_deref()) == Some("owner"),
+            locale: preferred_locale.map(|locale| locale.code()),
         };
 
-        match user_db.create_table().await {
+        match auth_user_db.insert(&account).await {
             Ok(()) => (),
-            Err(e) => return error_response!(500, e.to_string()),
+            Err(e) => {
+                if let Some(invite) = &invite {
+                    let _ = invite_db.release(&invite.code).await;
+                }
+                return error_response!(500, e.to_string());
+            }
         }
 
-        match user_db
-            .insert(uid, &json_content.username, &json_content.email)
-            .await
-        {
-            Ok(()) => (),
-            Err(e) => return error_response!(500, e.to_string()),
+        if let Some(invite) = &invite {
+            if let Err(e) = invite_db.record_redemption(uid, invite).await {
+                println!(
+                    "failed to record the invite {} of {}: {}",
+                    invite.code, uid, e
+                );
+            }
+        }
+
+        if hardened {
+            // no token in hardened mode, the user logs in after receiving the verification email
+            if let Ok(code) =
+                CodeStorage::EmailVerificationCodes.create(state.clock.as_ref(), &uid.to_string())
+            {
+                let locale = preferred_locale.unwrap_or_else(|| Locale::from_request(&req));
+
+                let link = links::verify_email_link(uid, &hashed, &code);
+
+                if let Err(e) = send_email(&state, &code, &link, &json_content.email, locale).await
+                {
+                    println!("failed to queue verification email: {}", e);
+                }
+            }
+
+            return message_response!(REGISTRATION_RECEIVED);
         }
 
-        let token = match TokenHandler::new().await.generate_token(uid).await {
+        let token = match tokens.generate_token(uid).await {
             Ok(token) => token,
             Err(e) => return error_response!(403, e.to_string()),
         };
@@ -128,7 +224,13 @@ pub async fn register(req_body: String) -> HttpResponse {
 }
 
 #[post("/login")]
-pub async fn login(req_body: String) -> HttpResponse {
+pub async fn login(
+    req: HttpRequest,
+    req_body: String,
+    state: web::Data<AppState>,
+    auth_user_db: Database,
+    tokens: TokenHandler,
+) -> HttpResponse {
     #[derive(Debug, Deserialize)]
     struct LoginRequest {
         username_or_email: String,
@@ -140,16 +242,6 @@ pub async fn login(req_body: String) -> HttpResponse {
         Err(e) => return error_response!(400, e.to_string()),
     };
 
-    let auth_user_db = match Database::new().await {
-        Ok(db) => db,
-        Err(e) => return error_response!(500, e.to_string()),
-    };
-
-    match auth_user_db.create_table().await {
-        Ok(()) => (),
-        Err(e) => return error_response!(500, e.to_string()),
-    }
-
     let auth_user = match UsernameOrEmail::parse(&json_content.username_or_email) {
         UsernameOrEmail::Email(email) => match auth_user_db.read_by_email(&email).await {
             Ok(user) => user,
@@ -167,52 +259,65 @@ pub async fn login(req_body: String) -> HttpResponse {
         let verify = verify(json_content.password, &user.password_hash).unwrap();
 
         if verify {
-            let token = match TokenHandler::new().await.generate_token(user.uid).await {
+            let token = match tokens.generate_token(user.uid).await {
                 Ok(token) => token,
                 Err(e) => return error_response!(403, e.to_string()),
             };
-            return token_response!(token);
+
+            record_login(&auth_user_db, user.uid).await;
+
+            send_login_alert(&state, &user, &req).await;
+
+            token_response!(token)
         } else {
-            return error_response!(403, "password or username is wrong");
+            error_response!(403, "password or username is wrong")
         }
+    } else if hardened_auth() {
+        dummy_verify(&json_content.password);
+        error_response!(403, "password or username is wrong")
     } else {
-        return error_response!(
+        error_response!(
             404,
             "couldnt find a user associated with this username or email"
-        );
+        )
     }
 }
 
-#[derive(Debug, Deserialize)]
-pub struct Token {
-    pub token: String,
-}
-
 #[post("/send_verification_email")]
-pub async fn send_verifiaction_email(req_body: String) -> HttpResponse {
-    let Token { token } = match serde_json::from_str(&req_body) {
+pub async fn send_verifiaction_email(
+    req: HttpRequest,
+    req_body: String,
+    state: web::Data<AppState>,
+    auth_user_db: Database,
+    tokens: TokenHandler,
+) -> HttpResponse {
+    #[derive(Debug, Deserialize)]
+    struct SendVerificationEmail {
+        token: String,
+        pow: Option<ProofOfWork>,
+    }
+
+    let SendVerificationEmail { token, pow } = match serde_json::from_str(&req_body) {
         Ok(token) => token,
         Err(e) => return error_response!(400, e.to_string()),
     };
 
-    let claims = match TokenHandler::new().await.verify_token(&token).await {
+    match verify_proof_of_work(state.clock.as_ref(), pow.as_ref()) {
+        Ok(()) => (),
+        Err(e) => return error_response!(403, e),
+    }
+
+    let claims = match tokens.verify_token(&token).await {
         Ok(result) => result,
         Err(e) => return error_response!(403, e.to_string()),
     };
 
-    let user_id = claims.user_id.clone();
-
-    let auth_user_db = match Database::new().await {
-        Ok(db) => db,
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
         Err(e) => return error_response!(500, e.to_string()),
     };
 
-    match auth_user_db.create_table().await {
-        Ok(()) => (),
-        Err(e) => return error_response!(500, e.to_string()),
-    }
-
-    let auth_user = match auth_user_db.read_by_uid(user_id.parse().unwrap()).await {
+    let auth_user = match auth_user_db.read_by_uid(user_id).await {
         Ok(user) => user,
         Err(e) => return error_response!(500, e.to_string()),
     };
@@ -224,54 +329,107 @@ pub async fn send_verifiaction_email(req_body: String) -> HttpResponse {
 
                 let result = cache.remove(&user.uid);
 
-                return error_response!(409, "your email is already verified");
+                error_response!(409, "your email is already verified")
             } else {
                 let code_gen = CodeStorage::EmailVerificationCodes;
 
-                let code = match code_gen.create(&user.uid.to_string()) {
+                let code = match code_gen.create(state.clock.as_ref(), &user.uid.to_string()) {
                     Ok(code) => code,
                     Err(e) => return error_response!(502, e.to_string()),
                 };
 
-                match send_email(&code, &user.email) {
+                let locale = Locale::for_user(&state, user.uid, &req).await;
+
+                let link = links::verify_email_link(user.uid, &user.password_hash, &code);
+
+                match send_email(&state, &code, &link, &user.email, locale).await {
                     Ok(()) => {}
-                    Err(e) => return error_response!(502, e.to_string()),
+                    Err(e) => return error_response!(500, e.to_string()),
                 }
-                return message_response!("Verification email sent.");
+                message_response!("Verification email sent.")
             }
         }
-        None => return error_response!(404, "Couldn't find a user associated with this token."),
+        None => error_response!(404, "Couldn't find a user associated with this token."),
     }
 }
 
-const EMAIL_VERIFY_BODY: &str = include_str!("verify_email_body.html");
+async fn send_email(
+    state: &AppState,
+    code: &str,
+    link: &str,
+    email: &str,
+    locale: Locale,
+) -> anyhow::Result<()> {
+    let email = templates::render(email, "verify_email", locale, context! { code, link })?;
 
-fn send_email(code: &str, email: &str) -> anyhow::Result<()> {
-    let body = EMAIL_VERIFY_BODY.replace("{code}", code);
+    queue::enqueue(state, email).await
+}
 
-    let email = Message::builder()
-        .from(SECRETS.get("NO_REPLY_EMAIL").unwrap().parse().unwrap())
-        .to(email.parse().unwrap())
-        .subject("Your Account Verificatio Code for acid4sigmas")
-        .singlepart(SinglePart::html(body))?;
+// a failed update shouldn't fail the login, /api/me just shows the previous login then
+pub async fn record_login(auth_user_db: &Database, uid: i64) {
+    if let Err(e) = auth_user_db.update_last_login(uid).await {
+        println!("failed to record the login of {}: {}", uid, e);
+    }
 
-    let username = SECRETS.get("SMTP_USERNAME").unwrap();
-    let password = SECRETS.get("SMTP_PASSWORD").unwrap();
-    let smtp_relay = SECRETS.get("SMTP_RELAY").unwrap();
+    let _ = USER_ME_CACHE.remove(&uid);
+}
 
-    let creds = Credentials::new(username.clone(), password.clone());
+// optional, users can turn login alerts off in their notification preferences
+pub async fn send_login_alert(state: &AppState, user: &AuthUser, req: &HttpRequest) {
+    let locale = Locale::for_user(state, user.uid, req).await;
+
+    let ip = req
+        .connection_info()
+        .realip_remote_addr()
+        .unwrap_or("unknown")
+        .to_string();
+
+    let user_agent = req
+        .headers()
+        .get("User-Agent")
+        .and_then(|agent| agent.to_str().ok())
+        .unwrap_or("unknown")
+        .to_string();
+
+    let ctx = context! {
+        username => user.username,
+        time => state.clock.now().format("%Y-%m-%d %H:%M UTC").to_string(),
+        ip,
+        user_agent,
+    };
 
-    let mailer = SmtpTransport::relay(&smtp_relay)?
-        .credentials(creds)
-        .build();
+    if let Err(e) = send_notification(
+        state,
+        user.uid,
+        &user.email,
+        NotificationCategory::LoginAlerts,
+        "login_alert",
+        locale,
+        ctx,
+    )
+    .await
+    {
+        println!("failed to queue login alert: {}", e);
+    }
+}
 
-    mailer.send(&email)?;
+async fn send_registration_attempt_email(
+    state: &AppState,
+    email: &str,
+    locale: Locale,
+) -> anyhow::Result<()> {
+    let email = templates::render(email, "registration_attempt", locale, context! {})?;
 
-    Ok(())
+    queue::enqueue(state, email).await
 }
 
 #[post("/verify_email")]
-pub async fn verify_email(req_body: String) -> HttpResponse {
+pub async fn verify_email(
+    req_body: String,
+    state: web::Data<AppState>,
+    auth_user_db: Database,
+    tokens: TokenHandler,
+) -> HttpResponse {
     #[derive(Debug, Deserialize)]
     struct VerifyEmail {
         token: String,
@@ -283,24 +441,17 @@ pub async fn verify_email(req_body: String) -> HttpResponse {
         Err(e) => return error_response!(400, e.to_string()),
     };
 
-    let claims = match TokenHandler::new().await.verify_token(&token).await {
+    let claims = match tokens.verify_token(&token).await {
         Ok(result) => result,
         Err(e) => return error_response!(403, e.to_string()),
     };
 
-    let user_id = claims.user_id.clone();
-
-    let auth_user_db = match Database::new().await {
-        Ok(db) => db,
+    let user_id = match claims.user_id.parse::<i64>() {
+        Ok(uid) => uid,
         Err(e) => return error_response!(500, e.to_string()),
     };
 
-    match auth_user_db.create_table().await {
-        Ok(()) => (),
-        Err(e) => return error_response!(500, e.to_string()),
-    }
-
-    let auth_user = match auth_user_db.read_by_uid(user_id.parse().unwrap()).await {
+    let auth_user = match auth_user_db.read_by_uid(user_id).await {
         Ok(user) => user,
         Err(e) => return error_response!(500, e.to_string()),
     };
@@ -308,11 +459,13 @@ pub async fn verify_email(req_body: String) -> HttpResponse {
     if let Some(user) = auth_user {
         let code_storage = CodeStorage::EmailVerificationCodes;
 
-        let code_ = code_storage.get_code(&user_id);
-
-        if let Some(stored_code) = code_ {
-            if stored_code == code.to_string() {
-                code_storage.delete_code(&user_id);
+        match code_storage.check_code(
+            state.clock.as_ref(),
+            &user.uid.to_string(),
+            &code.to_string(),
+        ) {
+            Ok(()) => {
+                code_storage.delete_code(&user.uid.to_string());
 
                 match auth_user_db.update_email_verification(user.uid, true).await {
                     Ok(()) => (),
@@ -327,20 +480,19 @@ pub async fn verify_email(req_body: String) -> HttpResponse {
 
                 let _ = cache_api.remove(&user.uid);
 
-                let generated_token = match TokenHandler::new().await.generate_token(user.uid).await
-                {
+                let generated_token = match tokens.generate_token(user.uid).await {
                     Ok(token) => token,
                     Err(e) => return error_response!(403, e.to_string()),
                 };
 
-                return token_response!(generated_token);
-            } else {
-                return error_response!(403, "the authentication code is wrong");
+                token_response!(generated_token)
             }
-        } else {
-            return error_response!(409, "no pending verification code outgoing");
+            Err(CodeError::Missing) => {
+                error_response!(409, "no pending verification code outgoing")
+            }
+            Err(e) => error_response!(403, e.to_string()),
         }
     } else {
-        return error_response!(404, "no user associated with this token.");
+        error_response!(404, "no user associated with this token.")
     }
 }
@@ -1,33 +1,48 @@
-use actix_web::{post, HttpResponse};
+use actix_web::{post, web, HttpRequest, HttpResponse};
 
 use bcrypt::{hash, DEFAULT_COST};
-use lettre::{message::SinglePart, transport::smtp::authentication::Credentials, Message, SmtpTransport, Transport};
+use minijinja::context;
 use serde::Deserialize;
 use serde_json::json;
 
-use crate::{auth::utils::{validate_password, CodeStorage, TokenHandler}, cache::init_caches::USER_CACHE, db::auth::auth::Database, error::ActixError, secrets::SECRETS};
+use crate::{auth::{pow::{verify_proof_of_work, ProofOfWork}, links::{self, LinkPurpose}, utils::{hardened_auth, validate_password, CodeError, CodeStorage, TokenHandler}}, cache::init_caches::USER_CACHE, db::auth::auth::{AuthDb, Database}, error::ActixError, mailer::{locale::Locale, queue, templates}, state::AppState};
 
 
 
 #[post("/request_reset_password")]
-pub async fn request_reset_password(req_body: String) -> Result<HttpResponse, ActixError> {
+pub async fn request_reset_password(req: HttpRequest, req_body: String, state: web::Data<AppState>, auth_user_db: Database) -> Result<HttpResponse, ActixError> {
     #[derive(Debug, Deserialize)]
     struct Email {
-        email: String
+        email: String,
+        pow: Option<ProofOfWork>
     }
 
-    let Email { email } = serde_json::from_str(&req_body)
+    let Email { email, pow } = serde_json::from_str(&req_body)
         .map_err(|e| ActixError::JsonError(e.to_string()))?;
 
-    let auth_user_db = Database::new().await
-        .map_err(|e| ActixError::DatabaseError(e.to_string()))?;
-    
-    auth_user_db.create_table().await
-        .map_err(|e| ActixError::DatabaseError(e.to_string()))?;
+    verify_proof_of_work(state.clock.as_ref(), pow.as_ref())
+        .map_err(ActixError::ChallengeError)?;
 
     let auth_user = auth_user_db.read_by_email(&email).await
         .map_err(|e| ActixError::DatabaseError(e.to_string()))?;
 
+    if hardened_auth() {
+        // same answer no matter if the account exists or is verified
+        if let Some(user) = auth_user.filter(|user| user.email_verified) {
+            if let Ok(code) = CodeStorage::PasswordResetCodes.create(state.clock.as_ref(), &user.uid.to_string()) {
+                let locale = Locale::for_user(&state, user.uid, &req).await;
+
+                let link = links::reset_password_link(user.uid, &user.email, &user.password_hash, &code);
+
+                if let Err(e) = send_password_reset_code_email(&state, &code, &link, &user.email, locale).await {
+                    println!("failed to queue password reset email: {}", e);
+                }
+            }
+        }
+
+        return Ok(HttpResponse::Ok().json(json!({"message": "if an account exists for this email we sent a password reset email."})));
+    }
+
     if let Some(user) = auth_user {
 
         if !user.email_verified {
@@ -35,12 +50,16 @@ pub async fn request_reset_password(req_body: String) -> Result<HttpResponse, Ac
         }
 
         let generate_code = CodeStorage::PasswordResetCodes;
-        let code = generate_code.create(&user.uid.to_string())
+        let code = generate_code.create(state.clock.as_ref(), &user.uid.to_string())
             .map_err(|e| ActixError::CodeGenError(e.to_string()))?;
 
         println!("code: {}", code);
 
-        match send_password_reset_code_email(&code, &user.email) {
+        let locale = Locale::for_user(&state, user.uid, &req).await;
+
+        let link = links::reset_password_link(user.uid, &user.email, &user.password_hash, &code);
+
+        match send_password_reset_code_email(&state, &code, &link, &user.email, locale).await {
             Ok(()) => {},
             Err(e) => return Ok(HttpResponse::InternalServerError().json(json!({"error": e.to_string()})))
         }
@@ -52,63 +71,58 @@ pub async fn request_reset_password(req_body: String) -> Result<HttpResponse, Ac
 
 }
 
-const EMAIL_RESET_PASSWORD_BODY: &str = include_str!("password_reset_body.html");
-
-fn send_password_reset_code_email(code: &str, email: &str) -> anyhow::Result<()> {
-    let body = EMAIL_RESET_PASSWORD_BODY.replace("{code}", code);
-
-    let email = Message::builder()
-        .from(SECRETS.get("NO_REPLY_EMAIL").unwrap().parse().unwrap())
-        .to(email.parse().unwrap())
-        .subject("Your Password Change request code for acid4sigmas")
-        .singlepart(SinglePart::html(body))?;
-
-    let username = SECRETS.get("SMTP_USERNAME").unwrap();
-    let password = SECRETS.get("SMTP_PASSWORD").unwrap();
-    let smtp_relay = SECRETS.get("SMTP_RELAY").unwrap();
-    
-    let creds = Credentials::new(username.clone(), password.clone());
-    
-    let mailer = SmtpTransport::relay(&smtp_relay)?
-        .credentials(creds)
-        .build();
-    
-    mailer.send(&email)?;
-
-    Ok(())
+async fn send_password_reset_code_email(state: &AppState, code: &str, link: &str, email: &str, locale: Locale) -> anyhow::Result<()> {
+    let email = templates::render(email, "password_reset", locale, context! { code, link })?;
+
+    queue::enqueue(state, email).await
 } 
 
 
 
+const HARDENED_RESET_ERROR: &str = "the authentication code is wrong or expired";
+
 #[post("/reset_password")]
-pub async fn reset_password(req_body: String) -> Result<HttpResponse, ActixError> {
+pub async fn reset_password(req: HttpRequest, req_body: String, state: web::Data<AppState>, auth_user_db: Database, tokens: TokenHandler) -> Result<HttpResponse, ActixError> {
 
     #[derive(Debug, Deserialize)]
     struct ResetPassword {
         email: String,
-        code: u64,
+        // either the code or the token from the link in the password reset email
+        code: Option<u64>,
+        token: Option<String>,
         new_password: String
     }
 
-    let ResetPassword { email, code, new_password } = serde_json::from_str(&req_body)
+    enum Proof {
+        Code(u64),
+        Token(String)
+    }
+
+    let ResetPassword { email, code, token, new_password } = serde_json::from_str(&req_body)
         .map_err(|e| ActixError::JsonError(e.to_string()))?;
 
-    let auth_user_db = Database::new().await
-        .map_err(|e| ActixError::DatabaseError(e.to_string()))?;
-    
-    auth_user_db.create_table().await
-        .map_err(|e| ActixError::DatabaseError(e.to_string()))?;
+    // checked before the account is looked up so the answer is the same for every email
+    let proof = match (code, token) {
+        (_, Some(token)) => Proof::Token(token),
+        (Some(code), None) => Proof::Code(code),
+        (None, None) => return Ok(HttpResponse::BadRequest().json(json!({"error": "either the code or the token of the reset link is required."})))
+    };
 
     let auth_user = auth_user_db.read_by_email(&email).await
         .map_err(|e| ActixError::DatabaseError(e.to_string()))?;
 
+    let hardened = hardened_auth();
+
     if let Some(user) = auth_user {
         let code_storage = CodeStorage::PasswordResetCodes;
 
-        let code_s = code_storage.get_code(&user.uid.to_string());
+        let check = match &proof {
+            Proof::Token(token) => links::check_link(state.clock.as_ref(), LinkPurpose::ResetPassword, token, user.uid, &user.password_hash),
+            Proof::Code(code) => code_storage.check_code(state.clock.as_ref(), &user.uid.to_string(), &code.to_string())
+        };
 
-        if let Some(stored_code) = code_s {
-            if stored_code == code.to_string() {
+        match check {
+            Ok(()) => {
 
                 match validate_password(&new_password) {
                     Ok(()) => (),
@@ -126,49 +140,38 @@ pub async fn reset_password(req_body: String) -> Result<HttpResponse, ActixError
 
                 let _ = cache.remove(&user.uid);
 
-                TokenHandler::new().await.destroy_all_tokens(user.uid).await.unwrap();
+                tokens.destroy_all_tokens(user.uid).await
+                    .map_err(|e| ActixError::DatabaseError(e.to_string()))?;
+
+                let locale = Locale::for_user(&state, user.uid, &req).await;
 
-                match send_password_changed_email(&user.email) {
+                match send_password_changed_email(&state, &user.email, locale).await {
                     Ok(()) => {},
                     Err(e) => return Ok(HttpResponse::InternalServerError().json(json!({"error": e.to_string()})))
                 }
 
-                return Ok(HttpResponse::Ok().json(json!({"message": "changed password successfully."})));
-            } else {
-                return Ok(HttpResponse::Unauthorized().json(json!({"erorr": "the authentication code is wrong"})));
+                Ok(HttpResponse::Ok().json(json!({"message": "changed password successfully."})))
+            },
+            Err(_) if hardened => {
+                Ok(HttpResponse::Unauthorized().json(json!({"error": HARDENED_RESET_ERROR})))
+            },
+            Err(CodeError::Missing) => {
+                Ok(HttpResponse::Conflict().json(json!({"error": "no pending verification code outgoing."})))
+            },
+            Err(e) => {
+                Ok(HttpResponse::Unauthorized().json(json!({"erorr": e.to_string()})))
             }
-        } else {
-            return Ok(HttpResponse::Conflict().json(json!({"error": "no pending verification code outgoing."})));
         }
+    } else if hardened {
+        Ok(HttpResponse::Unauthorized().json(json!({"error": HARDENED_RESET_ERROR})))
     } else {
-        return Ok(HttpResponse::NotFound().json(json!({"error": "no user associated with this email."})));
+        Ok(HttpResponse::NotFound().json(json!({"error": "no user associated with this email."})))
     }
 
 }
 
-const EMAIL_PASSWORD_CHANGED_BODY: &str = include_str!("password_changed_body.html");
-
-fn send_password_changed_email(email: &str) -> anyhow::Result<()> {
-
-    let body = EMAIL_PASSWORD_CHANGED_BODY.to_string();
-
-    let email = Message::builder()
-        .from(SECRETS.get("NO_REPLY_EMAIL").unwrap().parse().unwrap())
-        .to(email.parse().unwrap())
-        .subject("Your Password Change request code for acid4sigmas")
-        .singlepart(SinglePart::html(body))?;
-
-    let username = SECRETS.get("SMTP_USERNAME").unwrap();
-    let password = SECRETS.get("SMTP_PASSWORD").unwrap();
-    let smtp_relay = SECRETS.get("SMTP_RELAY").unwrap();
-    
-    let creds = Credentials::new(username.clone(), password.clone());
-    
-    let mailer = SmtpTransport::relay(&smtp_relay)?
-        .credentials(creds)
-        .build();
-    
-    mailer.send(&email)?;
+async fn send_password_changed_email(state: &AppState, email: &str, locale: Locale) -> anyhow::Result<()> {
+    let email = templates::render(email, "password_changed", locale, context! {})?;
 
-    Ok(())
+    queue::enqueue(state, email).await
 } 
@@ -0,0 +1,148 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use actix_web::{get, web, HttpResponse};
+use chrono::Duration;
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::{
+    error_response,
+    secrets::{secret, SECRETS},
+    state::AppState,
+    util::clock::Clock,
+};
+
+// a hashcash like proof of work. the client fetches a signed challenge and has to find a
+// solution so that sha256("{challenge}:{solution}") starts with `difficulty` zero bits.
+
+// off until an operator turns it on, clients which don't solve challenges keep working
+const DEFAULT_DIFFICULTY: u32 = 0;
+const MAX_DIFFICULTY: u32 = 32;
+const CHALLENGE_LIFETIME_SECS: i64 = 300;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChallengeClaims {
+    nonce: String,
+    difficulty: u32,
+    exp: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProofOfWork {
+    pub challenge: String,
+    pub solution: String,
+}
+
+// nonces of already solved challenges together with their expiry, so every challenge can only be used once
+static USED_CHALLENGES: Lazy<Mutex<HashMap<String, usize>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn get_secret_key() -> Vec<u8> {
+    SECRETS
+        .get("SECRET_KEY")
+        .expect("SECRET_KEY not found")
+        .as_bytes()
+        .to_vec()
+}
+
+// a difficulty of 0 disables the proof of work
+pub fn difficulty() -> u32 {
+    secret("POW_DIFFICULTY")
+        .and_then(|difficulty| difficulty.parse().ok())
+        .unwrap_or(DEFAULT_DIFFICULTY)
+        .min(MAX_DIFFICULTY)
+}
+
+fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+
+    for byte in hash {
+        if *byte == 0 {
+            bits += 8;
+        } else {
+            bits += byte.leading_zeros();
+            break;
+        }
+    }
+
+    bits
+}
+
+pub fn verify_proof_of_work(clock: &dyn Clock, pow: Option<&ProofOfWork>) -> Result<(), String> {
+    if difficulty() == 0 {
+        return Ok(());
+    }
+
+    let pow = match pow {
+        Some(pow) => pow,
+        None => {
+            return Err(String::from(
+                "a solved challenge from /auth/challenge is required for this request",
+            ))
+        }
+    };
+
+    // the expiry is checked against the clock below instead
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.validate_exp = false;
+
+    let now = clock.now().timestamp() as usize;
+
+    let claims = match decode::<ChallengeClaims>(
+        &pow.challenge,
+        &DecodingKey::from_secret(&get_secret_key()),
+        &validation,
+    ) {
+        Ok(data) if data.claims.exp >= now => data.claims,
+        _ => return Err(String::from("the challenge is invalid or expired")),
+    };
+
+    let hash = Sha256::digest(format!("{}:{}", pow.challenge, pow.solution).as_bytes());
+
+    if leading_zero_bits(&hash) < claims.difficulty {
+        return Err(String::from("the challenge solution is wrong"));
+    }
+
+    let mut used = USED_CHALLENGES.lock().unwrap();
+
+    used.retain(|_, exp| *exp > now);
+
+    if used.contains_key(&claims.nonce) {
+        return Err(String::from("this challenge was already used"));
+    }
+
+    used.insert(claims.nonce, claims.exp);
+
+    Ok(())
+}
+
+#[get("/challenge")]
+pub async fn challenge(state: web::Data<AppState>) -> HttpResponse {
+    let difficulty = difficulty();
+    let expiration = state.clock.now() + Duration::seconds(CHALLENGE_LIFETIME_SECS);
+
+    let claims = ChallengeClaims {
+        nonce: Uuid::new_v4().to_string(),
+        difficulty,
+        exp: expiration.timestamp() as usize,
+    };
+
+    let challenge = match encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(&get_secret_key()),
+    ) {
+        Ok(challenge) => challenge,
+        Err(e) => return error_response!(500, e.to_string()),
+    };
+
+    HttpResponse::Ok().json(json!({
+        "challenge": challenge,
+        "difficulty": difficulty,
+        "expires_at": claims.exp
+    }))
+}
@@ -1,5 +1,6 @@
 use anyhow::anyhow;
-use chrono::{Duration as ChronoDuration, Utc};
+use bcrypt::{hash, verify, DEFAULT_COST};
+use chrono::Duration as ChronoDuration;
 use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use once_cell::sync::Lazy;
 use rand::Rng;
@@ -7,60 +8,127 @@ use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
-    sync::Mutex,
-    time::{Duration, SystemTime, UNIX_EPOCH},
+    sync::{Arc, Mutex},
 };
 use uuid::Uuid;
 
-use crate::secrets::SECRETS;
+use crate::secrets::{secret, SECRETS};
+use crate::util::clock::Clock;
 
-use crate::db::auth::tokens::Database as TokenCheckDatabase;
+use crate::db::auth::tokens::{Database as TokenCheckDatabase, TokenDb};
 
-pub fn generate_uid() -> i64 {
-    let epoch = 1_704_037_200_000;
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_millis();
-    let timestamp = now - epoch;
+#[derive(Debug, PartialEq)]
+pub enum RegistrationMode {
+    Open,
+    InviteOnly,
+    Closed,
+}
 
-    let timestamp_part = (timestamp & 0x3FFFFFFFFFF) << 22;
+impl RegistrationMode {
+    pub fn current() -> Self {
+        match secret("REGISTRATION_MODE").as_deref() {
+            Some("invite_only") => RegistrationMode::InviteOnly,
+            Some("closed") => RegistrationMode::Closed,
+            _ => RegistrationMode::Open,
+        }
+    }
+}
 
-    let machine_id = rand::thread_rng().gen_range(0..1024);
-    let machine_id_part = (machine_id & 0x3FF) << 12;
+// in hardened mode the auth endpoints answer the same way whether an account exists or not
+pub fn hardened_auth() -> bool {
+    secret("HARDENED_AUTH")
+        .map(|hardened| hardened == "true")
+        .unwrap_or(false)
+}
+
+// owners can turn the passwordless login off with MAGIC_LINK_LOGIN = false
+pub fn magic_link_enabled() -> bool {
+    secret("MAGIC_LINK_LOGIN")
+        .map(|enabled| enabled == "true")
+        .unwrap_or(true)
+}
 
-    let sequence = rand::thread_rng().gen_range(0..4096);
+static DUMMY_PASSWORD_HASH: Lazy<String> =
+    Lazy::new(|| hash("dummy password for timing", DEFAULT_COST).unwrap());
 
-    let uid = timestamp_part | machine_id_part | sequence;
-    uid as i64
+// burns the same time as checking a real password, so a missing user can't be told apart by timing
+pub fn dummy_verify(password: &str) {
+    let _ = verify(password, &DUMMY_PASSWORD_HASH);
 }
 
-pub static PASSWORD_RESET_CODE_STORE: Lazy<Mutex<HashMap<String, (String, f64)>>> =
-    Lazy::new(|| Mutex::new(HashMap::new()));
+// how long a code (and the link sent with it) is valid, and how long to wait before a new one can be requested
+const CODE_LIFETIME_SECS: u64 = 600;
+const CODE_RESEND_COOLDOWN_SECS: f64 = 60.0;
+// wrong codes and links a pending code survives, after that a new one has to be requested
+const MAX_CODE_ATTEMPTS: u32 = 5;
 
-pub static EMAIL_VERIFICATION_CODE_STORE: Lazy<Mutex<HashMap<String, (String, f64)>>> =
-    Lazy::new(|| Mutex::new(HashMap::new()));
+pub struct PendingCode {
+    code: String,
+    expires_at: f64,
+    attempts: u32,
+}
+
+type CodeStore = Lazy<Mutex<HashMap<String, PendingCode>>>;
+
+pub static PASSWORD_RESET_CODE_STORE: CodeStore = Lazy::new(|| Mutex::new(HashMap::new()));
 
+pub static EMAIL_VERIFICATION_CODE_STORE: CodeStore = Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub static MAGIC_LINK_CODE_STORE: CodeStore = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, PartialEq)]
+pub enum CodeError {
+    Missing,
+    Wrong,
+    TooManyAttempts,
+}
+
+impl std::fmt::Display for CodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodeError::Missing => write!(f, "no pending verification code outgoing"),
+            CodeError::Wrong => write!(f, "the authentication code is wrong"),
+            CodeError::TooManyAttempts => write!(
+                f,
+                "too many wrong attempts, please request a new verification code"
+            ),
+        }
+    }
+}
+
+// every storage keeps codes, the postfix says so at the call sites
+#[allow(clippy::enum_variant_names)]
 pub enum CodeStorage {
     EmailVerificationCodes,
     PasswordResetCodes,
+    // never shown to the user, only the magic link carries (a digest of) it
+    MagicLinkCodes,
+}
+
+// the pending codes keep their expiry as a unix timestamp
+fn current_time(clock: &dyn Clock) -> f64 {
+    clock.now().timestamp() as f64
 }
 
 impl CodeStorage {
-    fn get_store(&self) -> &Lazy<Mutex<HashMap<String, (String, f64)>>> {
+    fn get_store(&self) -> &CodeStore {
         match self {
             CodeStorage::EmailVerificationCodes => &EMAIL_VERIFICATION_CODE_STORE,
             CodeStorage::PasswordResetCodes => &PASSWORD_RESET_CODE_STORE,
+            CodeStorage::MagicLinkCodes => &MAGIC_LINK_CODE_STORE,
         }
     }
 
-    fn insert_code(&self, user_id: &str, code: String) {
-        let expires_at =
-            SystemTime::now().duration_since(UNIX_EPOCH).unwrap() + Duration::new(600, 0);
-        self.get_store()
-            .lock()
-            .unwrap()
-            .insert(user_id.to_string(), (code, expires_at.as_secs() as f64));
+    fn insert_code(&self, clock: &dyn Clock, user_id: &str, code: String) {
+        let expires_at = current_time(clock) + CODE_LIFETIME_SECS as f64;
+        self.get_store().lock().unwrap().insert(
+            user_id.to_string(),
+            PendingCode {
+                code,
+                expires_at,
+                attempts: 0,
+            },
+        );
     }
 
     fn generate_verification_code(&self) -> String {
@@ -73,27 +141,25 @@ impl CodeStorage {
         store.contains_key(user_id)
     }
 
-    pub fn get_retry_time(&self, user_id: &str) -> Option<f64> {
+    pub fn get_retry_time(&self, clock: &dyn Clock, user_id: &str) -> Option<f64> {
         let store = self.get_store().lock().unwrap();
-        if let Some((_, expires_at)) = store.get(user_id) {
-            let current_time: f64 = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs() as f64;
-            let remaining_time = expires_at - current_time;
-            if remaining_time < 540.0 {
+        if let Some(pending) = store.get(user_id) {
+            let remaining_time = pending.expires_at - current_time(clock);
+            let cooldown_left =
+                remaining_time - (CODE_LIFETIME_SECS as f64 - CODE_RESEND_COOLDOWN_SECS);
+            if cooldown_left <= 0.0 {
                 None
             } else {
-                Some(remaining_time - 540.0)
+                Some(cooldown_left)
             }
         } else {
             None
         }
     }
 
-    pub fn create(&self, user_id: &str) -> Result<String, String> {
+    pub fn create(&self, clock: &dyn Clock, user_id: &str) -> Result<String, String> {
         if self.has_pending_code(user_id) {
-            if let Some(retry_time) = self.get_retry_time(user_id) {
+            if let Some(retry_time) = self.get_retry_time(clock, user_id) {
                 return Err(format!(
                     "Please wait {:?} seconds before requesting a new code",
                     retry_time
@@ -102,31 +168,92 @@ impl CodeStorage {
         }
 
         let code = self.generate_verification_code();
-        self.insert_code(user_id, code.clone());
+        self.insert_code(clock, user_id, code.clone());
         Ok(code)
     }
 
-    pub fn get_code(&self, user_id: &str) -> Option<String> {
+    // unix timestamp the pending code expires at, links sent with the code expire at the same time
+    pub fn expires_at(&self, user_id: &str) -> Option<usize> {
+        let store = self.get_store().lock().unwrap();
+        store
+            .get(user_id)
+            .map(|pending| pending.expires_at as usize)
+    }
+
+    // checks the code without consuming it, call `delete_code` once the action succeeded.
+    // every wrong code counts as an attempt, too many of them drop the pending code
+    pub fn check_code(
+        &self,
+        clock: &dyn Clock,
+        user_id: &str,
+        code: &str,
+    ) -> Result<(), CodeError> {
+        self.check(clock, user_id, |stored| stored == code)
+    }
+
+    // same as `check_code` for the links, which only carry a digest of the code
+    pub fn check_code_digest(
+        &self,
+        clock: &dyn Clock,
+        user_id: &str,
+        digest: &str,
+    ) -> Result<(), CodeError> {
+        self.check(clock, user_id, |stored| {
+            super::links::digest(stored) == digest
+        })
+    }
+
+    fn check(
+        &self,
+        clock: &dyn Clock,
+        user_id: &str,
+        matches: impl Fn(&str) -> bool,
+    ) -> Result<(), CodeError> {
         let mut store = self.get_store().lock().unwrap();
-        if let Some((code, expires_at)) = store.get(user_id) {
-            let current_time = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs() as f64;
-            if current_time <= *expires_at {
-                Some(code.clone())
-            } else {
-                store.remove(user_id);
-                None
-            }
-        } else {
-            None
+
+        let pending = match store.get_mut(user_id) {
+            Some(pending) => pending,
+            None => return Err(CodeError::Missing),
+        };
+
+        if current_time(clock) > pending.expires_at {
+            store.remove(user_id);
+            return Err(CodeError::Missing);
+        }
+
+        if matches(&pending.code) {
+            return Ok(());
         }
+
+        pending.attempts += 1;
+
+        if pending.attempts >= MAX_CODE_ATTEMPTS {
+            store.remove(user_id);
+            return Err(CodeError::TooManyAttempts);
+        }
+
+        Err(CodeError::Wrong)
+    }
+
+    // counts a failed attempt which wasn't a wrong code, e.g. a link for an outdated password
+    pub fn record_failed_attempt(&self, clock: &dyn Clock, user_id: &str) -> CodeError {
+        self.check(clock, user_id, |_| false).unwrap_err()
     }
 
     pub fn delete_code(&self, user_id: &str) {
         self.get_store().lock().unwrap().remove(user_id);
     }
+
+    // removes every expired code and returns how many were removed
+    pub fn prune_expired(&self, clock: &dyn Clock) -> usize {
+        let current_time = current_time(clock);
+
+        let mut store = self.get_store().lock().unwrap();
+        let before = store.len();
+        store.retain(|_, pending| current_time <= pending.expires_at);
+
+        before - store.len()
+    }
 }
 
 pub fn validate_password(password: &str) -> Result<(), String> {
@@ -195,7 +322,7 @@ pub fn validate_email(email: &str) -> Result<(), String> {
     let parts: Vec<String> = email.split('@').map(String::from).collect();
 
     if parts.len() != 2 {
-        return Err(String::from("does not contain the @"));
+        Err(String::from("does not contain the @"))
     } else {
         let local_part = parts[0].clone();
 
@@ -221,7 +348,7 @@ pub fn validate_email(email: &str) -> Result<(), String> {
             ));
         }
 
-        return Ok(());
+        Ok(())
     }
 }
 
@@ -253,6 +380,7 @@ pub struct Claims {
 pub struct TokenHandler {
     secret_key: Vec<u8>,
     db: TokenCheckDatabase,
+    clock: Arc<dyn Clock>,
 }
 
 impl TokenHandler {
@@ -264,17 +392,16 @@ impl TokenHandler {
             .to_vec()
     }
 
-    pub async fn new() -> Self {
-        let db = TokenCheckDatabase::new().await.unwrap();
-        db.create_table().await.unwrap();
+    pub fn new(db: TokenCheckDatabase, clock: Arc<dyn Clock>) -> Self {
         TokenHandler {
             secret_key: Self::get_secret_key(),
             db,
+            clock,
         }
     }
 
     pub async fn generate_token(&self, user_id: i64) -> anyhow::Result<String> {
-        let expiration = Utc::now() + ChronoDuration::days(365);
+        let expiration = self.clock.now() + ChronoDuration::days(365);
 
         let claims = Claims {
             user_id: user_id.to_string(),
@@ -283,7 +410,6 @@ impl TokenHandler {
         };
         let db = &self.db;
 
-        db.create_table().await?;
         db.insert(user_id, &claims.jti, expiration).await?;
 
         let token = encode(
@@ -297,7 +423,9 @@ impl TokenHandler {
     }
 
     pub async fn verify_token(&self, token: &str) -> anyhow::Result<Claims> {
-        let validation = Validation::new(Algorithm::HS256);
+        // the expiry is checked against the clock below instead
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.validate_exp = false;
 
         let db = &self.db;
 
@@ -316,8 +444,7 @@ impl TokenHandler {
                             .parse()
                             .map_err(|_| anyhow!("Invalid user ID in claims"))?,
                     )
-                    .await
-                    .unwrap();
+                    .await?;
 
                 let mut is_jti_valid = false;
 
@@ -328,7 +455,7 @@ impl TokenHandler {
                     }
                 }
 
-                if claims.exp < Utc::now().timestamp() as usize {
+                if claims.exp < self.clock.now().timestamp() as usize {
                     return Err(anyhow!("your token is expired"));
                 }
 
@@ -344,7 +471,6 @@ impl TokenHandler {
 
     pub async fn destroy_all_tokens(&self, user_id: i64) -> anyhow::Result<()> {
         let db = &self.db;
-        db.create_table().await?;
         db.delete_by_uid(user_id).await?;
 
         Ok(())
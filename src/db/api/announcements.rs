@@ -0,0 +1,188 @@
+use anyhow::{anyhow, Result};
+use sqlx::{postgres::PgRow, PgPool, Row};
+
+use crate::db::memory::announcements::MemoryAnnouncementDatabase;
+use crate::db::sqlite::announcements::SqliteAnnouncementDatabase;
+use crate::models::api::announcements::{Announcement, Audience, Recipient};
+use crate::util::snowflake::generate_uid;
+
+pub trait AnnouncementDb {
+    async fn insert(
+        &self,
+        created_by: i64,
+        subject: &str,
+        body: &str,
+        audience: Audience,
+        send_email: bool,
+    ) -> Result<Announcement>;
+    async fn read_all(&self, limit: i64) -> Result<Vec<Announcement>>;
+    async fn read_pending_emails(&self) -> Result<Vec<Announcement>>;
+    async fn read_recipients(
+        &self,
+        audience: Audience,
+        after_uid: i64,
+        limit: i64,
+    ) -> Result<Vec<Recipient>>;
+    async fn update_email_progress(&self, id: i64, cursor: i64, done: bool) -> Result<()>;
+}
+
+repository! {
+    AnnouncementDatabase: AnnouncementDb {
+        postgres: PgAnnouncementDatabase,
+        sqlite: SqliteAnnouncementDatabase,
+        memory: MemoryAnnouncementDatabase,
+        fn insert(
+            &self,
+            created_by: i64,
+            subject: &str,
+            body: &str,
+            audience: Audience,
+            send_email: bool,
+        ) -> Result<Announcement>;
+        #[retry]
+        fn read_all(&self, limit: i64) -> Result<Vec<Announcement>>;
+        #[retry]
+        fn read_pending_emails(&self) -> Result<Vec<Announcement>>;
+        #[retry]
+        fn read_recipients(
+            &self,
+            audience: Audience,
+            after_uid: i64,
+            limit: i64,
+        ) -> Result<Vec<Recipient>>;
+        fn update_email_progress(&self, id: i64, cursor: i64, done: bool) -> Result<()>;
+    }
+}
+
+pub struct PgAnnouncementDatabase {
+    pub pool: PgPool,
+}
+
+// the users an audience consists of, as condition on `accounts u`
+pub fn audience_filter(audience: Audience) -> &'static str {
+    match audience {
+        Audience::All => "u.email_verified = TRUE",
+        Audience::Owners => "u.email_verified = TRUE AND u.owner = TRUE",
+        Audience::Cloudthemes => {
+            "u.email_verified = TRUE AND EXISTS (
+                SELECT 1 FROM cloudthemes_status s WHERE s.uid = u.uid AND s.enabled = TRUE
+            )"
+        }
+    }
+}
+
+impl PgAnnouncementDatabase {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl AnnouncementDb for PgAnnouncementDatabase {
+    async fn insert(
+        &self,
+        created_by: i64,
+        subject: &str,
+        body: &str,
+        audience: Audience,
+        send_email: bool,
+    ) -> Result<Announcement> {
+        let row = sqlx::query(
+            "INSERT INTO announcements (
+                id,
+                created_by,
+                subject,
+                body,
+                audience,
+                send_email,
+                email_done
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING *",
+        )
+        .bind(generate_uid())
+        .bind(created_by)
+        .bind(subject)
+        .bind(body)
+        .bind(audience.as_str())
+        .bind(send_email)
+        // nothing to send, nothing to wait for
+        .bind(!send_email)
+        .fetch_one(&self.pool)
+        .await?;
+
+        parse_announcement_record(row)
+    }
+
+    async fn read_all(&self, limit: i64) -> Result<Vec<Announcement>> {
+        let rows = sqlx::query("SELECT * FROM announcements ORDER BY id DESC LIMIT $1")
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter().map(parse_announcement_record).collect()
+    }
+
+    async fn read_pending_emails(&self) -> Result<Vec<Announcement>> {
+        let rows = sqlx::query("SELECT * FROM announcements WHERE email_done = FALSE ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter().map(parse_announcement_record).collect()
+    }
+
+    async fn read_recipients(
+        &self,
+        audience: Audience,
+        after_uid: i64,
+        limit: i64,
+    ) -> Result<Vec<Recipient>> {
+        let rows = sqlx::query(&format!(
+            "SELECT u.uid, u.email, u.username FROM accounts u
+            WHERE {} AND u.uid > $1
+            ORDER BY u.uid
+            LIMIT $2",
+            audience_filter(audience)
+        ))
+        .bind(after_uid)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(Recipient {
+                    uid: row.try_get(0)?,
+                    email: row.try_get(1)?,
+                    username: row.try_get(2)?,
+                })
+            })
+            .collect()
+    }
+
+    async fn update_email_progress(&self, id: i64, cursor: i64, done: bool) -> Result<()> {
+        sqlx::query("UPDATE announcements SET email_cursor = $1, email_done = $2 WHERE id = $3")
+            .bind(cursor)
+            .bind(done)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+fn parse_announcement_record(row: PgRow) -> Result<Announcement> {
+    let audience: String = row.try_get(4)?;
+
+    Ok(Announcement {
+        id: row.try_get(0)?,
+        created_by: row.try_get(1)?,
+        subject: row.try_get(2)?,
+        body: row.try_get(3)?,
+        audience: Audience::parse(&audience)
+            .ok_or_else(|| anyhow!("unknown audience '{}'", audience))?,
+        send_email: row.try_get(5)?,
+        email_cursor: row.try_get(6)?,
+        email_done: row.try_get(7)?,
+        created_at: row.try_get(8)?,
+    })
+}
@@ -1,90 +1,147 @@
 use anyhow::Result;
-use serde::{Deserialize, Serialize};
 use sqlx::{postgres::PgRow, PgPool, Row};
+use thiserror::Error;
 
-use crate::db::Database;
+use crate::db::memory::cloudthemes::MemoryCloudThemeDatabase;
+use crate::db::sqlite::cloudthemes::SqliteCloudThemeDatabase;
 use crate::models::api::cloudtheme::{CloudTheme, Theme};
-use crate::secrets::SECRETS;
+use crate::util::snowflake::generate_uid;
 
-pub trait CloudThemeDb {
-    async fn new() -> Result<Self>
-    where
-        Self: Sized;
-    async fn create_table(&self) -> Result<()>;
-    async fn insert(&self, uid: i64, theme: Theme) -> Result<()>;
-    async fn read_by_uid(&self, uid: i64) -> Result<Option<CloudTheme>>;
+// why insert or rename refused, checked where the write happens so two requests at the same
+// time can't both get past it
+#[derive(Debug, Error)]
+pub enum CloudThemeConflict {
+    #[error("you already have a theme called {0}")]
+    NameTaken(String),
+    #[error("you can keep at most {0} themes")]
+    TooManyThemes(usize),
 }
 
-pub struct CloudThemeDatabase {
-    pub pool: PgPool,
+pub trait CloudThemeDb {
+    // an active theme replaces the one which was active so far. fails with a CloudThemeConflict
+    // if the user has `limit` themes already or one with the same name
+    async fn insert(
+        &self,
+        uid: i64,
+        name: &str,
+        theme: &Theme,
+        active: bool,
+        limit: usize,
+    ) -> Result<CloudTheme>;
+    async fn read_active(&self, uid: i64) -> Result<Option<CloudTheme>>;
+    // oldest first
+    async fn read_all(&self, uid: i64) -> Result<Vec<CloudTheme>>;
+    async fn read_by_id(&self, uid: i64, id: i64) -> Result<Option<CloudTheme>>;
+    async fn update_theme(&self, uid: i64, id: i64, theme: &Theme) -> Result<bool>;
+    // fails with CloudThemeConflict::NameTaken if another theme of the user has the name
+    async fn rename(&self, uid: i64, id: i64, name: &str) -> Result<bool>;
+    async fn activate(&self, uid: i64, id: i64) -> Result<bool>;
+    // if the deleted theme was active the most recently changed one of the rest becomes active
+    async fn delete(&self, uid: i64, id: i64) -> Result<bool>;
 }
 
-impl CloudThemeDb for CloudThemeDatabase {
-    async fn new() -> Result<Self> {
-        Ok(Self {
-            pool: Database::get_pool().await?,
-        })
+repository! {
+    CloudThemeDatabase: CloudThemeDb {
+        postgres: PgCloudThemeDatabase,
+        sqlite: SqliteCloudThemeDatabase,
+        memory: MemoryCloudThemeDatabase,
+        fn insert(
+            &self,
+            uid: i64,
+            name: &str,
+            theme: &Theme,
+            active: bool,
+            limit: usize,
+        ) -> Result<CloudTheme>;
+        #[retry]
+        fn read_active(&self, uid: i64) -> Result<Option<CloudTheme>>;
+        #[retry]
+        fn read_all(&self, uid: i64) -> Result<Vec<CloudTheme>>;
+        #[retry]
+        fn read_by_id(&self, uid: i64, id: i64) -> Result<Option<CloudTheme>>;
+        fn update_theme(&self, uid: i64, id: i64, theme: &Theme) -> Result<bool>;
+        fn rename(&self, uid: i64, id: i64, name: &str) -> Result<bool>;
+        fn activate(&self, uid: i64, id: i64) -> Result<bool>;
+        fn delete(&self, uid: i64, id: i64) -> Result<bool>;
     }
+}
 
-    async fn create_table(&self) -> Result<()> {
-        sqlx::query(
-            "CREATE TABLE IF NOT EXISTS cloudthemes (
-                uid BIGINT PRIMARY KEY,
-                primary_color_text TEXT,
-                primary_color TEXT,
-                secondary_color TEXT,
-                background_color_primary TEXT,
-                background_color_secondary TEXT,
-                background_color_tertiary TEXT,
-                primary_grey TEXT,
-                secondary_grey TEXT,
-                font_size TEXT,
-                transparency BOOLEAN DEFAULT TRUE,
-                transparency_value FLOAT NOT NULL,
-                transparency_blur TEXT
-            )",
-        )
-        .execute(&self.pool)
-        .await?;
+// the order parse_cloudtheme_record expects, the sqlite repository selects the same
+pub const CLOUDTHEME_COLUMNS: &str = "id, uid, name, active, primary_color_text, primary_color,
+    secondary_color, background_color_primary, background_color_secondary,
+    background_color_tertiary, primary_grey, secondary_grey, font_size, transparency,
+    transparency_value, transparency_blur, created_at, updated_at";
+
+pub struct PgCloudThemeDatabase {
+    pub pool: PgPool,
+}
 
-        Ok(())
+impl PgCloudThemeDatabase {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
     }
+}
 
-    async fn insert(&self, uid: i64, theme: Theme) -> Result<()> {
+impl CloudThemeDb for PgCloudThemeDatabase {
+    async fn insert(
+        &self,
+        uid: i64,
+        name: &str,
+        theme: &Theme,
+        active: bool,
+        limit: usize,
+    ) -> Result<CloudTheme> {
         let mut txn = self.pool.begin().await?;
 
-        sqlx::query(
+        // the lock on the account makes a second insert for the same user wait until this one
+        // is done, so it counts the theme added here
+        sqlx::query("SELECT uid FROM accounts WHERE uid = $1 FOR UPDATE")
+            .bind(uid)
+            .execute(&mut *txn)
+            .await?;
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM cloudthemes WHERE uid = $1")
+            .bind(uid)
+            .fetch_one(&mut *txn)
+            .await?;
+
+        if count as usize >= limit {
+            return Err(CloudThemeConflict::TooManyThemes(limit).into());
+        }
+
+        if active {
+            sqlx::query("UPDATE cloudthemes SET active = FALSE WHERE uid = $1 AND active")
+                .bind(uid)
+                .execute(&mut *txn)
+                .await?;
+        }
+
+        let row = sqlx::query(&format!(
             "INSERT INTO cloudthemes (
-            uid,
-            primary_color_text,
-            primary_color,
-            secondary_color,
-            background_color_primary,
-            background_color_secondary,
-            background_color_tertiary,
-            primary_grey,
-            secondary_grey,
-            font_size,
-            transparency,
-            transparency_value,
-            transparency_blur
-        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
-        ON CONFLICT (uid) DO UPDATE SET
-            primary_color_text = EXCLUDED.primary_color_text,
-            primary_color = EXCLUDED.primary_color,
-            secondary_color = EXCLUDED.secondary_color,
-            background_color_primary = EXCLUDED.background_color_primary,
-            background_color_secondary = EXCLUDED.background_color_secondary,
-            background_color_tertiary = EXCLUDED.background_color_tertiary,
-            primary_grey = EXCLUDED.primary_grey,
-            secondary_grey = EXCLUDED.secondary_grey,
-            font_size = EXCLUDED.font_size,
-            transparency = EXCLUDED.transparency,
-            transparency_value = EXCLUDED.transparency_value,
-            transparency_blur = EXCLUDED.transparency_blur
-        ",
-        )
+                id,
+                uid,
+                name,
+                active,
+                primary_color_text,
+                primary_color,
+                secondary_color,
+                background_color_primary,
+                background_color_secondary,
+                background_color_tertiary,
+                primary_grey,
+                secondary_grey,
+                font_size,
+                transparency,
+                transparency_value,
+                transparency_blur
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+            RETURNING {}",
+            CLOUDTHEME_COLUMNS
+        ))
+        .bind(generate_uid())
         .bind(uid)
+        .bind(name)
+        .bind(active)
         .bind(&theme.primary_color_text)
         .bind(&theme.primary_color)
         .bind(&theme.secondary_color)
@@ -97,135 +154,72 @@ impl CloudThemeDb for CloudThemeDatabase {
         .bind(theme.transparency)
         .bind(theme.transparency_value)
         .bind(&theme.transparency_blur)
-        .execute(&mut *txn)
-        .await?;
+        .fetch_one(&mut *txn)
+        .await
+        .map_err(|e| name_taken(e, name))?;
 
         txn.commit().await?;
 
-        Ok(())
-    }
-
-    async fn read_by_uid(&self, uid: i64) -> Result<Option<CloudTheme>> {
-        let row = sqlx::query("SELECT * FROM cloudthemes WHERE uid = $1")
-            .bind(uid)
-            .fetch_optional(&self.pool)
-            .await?;
-
-        let user = match row {
-            Some(row) => Some(CloudTheme {
-                uid: row.try_get(0)?,
-                theme: Theme {
-                    primary_color_text: row.try_get(1)?,
-                    primary_color: row.try_get(2)?,
-                    secondary_color: row.try_get(3)?,
-                    background_color_primary: row.try_get(4)?,
-                    background_color_secondary: row.try_get(5)?,
-                    background_color_tertiary: row.try_get(6)?,
-                    primary_grey: row.try_get(7)?,
-                    secondary_grey: row.try_get(8)?,
-                    font_size: row.try_get(9)?,
-                    transparency: row.try_get(10)?,
-                    transparency_value: row.try_get(11)?,
-                    transparency_blur: row.try_get(12)?,
-                },
-            }),
-            None => None,
-        };
-
-        Ok(user)
+        parse_cloudtheme_record(row)
     }
-}
 
-/*
-pub struct Database {
-    pub pool: PgPool,
-}
+    async fn read_active(&self, uid: i64) -> Result<Option<CloudTheme>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM cloudthemes WHERE uid = $1 AND active",
+            CLOUDTHEME_COLUMNS
+        ))
+        .bind(uid)
+        .fetch_optional(&self.pool)
+        .await?;
 
-impl Database {
-    pub async fn new() -> Result<Self> {
-        let url = format!(
-            "postgresql://postgres:{}@localhost:{}/{}",
-            SECRETS.get("DB_PW").unwrap(),
-            SECRETS.get("DB_PORT").unwrap(),
-            SECRETS.get("DB_NAME").unwrap()
-        );
-        let pool = sqlx::postgres::PgPool::connect(&url).await?;
-        Ok(Self { pool })
+        row.map(parse_cloudtheme_record).transpose()
     }
 
-    pub async fn create_table(&self) -> Result<()> {
-        sqlx::query(
-            "CREATE TABLE IF NOT EXISTS cloudthemes (
-                uid BIGINT PRIMARY KEY,
-                primary_color_text TEXT,
-                primary_color TEXT,
-                secondary_color TEXT,
-                background_color_primary TEXT,
-                background_color_secondary TEXT,
-                background_color_tertiary TEXT,
-                primary_grey TEXT,
-                secondary_grey TEXT,
-                font_size TEXT,
-                transparency BOOLEAN DEFAULT TRUE,
-                transparency_value FLOAT NOT NULL,
-                transparency_blur TEXT
-            )",
-        )
-        .execute(&self.pool)
+    async fn read_all(&self, uid: i64) -> Result<Vec<CloudTheme>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM cloudthemes WHERE uid = $1 ORDER BY created_at, id",
+            CLOUDTHEME_COLUMNS
+        ))
+        .bind(uid)
+        .fetch_all(&self.pool)
         .await?;
 
-        Ok(())
+        rows.into_iter().map(parse_cloudtheme_record).collect()
     }
 
-    pub async fn read_by_uid(&self, uid: i64) -> Result<Option<CloudTheme>> {
-        let row = sqlx::query("SELECT * FROM cloudthemes WHERE uid = $1")
-            .bind(uid)
-            .fetch_optional(&self.pool)
-            .await?;
-
-        let user = match row {
-            Some(row) => Some(parse_cloudtheme_record(row)?),
-            None => None,
-        };
+    async fn read_by_id(&self, uid: i64, id: i64) -> Result<Option<CloudTheme>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM cloudthemes WHERE uid = $1 AND id = $2",
+            CLOUDTHEME_COLUMNS
+        ))
+        .bind(uid)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
 
-        Ok(user)
+        row.map(parse_cloudtheme_record).transpose()
     }
 
-    pub async fn insert(&self, uid: i64, theme: Theme) -> Result<()> {
-        let mut txn = self.pool.begin().await?;
-
-        sqlx::query(
-            "INSERT INTO cloudthemes (
-            uid,
-            primary_color_text,
-            primary_color,
-            secondary_color,
-            background_color_primary,
-            background_color_secondary,
-            background_color_tertiary,
-            primary_grey,
-            secondary_grey,
-            font_size,
-            transparency,
-            transparency_value,
-            transparency_blur
-        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
-        ON CONFLICT (uid) DO UPDATE SET
-            primary_color_text = EXCLUDED.primary_color_text,
-            primary_color = EXCLUDED.primary_color,
-            secondary_color = EXCLUDED.secondary_color,
-            background_color_primary = EXCLUDED.background_color_primary,
-            background_color_secondary = EXCLUDED.background_color_secondary,
-            background_color_tertiary = EXCLUDED.background_color_tertiary,
-            primary_grey = EXCLUDED.primary_grey,
-            secondary_grey = EXCLUDED.secondary_grey,
-            font_size = EXCLUDED.font_size,
-            transparency = EXCLUDED.transparency,
-            transparency_value = EXCLUDED.transparency_value,
-            transparency_blur = EXCLUDED.transparency_blur
-        ",
+    async fn update_theme(&self, uid: i64, id: i64, theme: &Theme) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE cloudthemes SET
+                primary_color_text = $3,
+                primary_color = $4,
+                secondary_color = $5,
+                background_color_primary = $6,
+                background_color_secondary = $7,
+                background_color_tertiary = $8,
+                primary_grey = $9,
+                secondary_grey = $10,
+                font_size = $11,
+                transparency = $12,
+                transparency_value = $13,
+                transparency_blur = $14,
+                updated_at = NOW()
+            WHERE uid = $1 AND id = $2",
         )
         .bind(uid)
+        .bind(id)
         .bind(&theme.primary_color_text)
         .bind(&theme.primary_color)
         .bind(&theme.secondary_color)
@@ -238,32 +232,115 @@ impl Database {
         .bind(theme.transparency)
         .bind(theme.transparency_value)
         .bind(&theme.transparency_blur)
-        .execute(&mut *txn)
+        .execute(&self.pool)
         .await?;
 
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn rename(&self, uid: i64, id: i64, name: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE cloudthemes SET name = $3, updated_at = NOW() WHERE uid = $1 AND id = $2",
+        )
+        .bind(uid)
+        .bind(id)
+        .bind(name)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| name_taken(e, name))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn activate(&self, uid: i64, id: i64) -> Result<bool> {
+        let mut txn = self.pool.begin().await?;
+
+        // the old one is deactivated first, the index allows only one active theme at any time
+        sqlx::query("UPDATE cloudthemes SET active = FALSE WHERE uid = $1 AND active AND id <> $2")
+            .bind(uid)
+            .bind(id)
+            .execute(&mut *txn)
+            .await?;
+
+        let result = sqlx::query("UPDATE cloudthemes SET active = TRUE WHERE uid = $1 AND id = $2")
+            .bind(uid)
+            .bind(id)
+            .execute(&mut *txn)
+            .await?;
+
+        // an unknown id leaves the active theme alone
+        if result.rows_affected() == 0 {
+            txn.rollback().await?;
+            return Ok(false);
+        }
+
         txn.commit().await?;
 
-        Ok(())
+        Ok(true)
+    }
+
+    async fn delete(&self, uid: i64, id: i64) -> Result<bool> {
+        let mut txn = self.pool.begin().await?;
+
+        let row =
+            sqlx::query("DELETE FROM cloudthemes WHERE uid = $1 AND id = $2 RETURNING active")
+                .bind(uid)
+                .bind(id)
+                .fetch_optional(&mut *txn)
+                .await?;
+
+        let Some(row) = row else {
+            return Ok(false);
+        };
+
+        if row.try_get::<bool, _>(0)? {
+            sqlx::query(
+                "UPDATE cloudthemes SET active = TRUE WHERE id = (
+                    SELECT id FROM cloudthemes WHERE uid = $1 ORDER BY updated_at DESC, id DESC LIMIT 1
+                )",
+            )
+            .bind(uid)
+            .execute(&mut *txn)
+            .await?;
+        }
+
+        txn.commit().await?;
+
+        Ok(true)
+    }
+}
+
+// the unique index on (uid, name) turned into the error the handlers answer with 409
+fn name_taken(error: sqlx::Error, name: &str) -> anyhow::Error {
+    match &error {
+        sqlx::Error::Database(e) if e.constraint() == Some("cloudthemes_uid_name_idx") => {
+            CloudThemeConflict::NameTaken(name.to_string()).into()
+        }
+        _ => error.into(),
     }
 }
 
-fn parse_cloudtheme_record(row: PgRow) -> Result<CloudTheme, sqlx::Error> {
+fn parse_cloudtheme_record(row: PgRow) -> Result<CloudTheme> {
     Ok(CloudTheme {
-        uid: row.try_get(0)?,
+        id: row.try_get(0)?,
+        uid: row.try_get(1)?,
+        name: row.try_get(2)?,
+        active: row.try_get(3)?,
         theme: Theme {
-            primary_color_text: row.try_get(1)?,
-            primary_color: row.try_get(2)?,
-            secondary_color: row.try_get(3)?,
-            background_color_primary: row.try_get(4)?,
-            background_color_secondary: row.try_get(5)?,
-            background_color_tertiary: row.try_get(6)?,
-            primary_grey: row.try_get(7)?,
-            secondary_grey: row.try_get(8)?,
-            font_size: row.try_get(9)?,
-            transparency: row.try_get(10)?,
-            transparency_value: row.try_get(11)?,
-            transparency_blur: row.try_get(12)?,
+            primary_color_text: row.try_get(4)?,
+            primary_color: row.try_get(5)?,
+            secondary_color: row.try_get(6)?,
+            background_color_primary: row.try_get(7)?,
+            background_color_secondary: row.try_get(8)?,
+            background_color_tertiary: row.try_get(9)?,
+            primary_grey: row.try_get(10)?,
+            secondary_grey: row.try_get(11)?,
+            font_size: row.try_get(12)?,
+            transparency: row.try_get(13)?,
+            transparency_value: row.try_get(14)?,
+            transparency_blur: row.try_get(15)?,
         },
+        created_at: row.try_get(16)?,
+        updated_at: row.try_get(17)?,
     })
 }
-*/
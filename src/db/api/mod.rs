@@ -1,2 +1,9 @@
+pub mod announcements;
 pub mod cloudthemes;
+pub mod invites;
+pub mod notification_preferences;
+pub mod notifications;
+pub mod outbox;
+pub mod signup_cleanup;
+pub mod theme_gallery;
 pub mod users;
@@ -0,0 +1,75 @@
+use anyhow::Result;
+use sqlx::{PgPool, Row};
+
+use crate::db::memory::notification_preferences::MemoryNotificationPreferencesDatabase;
+use crate::db::sqlite::notification_preferences::SqliteNotificationPreferencesDatabase;
+use crate::models::api::notification_preferences::NotificationPreferences;
+
+pub trait NotificationPreferencesDb {
+    async fn read_by_uid(&self, uid: i64) -> Result<NotificationPreferences>;
+    async fn upsert(&self, uid: i64, preferences: &NotificationPreferences) -> Result<()>;
+}
+
+repository! {
+    NotificationPreferencesDatabase: NotificationPreferencesDb {
+        postgres: PgNotificationPreferencesDatabase,
+        sqlite: SqliteNotificationPreferencesDatabase,
+        memory: MemoryNotificationPreferencesDatabase,
+        #[retry]
+        fn read_by_uid(&self, uid: i64) -> Result<NotificationPreferences>;
+        fn upsert(&self, uid: i64, preferences: &NotificationPreferences) -> Result<()>;
+    }
+}
+
+pub struct PgNotificationPreferencesDatabase {
+    pub pool: PgPool,
+}
+
+impl PgNotificationPreferencesDatabase {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl NotificationPreferencesDb for PgNotificationPreferencesDatabase {
+    // users without a row get the defaults, everything enabled
+    async fn read_by_uid(&self, uid: i64) -> Result<NotificationPreferences> {
+        let row = sqlx::query(
+            "SELECT reminders, login_alerts, announcements FROM notification_preferences WHERE uid = $1",
+        )
+        .bind(uid)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let preferences = match row {
+            Some(row) => NotificationPreferences {
+                security: true,
+                reminders: row.try_get(0)?,
+                login_alerts: row.try_get(1)?,
+                announcements: row.try_get(2)?,
+            },
+            None => NotificationPreferences::default(),
+        };
+
+        Ok(preferences)
+    }
+
+    async fn upsert(&self, uid: i64, preferences: &NotificationPreferences) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO notification_preferences (uid, reminders, login_alerts, announcements)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (uid) DO UPDATE SET
+                reminders = EXCLUDED.reminders,
+                login_alerts = EXCLUDED.login_alerts,
+                announcements = EXCLUDED.announcements",
+        )
+        .bind(uid)
+        .bind(preferences.reminders)
+        .bind(preferences.login_alerts)
+        .bind(preferences.announcements)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use anyhow::Result;
+use sqlx::{postgres::PgRow, PgPool, Row};
+
+use crate::db::api::announcements::audience_filter;
+use crate::db::memory::notifications::MemoryNotificationDatabase;
+use crate::db::sqlite::notifications::SqliteNotificationDatabase;
+use crate::models::api::announcements::Announcement;
+use crate::models::api::notifications::Notification;
+use crate::util::snowflake::generate_uid;
+
+pub trait NotificationDb {
+    async fn insert_announcement(&self, announcement: &Announcement) -> Result<u64>;
+    async fn read_by_uid(
+        &self,
+        uid: i64,
+        unread_only: bool,
+        limit: i64,
+    ) -> Result<Vec<Notification>>;
+    async fn mark_read(&self, uid: i64, id: i64) -> Result<bool>;
+    async fn mark_all_read(&self, uid: i64) -> Result<u64>;
+}
+
+repository! {
+    NotificationDatabase: NotificationDb {
+        postgres: PgNotificationDatabase,
+        sqlite: SqliteNotificationDatabase,
+        memory: MemoryNotificationDatabase,
+        fn insert_announcement(&self, announcement: &Announcement) -> Result<u64>;
+        #[retry]
+        fn read_by_uid(&self, uid: i64, unread_only: bool, limit: i64) -> Result<Vec<Notification>>;
+        fn mark_read(&self, uid: i64, id: i64) -> Result<bool>;
+        fn mark_all_read(&self, uid: i64) -> Result<u64>;
+    }
+}
+
+pub struct PgNotificationDatabase {
+    pub pool: PgPool,
+}
+
+impl PgNotificationDatabase {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl NotificationDb for PgNotificationDatabase {
+    // puts the announcement into the inbox of everyone in its audience, returns how many got it
+    async fn insert_announcement(&self, announcement: &Announcement) -> Result<u64> {
+        let mut txn = self.pool.begin().await?;
+
+        let rows = sqlx::query(&format!(
+            "SELECT u.uid FROM accounts u WHERE {}",
+            audience_filter(announcement.audience)
+        ))
+        .fetch_all(&mut *txn)
+        .await?;
+
+        let mut inserted = 0;
+
+        for row in rows {
+            let uid: i64 = row.try_get(0)?;
+
+            sqlx::query(
+                "INSERT INTO notifications (
+                    id,
+                    uid,
+                    title,
+                    body,
+                    announcement_id
+                ) VALUES ($1, $2, $3, $4, $5)",
+            )
+            .bind(generate_uid())
+            .bind(uid)
+            .bind(&announcement.subject)
+            .bind(&announcement.body)
+            .bind(announcement.id)
+            .execute(&mut *txn)
+            .await?;
+
+            inserted += 1;
+        }
+
+        txn.commit().await?;
+
+        Ok(inserted)
+    }
+
+    async fn read_by_uid(
+        &self,
+        uid: i64,
+        unread_only: bool,
+        limit: i64,
+    ) -> Result<Vec<Notification>> {
+        let rows = sqlx::query(
+            "SELECT * FROM notifications
+            WHERE uid = $1 AND ($2 = FALSE OR read_at IS NULL)
+            ORDER BY id DESC
+            LIMIT $3",
+        )
+        .bind(uid)
+        .bind(unread_only)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(parse_notification_record).collect()
+    }
+
+    async fn mark_read(&self, uid: i64, id: i64) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE notifications SET read_at = COALESCE(read_at, NOW()) WHERE id = $1 AND uid = $2",
+        )
+        .bind(id)
+        .bind(uid)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn mark_all_read(&self, uid: i64) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE notifications SET read_at = NOW() WHERE uid = $1 AND read_at IS NULL",
+        )
+        .bind(uid)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+fn parse_notification_record(row: PgRow) -> Result<Notification> {
+    Ok(Notification {
+        id: row.try_get(0)?,
+        uid: row.try_get(1)?,
+        title: row.try_get(2)?,
+        body: row.try_get(3)?,
+        announcement_id: row.try_get(4)?,
+        read_at: row.try_get(5)?,
+        created_at: row.try_get(6)?,
+    })
+}
@@ -0,0 +1,265 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgRow, PgPool, Row};
+
+use crate::db::memory::outbox::MemoryOutboxDatabase;
+use crate::db::sqlite::outbox::SqliteOutboxDatabase;
+use crate::mailer::Email;
+use crate::models::api::outbox::{
+    OutboxMessage, OutboxSummary, STATUS_DEAD, STATUS_PENDING, STATUS_SENT,
+};
+use crate::util::snowflake::generate_uid;
+
+pub trait OutboxDb {
+    async fn enqueue(&self, email: &Email) -> Result<i64>;
+    async fn claim_due(&self, limit: i64) -> Result<Vec<OutboxMessage>>;
+    async fn mark_sent(&self, id: i64) -> Result<()>;
+    async fn mark_failed(
+        &self,
+        id: i64,
+        attempts: i32,
+        error: &str,
+        next_attempt_at: DateTime<Utc>,
+    ) -> Result<()>;
+    async fn mark_dead(&self, id: i64, attempts: i32, error: &str) -> Result<()>;
+    async fn postpone(&self, id: i64, next_attempt_at: DateTime<Utc>) -> Result<()>;
+    async fn count_sent_to(&self, recipient: &str, since: DateTime<Utc>) -> Result<i64>;
+    // messages with the status which failed at least once, newest first
+    async fn read_failed(&self, status: &str, limit: i64) -> Result<Vec<OutboxSummary>>;
+    async fn retry(&self, id: i64) -> Result<bool>;
+    async fn delete_sent_before(&self, before: DateTime<Utc>) -> Result<u64>;
+}
+
+repository! {
+    OutboxDatabase: OutboxDb {
+        postgres: PgOutboxDatabase,
+        sqlite: SqliteOutboxDatabase,
+        memory: MemoryOutboxDatabase,
+        fn enqueue(&self, email: &Email) -> Result<i64>;
+        fn claim_due(&self, limit: i64) -> Result<Vec<OutboxMessage>>;
+        fn mark_sent(&self, id: i64) -> Result<()>;
+        fn mark_failed(
+            &self,
+            id: i64,
+            attempts: i32,
+            error: &str,
+            next_attempt_at: DateTime<Utc>,
+        ) -> Result<()>;
+        fn mark_dead(&self, id: i64, attempts: i32, error: &str) -> Result<()>;
+        fn postpone(&self, id: i64, next_attempt_at: DateTime<Utc>) -> Result<()>;
+        #[retry]
+        fn count_sent_to(&self, recipient: &str, since: DateTime<Utc>) -> Result<i64>;
+        #[retry]
+        fn read_failed(&self, status: &str, limit: i64) -> Result<Vec<OutboxSummary>>;
+        fn retry(&self, id: i64) -> Result<bool>;
+        fn delete_sent_before(&self, before: DateTime<Utc>) -> Result<u64>;
+    }
+}
+
+// the order parse_outbox_record expects, the sqlite repository selects the same
+pub const OUTBOX_COLUMNS: &str = "id, recipient, subject, html, text, unsubscribe_url, status,
+    attempts, next_attempt_at, last_error, created_at, sent_at";
+
+// the order parse_outbox_summary expects, without the bodies
+pub const OUTBOX_SUMMARY_COLUMNS: &str =
+    "id, recipient, subject, status, attempts, next_attempt_at, last_error, created_at, sent_at";
+
+pub struct PgOutboxDatabase {
+    pub pool: PgPool,
+}
+
+impl PgOutboxDatabase {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl OutboxDb for PgOutboxDatabase {
+    async fn enqueue(&self, email: &Email) -> Result<i64> {
+        let id = generate_uid();
+
+        sqlx::query(
+            "INSERT INTO email_outbox (
+                id,
+                recipient,
+                subject,
+                html,
+                text,
+                unsubscribe_url
+            ) VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(id)
+        .bind(&email.to)
+        .bind(&email.subject)
+        .bind(&email.html)
+        .bind(&email.text)
+        .bind(&email.unsubscribe_url)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(id)
+    }
+
+    // takes a lease on the due messages by moving their next attempt 5 minutes into the future,
+    // so a second sender skips them and a crashed send gets picked up again later
+    async fn claim_due(&self, limit: i64) -> Result<Vec<OutboxMessage>> {
+        let rows = sqlx::query(&format!(
+            "UPDATE email_outbox SET next_attempt_at = NOW() + INTERVAL '5 minutes'
+            WHERE id IN (
+                SELECT id FROM email_outbox
+                WHERE status = $1 AND next_attempt_at <= NOW()
+                ORDER BY next_attempt_at
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING {}",
+            OUTBOX_COLUMNS
+        ))
+        .bind(STATUS_PENDING)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(parse_outbox_record).collect()
+    }
+
+    async fn mark_sent(&self, id: i64) -> Result<()> {
+        sqlx::query(
+            "UPDATE email_outbox SET status = $1, sent_at = NOW(), last_error = NULL WHERE id = $2",
+        )
+        .bind(STATUS_SENT)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn mark_failed(
+        &self,
+        id: i64,
+        attempts: i32,
+        error: &str,
+        next_attempt_at: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE email_outbox SET attempts = $1, last_error = $2, next_attempt_at = $3 WHERE id = $4",
+        )
+        .bind(attempts)
+        .bind(error)
+        .bind(next_attempt_at)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn mark_dead(&self, id: i64, attempts: i32, error: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE email_outbox SET status = $1, attempts = $2, last_error = $3 WHERE id = $4",
+        )
+        .bind(STATUS_DEAD)
+        .bind(attempts)
+        .bind(error)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn postpone(&self, id: i64, next_attempt_at: DateTime<Utc>) -> Result<()> {
+        sqlx::query("UPDATE email_outbox SET next_attempt_at = $1 WHERE id = $2")
+            .bind(next_attempt_at)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn count_sent_to(&self, recipient: &str, since: DateTime<Utc>) -> Result<i64> {
+        let row = sqlx::query(
+            "SELECT COUNT(*) FROM email_outbox WHERE recipient = $1 AND status = $2 AND sent_at > $3",
+        )
+        .bind(recipient)
+        .bind(STATUS_SENT)
+        .bind(since)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(row.try_get(0)?)
+    }
+
+    async fn read_failed(&self, status: &str, limit: i64) -> Result<Vec<OutboxSummary>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM email_outbox
+            WHERE status = $1 AND attempts > 0
+            ORDER BY created_at DESC
+            LIMIT $2",
+            OUTBOX_SUMMARY_COLUMNS
+        ))
+        .bind(status)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(parse_outbox_summary).collect()
+    }
+
+    async fn retry(&self, id: i64) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE email_outbox SET status = $1, attempts = 0, next_attempt_at = NOW()
+            WHERE id = $2 AND status <> $3",
+        )
+        .bind(STATUS_PENDING)
+        .bind(id)
+        .bind(STATUS_SENT)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_sent_before(&self, before: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM email_outbox WHERE status = $1 AND sent_at < $2")
+            .bind(STATUS_SENT)
+            .bind(before)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+fn parse_outbox_record(row: PgRow) -> Result<OutboxMessage> {
+    Ok(OutboxMessage {
+        id: row.try_get(0)?,
+        recipient: row.try_get(1)?,
+        subject: row.try_get(2)?,
+        html: row.try_get(3)?,
+        text: row.try_get(4)?,
+        unsubscribe_url: row.try_get(5)?,
+        status: row.try_get(6)?,
+        attempts: row.try_get(7)?,
+        next_attempt_at: row.try_get(8)?,
+        last_error: row.try_get(9)?,
+        created_at: row.try_get(10)?,
+        sent_at: row.try_get(11)?,
+    })
+}
+
+fn parse_outbox_summary(row: PgRow) -> Result<OutboxSummary> {
+    Ok(OutboxSummary {
+        id: row.try_get(0)?,
+        recipient: row.try_get(1)?,
+        subject: row.try_get(2)?,
+        status: row.try_get(3)?,
+        attempts: row.try_get(4)?,
+        next_attempt_at: row.try_get(5)?,
+        last_error: row.try_get(6)?,
+        created_at: row.try_get(7)?,
+        sent_at: row.try_get(8)?,
+    })
+}
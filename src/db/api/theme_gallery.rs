@@ -0,0 +1,373 @@
+use anyhow::Result;
+use sqlx::{postgres::PgRow, PgPool, Row};
+use thiserror::Error;
+
+use crate::db::memory::theme_gallery::MemoryThemeGalleryDatabase;
+use crate::db::sqlite::theme_gallery::SqliteThemeGalleryDatabase;
+use crate::models::api::{
+    cloudtheme::Theme,
+    theme_gallery::{GallerySort, GalleryTheme},
+};
+use crate::util::snowflake::generate_uid;
+
+// why insert refused, checked where the entry is written so two publishes at the same time can't
+// both get past it
+#[derive(Debug, Error)]
+pub enum GalleryConflict {
+    #[error("this theme was taken out of the gallery by a moderator and can't be published again")]
+    Moderated,
+    #[error("you can have at most {0} themes in the gallery")]
+    TooManyPublished(usize),
+}
+
+// everything needed to publish an entry, see ThemeGalleryDb::insert
+#[derive(Clone, Copy)]
+pub struct NewGalleryEntry<'a> {
+    pub uid: i64,
+    // the cloudtheme the entry is copied from
+    pub source_id: i64,
+    pub share_code: &'a str,
+    pub title: &'a str,
+    pub description: &'a str,
+    pub listed: bool,
+    pub theme: &'a Theme,
+}
+
+pub trait ThemeGalleryDb {
+    // fails with a GalleryConflict if the user has `limit` published entries already or an
+    // owner took down an entry of the same cloudtheme
+    async fn insert(&self, entry: &NewGalleryEntry<'_>, limit: usize) -> Result<GalleryTheme>;
+    // the published and listed entries
+    async fn read_gallery(
+        &self,
+        sort: GallerySort,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<GalleryTheme>>;
+    // published or not
+    async fn read_by_share_code(&self, share_code: &str) -> Result<Option<GalleryTheme>>;
+    // newest first
+    async fn read_by_uid(&self, uid: i64) -> Result<Vec<GalleryTheme>>;
+    // every entry newest first, published or not
+    async fn read_all(&self, limit: i64) -> Result<Vec<GalleryTheme>>;
+    // false if there is no published entry with this id
+    async fn unpublish(&self, id: i64, by: i64, reason: Option<&str>) -> Result<bool>;
+    async fn republish(&self, id: i64) -> Result<bool>;
+    // counts the install unless the user installed the entry before, true if it counted
+    async fn record_install(&self, id: i64, uid: i64) -> Result<bool>;
+}
+
+repository! {
+    ThemeGalleryDatabase: ThemeGalleryDb {
+        postgres: PgThemeGalleryDatabase,
+        sqlite: SqliteThemeGalleryDatabase,
+        memory: MemoryThemeGalleryDatabase,
+        fn insert(&self, entry: &NewGalleryEntry<'_>, limit: usize) -> Result<GalleryTheme>;
+        #[retry]
+        fn read_gallery(
+            &self,
+            sort: GallerySort,
+            limit: i64,
+            offset: i64,
+        ) -> Result<Vec<GalleryTheme>>;
+        #[retry]
+        fn read_by_share_code(&self, share_code: &str) -> Result<Option<GalleryTheme>>;
+        #[retry]
+        fn read_by_uid(&self, uid: i64) -> Result<Vec<GalleryTheme>>;
+        #[retry]
+        fn read_all(&self, limit: i64) -> Result<Vec<GalleryTheme>>;
+        fn unpublish(&self, id: i64, by: i64, reason: Option<&str>) -> Result<bool>;
+        fn republish(&self, id: i64) -> Result<bool>;
+        fn record_install(&self, id: i64, uid: i64) -> Result<bool>;
+    }
+}
+
+// the order parse_gallery_record expects, selected from `theme_gallery g JOIN accounts a`. the
+// sqlite repository selects the same
+pub const GALLERY_COLUMNS: &str = "g.id, g.share_code, g.uid, g.source_id, a.username, g.title,
+    g.description, g.listed, g.primary_color_text, g.primary_color, g.secondary_color,
+    g.background_color_primary, g.background_color_secondary, g.background_color_tertiary,
+    g.primary_grey, g.secondary_grey, g.font_size, g.transparency, g.transparency_value,
+    g.transparency_blur, g.installs, g.published, g.unpublished_by, g.unpublish_reason,
+    g.unpublished_at, g.created_at";
+
+// the ORDER BY of a gallery page, the id breaks ties between entries of the same millisecond
+pub fn gallery_order(sort: GallerySort) -> &'static str {
+    match sort {
+        GallerySort::Newest => "g.created_at DESC, g.id DESC",
+        GallerySort::MostInstalled => "g.installs DESC, g.created_at DESC, g.id DESC",
+    }
+}
+
+pub struct PgThemeGalleryDatabase {
+    pub pool: PgPool,
+}
+
+impl PgThemeGalleryDatabase {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl ThemeGalleryDb for PgThemeGalleryDatabase {
+    async fn insert(&self, entry: &NewGalleryEntry<'_>, limit: usize) -> Result<GalleryTheme> {
+        let NewGalleryEntry {
+            uid,
+            source_id,
+            share_code,
+            title,
+            description,
+            listed,
+            theme,
+        } = *entry;
+        let id = generate_uid();
+        let mut txn = self.pool.begin().await?;
+
+        // the lock on the account makes a second publish of the same user wait until this one is
+        // done, so it counts the entry added here
+        sqlx::query("SELECT uid FROM accounts WHERE uid = $1 FOR UPDATE")
+            .bind(uid)
+            .execute(&mut *txn)
+            .await?;
+
+        let moderated: bool = sqlx::query_scalar(
+            "SELECT EXISTS (
+                SELECT 1 FROM theme_gallery
+                WHERE uid = $1 AND source_id = $2
+                    AND NOT published AND unpublished_by IS DISTINCT FROM uid
+            )",
+        )
+        .bind(uid)
+        .bind(source_id)
+        .fetch_one(&mut *txn)
+        .await?;
+
+        if moderated {
+            return Err(GalleryConflict::Moderated.into());
+        }
+
+        let published: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM theme_gallery WHERE uid = $1 AND published")
+                .bind(uid)
+                .fetch_one(&mut *txn)
+                .await?;
+
+        if published as usize >= limit {
+            return Err(GalleryConflict::TooManyPublished(limit).into());
+        }
+
+        sqlx::query(
+            "INSERT INTO theme_gallery (
+                id,
+                share_code,
+                uid,
+                source_id,
+                title,
+                description,
+                listed,
+                primary_color_text,
+                primary_color,
+                secondary_color,
+                background_color_primary,
+                background_color_secondary,
+                background_color_tertiary,
+                primary_grey,
+                secondary_grey,
+                font_size,
+                transparency,
+                transparency_value,
+                transparency_blur
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19)",
+        )
+        .bind(id)
+        .bind(share_code)
+        .bind(uid)
+        .bind(source_id)
+        .bind(title)
+        .bind(description)
+        .bind(listed)
+        .bind(&theme.primary_color_text)
+        .bind(&theme.primary_color)
+        .bind(&theme.secondary_color)
+        .bind(&theme.background_color_primary)
+        .bind(&theme.background_color_secondary)
+        .bind(&theme.background_color_tertiary)
+        .bind(&theme.primary_grey)
+        .bind(&theme.secondary_grey)
+        .bind(&theme.font_size)
+        .bind(theme.transparency)
+        .bind(theme.transparency_value)
+        .bind(&theme.transparency_blur)
+        .execute(&mut *txn)
+        .await?;
+
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM theme_gallery g JOIN accounts a ON a.uid = g.uid WHERE g.id = $1",
+            GALLERY_COLUMNS
+        ))
+        .bind(id)
+        .fetch_one(&mut *txn)
+        .await?;
+
+        txn.commit().await?;
+
+        parse_gallery_record(row)
+    }
+
+    async fn read_gallery(
+        &self,
+        sort: GallerySort,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<GalleryTheme>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM theme_gallery g JOIN accounts a ON a.uid = g.uid
+            WHERE g.published AND g.listed
+            ORDER BY {}
+            LIMIT $1 OFFSET $2",
+            GALLERY_COLUMNS,
+            gallery_order(sort)
+        ))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(parse_gallery_record).collect()
+    }
+
+    async fn read_by_share_code(&self, share_code: &str) -> Result<Option<GalleryTheme>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM theme_gallery g JOIN accounts a ON a.uid = g.uid
+            WHERE g.share_code = $1",
+            GALLERY_COLUMNS
+        ))
+        .bind(share_code)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(parse_gallery_record).transpose()
+    }
+
+    async fn read_by_uid(&self, uid: i64) -> Result<Vec<GalleryTheme>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM theme_gallery g JOIN accounts a ON a.uid = g.uid
+            WHERE g.uid = $1
+            ORDER BY {}",
+            GALLERY_COLUMNS,
+            gallery_order(GallerySort::Newest)
+        ))
+        .bind(uid)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(parse_gallery_record).collect()
+    }
+
+    async fn read_all(&self, limit: i64) -> Result<Vec<GalleryTheme>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM theme_gallery g JOIN accounts a ON a.uid = g.uid
+            ORDER BY {}
+            LIMIT $1",
+            GALLERY_COLUMNS,
+            gallery_order(GallerySort::Newest)
+        ))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(parse_gallery_record).collect()
+    }
+
+    async fn unpublish(&self, id: i64, by: i64, reason: Option<&str>) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE theme_gallery SET
+                published = FALSE,
+                unpublished_by = $2,
+                unpublish_reason = $3,
+                unpublished_at = NOW()
+            WHERE id = $1 AND published",
+        )
+        .bind(id)
+        .bind(by)
+        .bind(reason)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn republish(&self, id: i64) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE theme_gallery SET
+                published = TRUE,
+                unpublished_by = NULL,
+                unpublish_reason = NULL,
+                unpublished_at = NULL
+            WHERE id = $1",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn record_install(&self, id: i64, uid: i64) -> Result<bool> {
+        let mut txn = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            "INSERT INTO theme_gallery_installs (gallery_id, uid) VALUES ($1, $2)
+            ON CONFLICT DO NOTHING",
+        )
+        .bind(id)
+        .bind(uid)
+        .execute(&mut *txn)
+        .await?;
+
+        let counted = result.rows_affected() > 0;
+        if counted {
+            sqlx::query("UPDATE theme_gallery SET installs = installs + 1 WHERE id = $1")
+                .bind(id)
+                .execute(&mut *txn)
+                .await?;
+        }
+
+        txn.commit().await?;
+
+        Ok(counted)
+    }
+}
+
+fn parse_gallery_record(row: PgRow) -> Result<GalleryTheme> {
+    Ok(GalleryTheme {
+        id: row.try_get(0)?,
+        share_code: row.try_get(1)?,
+        uid: row.try_get(2)?,
+        source_id: row.try_get(3)?,
+        author: row.try_get(4)?,
+        title: row.try_get(5)?,
+        description: row.try_get(6)?,
+        listed: row.try_get(7)?,
+        theme: Theme {
+            primary_color_text: row.try_get(8)?,
+            primary_color: row.try_get(9)?,
+            secondary_color: row.try_get(10)?,
+            background_color_primary: row.try_get(11)?,
+            background_color_secondary: row.try_get(12)?,
+            background_color_tertiary: row.try_get(13)?,
+            primary_grey: row.try_get(14)?,
+            secondary_grey: row.try_get(15)?,
+            font_size: row.try_get(16)?,
+            transparency: row.try_get(17)?,
+            transparency_value: row.try_get(18)?,
+            transparency_blur: row.try_get(19)?,
+        },
+        installs: row.try_get(20)?,
+        published: row.try_get(21)?,
+        unpublished_by: row.try_get(22)?,
+        unpublish_reason: row.try_get(23)?,
+        unpublished_at: row.try_get(24)?,
+        created_at: row.try_get(25)?,
+    })
+}
@@ -1,76 +1,48 @@
 use anyhow::Result;
-use serde::{Deserialize, Serialize};
-use sqlx::pool;
-use sqlx::{postgres::PgRow, PgPool, Row};
+use sqlx::{PgPool, Row};
 
-use crate::db::Database;
 use crate::models::api::users::User;
 
-use crate::secrets::SECRETS;
+use crate::db::memory::users::MemoryUserDatabase;
+use crate::db::sqlite::users::SqliteUserDatabase;
 
 pub trait UserDb {
-    async fn new() -> Result<Self>
-    where
-        Self: Sized;
-    async fn create_table(&self) -> Result<()>;
-    async fn insert(&self, uid: i64, username: &str, email: &str) -> Result<()>;
     async fn read_by_uid(&self, uid: i64) -> Result<Option<User>>;
+    async fn update_locale(&self, uid: i64, locale: Option<&str>) -> Result<()>;
 }
 
-pub struct UserDatabase {
-    pool: PgPool,
-}
-
-impl UserDb for UserDatabase {
-    async fn new() -> Result<Self> {
-        Ok(Self {
-            pool: Database::get_pool().await?,
-        })
+repository! {
+    UserDatabase: UserDb {
+        postgres: PgUserDatabase,
+        sqlite: SqliteUserDatabase,
+        memory: MemoryUserDatabase,
+        #[retry]
+        fn read_by_uid(&self, uid: i64) -> Result<Option<User>>;
+        fn update_locale(&self, uid: i64, locale: Option<&str>) -> Result<()>;
     }
+}
 
-    async fn create_table(&self) -> Result<()> {
-        sqlx::query(
-            "CREATE TABLE IF NOT EXISTS users (
-                uid BIGINT PRIMARY KEY,
-                email TEXT,
-                owner BOOLEAN DEFAULT FALSE,
-                email_verified BOOLEAN DEFAULT FALSE,
-                username TEXT
-            )",
-        )
-        .execute(&self.pool)
-        .await?;
+pub struct PgUserDatabase {
+    pool: PgPool,
+}
 
-        Ok(())
+impl PgUserDatabase {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
     }
+}
 
-    async fn insert(&self, uid: i64, username: &str, email: &str) -> Result<()> {
-        let mut txn = self.pool.begin().await?;
-
-        sqlx::query(
-            "INSERT INTO users (
-                uid,
-                email,
-                username
-            ) VALUES ($1, $2, $3)",
+impl UserDb for PgUserDatabase {
+    async fn read_by_uid(&self, uid: i64) -> Result<Option<User>> {
+        let row = sqlx::query(
+            "SELECT uid, email, owner, email_verified, username, locale, created_at, updated_at,
+                last_login_at, email_verified_at
+            FROM accounts WHERE uid = $1",
         )
         .bind(uid)
-        .bind(email)
-        .bind(username)
-        .execute(&mut *txn)
+        .fetch_optional(&self.pool)
         .await?;
 
-        txn.commit().await?;
-
-        Ok(())
-    }
-
-    async fn read_by_uid(&self, uid: i64) -> Result<Option<User>> {
-        let row = sqlx::query("SELECT * FROM users WHERE uid = $1")
-            .bind(uid)
-            .fetch_optional(&self.pool)
-            .await?;
-
         let user = match row {
             Some(row) => Some(User {
                 uid: row.try_get(0)?,
@@ -78,90 +50,25 @@ impl UserDb for UserDatabase {
                 owner: row.try_get(2)?,
                 email_verified: row.try_get(3)?,
                 username: row.try_get(4)?,
+                locale: row.try_get(5)?,
+                created_at: row.try_get(6)?,
+                updated_at: row.try_get(7)?,
+                last_login_at: row.try_get(8)?,
+                email_verified_at: row.try_get(9)?,
             }),
             None => None,
         };
 
         Ok(user)
     }
-}
-
-/*
-pub struct Database {
-    pub pool: PgPool,
-}
-
-impl Database {
-    pub async fn new() -> Result<Self> {
-        let url = format!(
-            "postgresql://postgres:{}@localhost:{}/{}",
-            SECRETS.get("DB_PW").unwrap(),
-            SECRETS.get("DB_PORT").unwrap(),
-            SECRETS.get("DB_NAME").unwrap()
-        );
-        let pool = sqlx::postgres::PgPool::connect(&url).await?;
-        Ok(Self { pool })
-    }
-
-    pub async fn create_table(&self) -> Result<()> {
-        sqlx::query(
-            "CREATE TABLE IF NOT EXISTS users (
-                uid BIGINT PRIMARY KEY,
-                email TEXT,
-                owner BOOLEAN DEFAULT FALSE,
-                email_verified BOOLEAN DEFAULT FALSE,
-                username TEXT
-            )",
-        )
-        .execute(&self.pool)
-        .await?;
-
-        Ok(())
-    }
-
-    pub async fn insert(&self, uid: i64, username: &str, email: &str) -> Result<()> {
-        let mut txn = self.pool.begin().await?;
-
-        sqlx::query(
-            "INSERT INTO users (
-                uid,
-                email,
-                username
-            ) VALUES ($1, $2, $3)",
-        )
-        .bind(uid)
-        .bind(email)
-        .bind(username)
-        .execute(&mut *txn)
-        .await?;
-
-        txn.commit().await?;
-
-        Ok(())
-    }
 
-    pub async fn read_by_uid(&self, uid: i64) -> Result<Option<User>> {
-        let row = sqlx::query("SELECT * FROM users WHERE uid = $1")
+    async fn update_locale(&self, uid: i64, locale: Option<&str>) -> Result<()> {
+        sqlx::query("UPDATE accounts SET locale = $1, updated_at = now() WHERE uid = $2")
+            .bind(locale)
             .bind(uid)
-            .fetch_optional(&self.pool)
+            .execute(&self.pool)
             .await?;
 
-        let user = match row {
-            Some(row) => Some(parse_auth_user_record(row)?),
-            None => None,
-        };
-
-        Ok(user)
+        Ok(())
     }
 }
-
-fn parse_auth_user_record(row: PgRow) -> Result<User> {
-    Ok(User {
-        uid: row.try_get(0)?,
-        email: row.try_get(1)?,
-        owner: row.try_get(2)?,
-        email_verified: row.try_get(3)?,
-        username: row.try_get(4)?,
-    })
-}
-*/
@@ -3,11 +3,45 @@ use sqlx::postgres::PgRow;
 use sqlx::Row;
 use sqlx::PgPool;
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+use crate::db::memory::auth::MemoryAuthDatabase;
+use crate::db::sqlite::auth::SqliteAuthDatabase;
+
+pub trait AuthDb {
+    async fn insert(&self, account: &NewAccount<'_>) -> Result<()>;
+    async fn read_by_username(&self, username: &str) -> Result<Option<AuthUser>>;
+    async fn read_by_uid(&self, uid: i64) -> Result<Option<AuthUser>>;
+    async fn read_by_email(&self, email: &str) -> Result<Option<AuthUser>>;
+    async fn read_unverified(&self) -> Result<Vec<AuthUser>>;
+    async fn delete_by_uid(&self, uid: i64) -> Result<()>;
+    async fn update_email_verification(&self, uid: i64, email_verification: bool) -> Result<()>;
+    async fn update_password(&self, uid: i64, password_hash: &str) -> Result<()>;
+    async fn update_last_login(&self, uid: i64) -> Result<()>;
+}
 
+repository! {
+    Database: AuthDb {
+        postgres: PgAuthDatabase,
+        sqlite: SqliteAuthDatabase,
+        memory: MemoryAuthDatabase,
+        fn insert(&self, account: &NewAccount<'_>) -> Result<()>;
+        #[retry]
+        fn read_by_username(&self, username: &str) -> Result<Option<AuthUser>>;
+        #[retry]
+        fn read_by_uid(&self, uid: i64) -> Result<Option<AuthUser>>;
+        #[retry]
+        fn read_by_email(&self, email: &str) -> Result<Option<AuthUser>>;
+        #[retry]
+        fn read_unverified(&self) -> Result<Vec<AuthUser>>;
+        fn delete_by_uid(&self, uid: i64) -> Result<()>;
+        fn update_email_verification(&self, uid: i64, email_verification: bool) -> Result<()>;
+        fn update_password(&self, uid: i64, password_hash: &str) -> Result<()>;
+        fn update_last_login(&self, uid: i64) -> Result<()>;
+    }
+}
 
-use crate::secrets::SECRETS;
-
-pub struct Database {
+pub struct PgAuthDatabase {
     pub pool: PgPool
 } 
 
@@ -17,59 +51,67 @@ pub struct AuthUser {
     pub email: String,
     pub email_verified: bool,
     pub username: String,
-    pub password_hash: String
+    pub password_hash: String,
+    pub created_at: DateTime<Utc>
 }
 
-impl Database {
-    pub async fn new() -> Result<Self> {
-        let url = format!(
-            "postgresql://postgres:{}@localhost:{}/{}",
-            SECRETS.get("DB_PW").unwrap(),
-            SECRETS.get("DB_PORT").unwrap(),
-            SECRETS.get("DB_NAME").unwrap()
-        );
-        let pool = sqlx::postgres::PgPool::connect(&url).await?;
-        Ok(Self { pool })
-    }
+// everything needed to create an account, see Database::insert
+pub struct NewAccount<'a> {
+    pub uid: i64,
+    pub username: &'a str,
+    pub email: &'a str,
+    pub password_hash: &'a str,
+    pub owner: bool,
+    pub locale: Option<&'a str>
+}
 
-    pub async fn create_table(&self) -> Result<()> {
-        sqlx::query(
-            "CREATE TABLE IF NOT EXISTS auth_users (
-                uid BIGINT PRIMARY KEY,
-                email TEXT,
-                email_verified BOOLEAN DEFAULT FALSE,
-                username TEXT,
-                password_hash TEXT
-            )"
-        )
-        .execute(&self.pool)
-        .await?;
-        Ok(())
+// the columns in the order parse_auth_user_record expects them
+const SELECT_AUTH_USER: &str = "SELECT a.uid, a.email, a.email_verified, a.username, c.password_hash,
+        a.created_at
+    FROM accounts a
+    JOIN credentials c ON c.uid = a.uid";
+
+impl PgAuthDatabase {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
     }
+}
 
-    pub async fn insert(&self, uid: i64, username: &str, password_hash: &str, email: &str) -> Result<()> {
+impl AuthDb for PgAuthDatabase {
+    // creates the account together with its credentials, either both rows are written or none
+    async fn insert(&self, account: &NewAccount<'_>) -> Result<()> {
         let mut txn = self.pool.begin().await?;
 
-        sqlx::query("INSERT INTO auth_users (
+        sqlx::query("INSERT INTO accounts (
             uid,
-            email,
             username,
-            password_hash
-        ) VALUES ($1, $2, $3, $4)")
-        .bind(uid)
-        .bind(email)
-        .bind(username)
-        .bind(password_hash)
+            email,
+            owner,
+            locale,
+            created_at,
+            updated_at
+        ) VALUES ($1, $2, $3, $4, $5, now(), now())")
+        .bind(account.uid)
+        .bind(account.username)
+        .bind(account.email)
+        .bind(account.owner)
+        .bind(account.locale)
         .execute(&mut *txn)
         .await?;
+
+        sqlx::query("INSERT INTO credentials (uid, password_hash) VALUES ($1, $2)")
+            .bind(account.uid)
+            .bind(account.password_hash)
+            .execute(&mut *txn)
+            .await?;
         
         txn.commit().await?;
 
         Ok(())
     }
 
-    pub async fn read_by_username(&self, username: &str) -> Result<Option<AuthUser>> {
-        let row = sqlx::query("SELECT * FROM auth_users WHERE username = $1")
+    async fn read_by_username(&self, username: &str) -> Result<Option<AuthUser>> {
+        let row = sqlx::query(&format!("{} WHERE a.username = $1", SELECT_AUTH_USER))
             .bind(username)
             .fetch_optional(&self.pool)
             .await?;
@@ -82,8 +124,8 @@ impl Database {
         Ok(user)
     }
 
-    pub async fn read_by_uid(&self, uid: i64) -> Result<Option<AuthUser>> {
-        let row = sqlx::query("SELECT * FROM auth_users WHERE uid = $1")
+    async fn read_by_uid(&self, uid: i64) -> Result<Option<AuthUser>> {
+        let row = sqlx::query(&format!("{} WHERE a.uid = $1", SELECT_AUTH_USER))
             .bind(uid)
             .fetch_optional(&self.pool)
             .await?;
@@ -96,8 +138,8 @@ impl Database {
         Ok(user)
     }
 
-    pub async fn read_by_email(&self, email: &str) -> Result<Option<AuthUser>> {
-        let row = sqlx::query("SELECT * FROM auth_users WHERE email = $1")
+    async fn read_by_email(&self, email: &str) -> Result<Option<AuthUser>> {
+        let row = sqlx::query(&format!("{} WHERE a.email = $1", SELECT_AUTH_USER))
             .bind(email)
             .fetch_optional(&self.pool)
             .await?;
@@ -110,41 +152,67 @@ impl Database {
         Ok(user)
     }
 
-    pub async fn update_email_verification(&self, uid: i64, email_verification: bool) -> Result<()> {
-        let mut txn = self.pool.begin().await?;
+    async fn read_unverified(&self) -> Result<Vec<AuthUser>> {
+        let rows = sqlx::query(&format!("{} WHERE a.email_verified = FALSE", SELECT_AUTH_USER))
+            .fetch_all(&self.pool)
+            .await?;
 
-        sqlx::query("UPDATE auth_users SET email_verified = $1 WHERE uid = $2")
-            .bind(email_verification)
+        rows.into_iter().map(parse_auth_user_record).collect()
+    }
+
+    // removes the account, its credentials, tokens and cloudthemes go with it through ON DELETE CASCADE
+    async fn delete_by_uid(&self, uid: i64) -> Result<()> {
+        sqlx::query("DELETE FROM accounts WHERE uid = $1")
             .bind(uid)
-            .execute(&mut *txn)
+            .execute(&self.pool)
             .await?;
 
-        // also update the users db 
+        Ok(())
+    }
 
-        sqlx::query("UPDATE users SET email_verified = $1 WHERE uid = $2")
+    async fn update_email_verification(&self, uid: i64, email_verification: bool) -> Result<()> {
+        // the first verification is kept, unverifying forgets it
+        sqlx::query("UPDATE accounts SET
+            email_verified = $1,
+            email_verified_at = CASE WHEN $1 THEN COALESCE(email_verified_at, now()) END,
+            updated_at = now()
+        WHERE uid = $2")
             .bind(email_verification)
             .bind(uid)
-            .execute(&mut *txn)
+            .execute(&self.pool)
             .await?;
 
-        txn.commit().await?;
-
         Ok(())
     }
 
-    pub async fn update_password(&self, uid: i64, password_hash: &str) -> Result<()> {
+    async fn update_password(&self, uid: i64, password_hash: &str) -> Result<()> {
         let mut txn = self.pool.begin().await?;
 
-        sqlx::query("UPDATE auth_users SET password_hash = $1 WHERE uid = $2")
+        sqlx::query("UPDATE credentials SET password_hash = $1 WHERE uid = $2")
             .bind(password_hash)
             .bind(uid)
             .execute(&mut *txn)
             .await?;
 
+        sqlx::query("UPDATE accounts SET updated_at = now() WHERE uid = $1")
+            .bind(uid)
+            .execute(&mut *txn)
+            .await?;
+
         txn.commit().await?;
 
         Ok(())
     }
+
+    // only the login time, a login isn't a change of the account so updated_at stays
+    async fn update_last_login(&self, uid: i64) -> Result<()> {
+        sqlx::query("UPDATE accounts SET last_login_at = now() WHERE uid = $1")
+            .bind(uid)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
 }
 
 fn parse_auth_user_record(row: PgRow) -> Result<AuthUser> {
@@ -153,6 +221,7 @@ fn parse_auth_user_record(row: PgRow) -> Result<AuthUser> {
         email: row.try_get(1)?,
         email_verified: row.try_get(2)?,
         username: row.try_get(3)?,
-        password_hash: row.try_get(4)?
+        password_hash: row.try_get(4)?,
+        created_at: row.try_get(5)?
     })
 }
\ No newline at end of file
@@ -8,9 +8,31 @@ use sqlx::Row;
 use sqlx::PgPool;
 use anyhow::Result;
 
-use crate::secrets::SECRETS;
+use crate::db::memory::tokens::MemoryTokenDatabase;
+use crate::db::sqlite::tokens::SqliteTokenDatabase;
 
-pub struct Database {
+
+pub trait TokenDb {
+    async fn insert(&self, user_id: i64, jti: &str, expires_at: DateTime<Utc>) -> Result<()>;
+    async fn read_by_uid(&self, user_id: i64) -> Result<Vec<String>>;
+    async fn delete_expired(&self) -> Result<u64>;
+    async fn delete_by_uid(&self, user_id: i64) -> Result<()>;
+}
+
+repository! {
+    Database: TokenDb {
+        postgres: PgTokenDatabase,
+        sqlite: SqliteTokenDatabase,
+        memory: MemoryTokenDatabase,
+        fn insert(&self, user_id: i64, jti: &str, expires_at: DateTime<Utc>) -> Result<()>;
+        #[retry]
+        fn read_by_uid(&self, user_id: i64) -> Result<Vec<String>>;
+        fn delete_expired(&self) -> Result<u64>;
+        fn delete_by_uid(&self, user_id: i64) -> Result<()>;
+    }
+}
+
+pub struct PgTokenDatabase {
     pub pool: PgPool
 } 
 
@@ -21,33 +43,14 @@ pub struct AuthTokens {
     pub _expires_at: DateTime<Utc>
 }
 
-impl Database {
-    pub async fn new() -> Result<Self> {
-        let url = format!(
-            "postgresql://postgres:{}@localhost:{}/{}",
-            SECRETS.get("DB_PW").unwrap(),
-            SECRETS.get("DB_PORT").unwrap(),
-            SECRETS.get("DB_NAME").unwrap()
-        );
-        let pool = sqlx::postgres::PgPool::connect(&url).await?;
-        Ok(Self { pool })
-    }
-
-    pub async fn create_table(&self) -> Result<()> {
-        sqlx::query(
-            "CREATE TABLE IF NOT EXISTS auth_tokens (
-                jti TEXT PRIMARY KEY,
-                uid BIGINT NOT NULL,
-                expires_at TIMESTAMPTZ NOT NULL
-            )"
-        )
-        .execute(&self.pool)
-        .await?;
-        
-        Ok(())
+impl PgTokenDatabase {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
     }
+}
 
-    pub async fn insert(&self, user_id: i64, jti: &str, expires_at: DateTime<Utc>) -> Result<()> {
+impl TokenDb for PgTokenDatabase {
+    async fn insert(&self, user_id: i64, jti: &str, expires_at: DateTime<Utc>) -> Result<()> {
         let mut txn = self.pool.begin().await?;
 
         sqlx::query(
@@ -68,7 +71,7 @@ impl Database {
         Ok(())
     }
 
-    pub async fn read_by_uid(&self, user_id: i64) -> Result<Vec<String>> {
+    async fn read_by_uid(&self, user_id: i64) -> Result<Vec<String>> {
         let results = sqlx::query(
             "SELECT * FROM auth_tokens WHERE uid = $1 AND expires_at > NOW()"
         )
@@ -76,10 +79,21 @@ impl Database {
         .fetch_all(&self.pool)
         .await?;
 
-        Ok(results.into_iter().map(|record| parse_auth_tokens(record).unwrap().jti).collect())
+        results
+            .into_iter()
+            .map(|record| parse_auth_tokens(record).map(|tokens| tokens.jti))
+            .collect()
+    }
+
+    async fn delete_expired(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM auth_tokens WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
     }
 
-    pub async fn delete_by_uid(&self, user_id: i64) -> Result<()> {
+    async fn delete_by_uid(&self, user_id: i64) -> Result<()> {
 
         let mut txn = self.pool.begin().await?;
 
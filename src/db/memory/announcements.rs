@@ -0,0 +1,100 @@
+use anyhow::Result;
+
+use super::MemoryStore;
+use crate::db::api::announcements::AnnouncementDb;
+use crate::models::api::announcements::{Announcement, Audience, Recipient};
+use crate::util::snowflake::generate_uid;
+
+pub struct MemoryAnnouncementDatabase {
+    store: MemoryStore,
+}
+
+impl MemoryAnnouncementDatabase {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+impl AnnouncementDb for MemoryAnnouncementDatabase {
+    async fn insert(
+        &self,
+        created_by: i64,
+        subject: &str,
+        body: &str,
+        audience: Audience,
+        send_email: bool,
+    ) -> Result<Announcement> {
+        let announcement = Announcement {
+            id: generate_uid(),
+            created_by,
+            subject: subject.to_string(),
+            body: body.to_string(),
+            audience,
+            send_email,
+            email_cursor: 0,
+            email_done: !send_email,
+            created_at: self.store.now(),
+        };
+
+        self.store
+            .tables()
+            .announcements
+            .insert(announcement.id, announcement.clone());
+
+        Ok(announcement)
+    }
+
+    async fn read_all(&self, limit: i64) -> Result<Vec<Announcement>> {
+        Ok(self
+            .store
+            .tables()
+            .announcements
+            .values()
+            .rev()
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn read_pending_emails(&self) -> Result<Vec<Announcement>> {
+        Ok(self
+            .store
+            .tables()
+            .announcements
+            .values()
+            .filter(|announcement| !announcement.email_done)
+            .cloned()
+            .collect())
+    }
+
+    async fn read_recipients(
+        &self,
+        audience: Audience,
+        after_uid: i64,
+        limit: i64,
+    ) -> Result<Vec<Recipient>> {
+        let tables = self.store.tables();
+
+        Ok(tables
+            .accounts
+            .range(after_uid + 1..)
+            .map(|(_, account)| account)
+            .filter(|account| tables.in_audience(account, audience))
+            .take(limit.max(0) as usize)
+            .map(|account| Recipient {
+                uid: account.uid,
+                email: account.email.clone(),
+                username: account.username.clone(),
+            })
+            .collect())
+    }
+
+    async fn update_email_progress(&self, id: i64, cursor: i64, done: bool) -> Result<()> {
+        if let Some(announcement) = self.store.tables().announcements.get_mut(&id) {
+            announcement.email_cursor = cursor;
+            announcement.email_done = done;
+        }
+
+        Ok(())
+    }
+}
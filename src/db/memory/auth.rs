@@ -0,0 +1,131 @@
+use anyhow::{bail, Result};
+
+use super::{AccountRow, MemoryStore};
+use crate::db::auth::auth::{AuthDb, AuthUser, NewAccount};
+
+pub struct MemoryAuthDatabase {
+    store: MemoryStore,
+}
+
+impl MemoryAuthDatabase {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+
+    fn find(&self, matches: impl Fn(&AccountRow) -> bool) -> Option<AuthUser> {
+        self.store
+            .tables()
+            .accounts
+            .values()
+            .find(|account| matches(account))
+            .map(auth_user)
+    }
+}
+
+fn auth_user(account: &AccountRow) -> AuthUser {
+    AuthUser {
+        uid: account.uid,
+        email: account.email.clone(),
+        email_verified: account.email_verified,
+        username: account.username.clone(),
+        password_hash: account.password_hash.clone(),
+        created_at: account.created_at,
+    }
+}
+
+impl AuthDb for MemoryAuthDatabase {
+    async fn insert(&self, account: &NewAccount<'_>) -> Result<()> {
+        let mut tables = self.store.tables();
+        let now = self.store.now();
+
+        // the unique constraints of the accounts table
+        for existing in tables.accounts.values() {
+            if existing.uid == account.uid
+                || existing.username == account.username
+                || existing.email == account.email
+            {
+                bail!("an account with this uid, username or email already exists");
+            }
+        }
+
+        tables.accounts.insert(
+            account.uid,
+            AccountRow {
+                uid: account.uid,
+                username: account.username.to_string(),
+                email: account.email.to_string(),
+                email_verified: false,
+                owner: account.owner,
+                locale: account.locale.map(str::to_string),
+                password_hash: account.password_hash.to_string(),
+                created_at: now,
+                updated_at: now,
+                last_login_at: None,
+                email_verified_at: None,
+            },
+        );
+
+        Ok(())
+    }
+
+    async fn read_by_username(&self, username: &str) -> Result<Option<AuthUser>> {
+        Ok(self.find(|account| account.username == username))
+    }
+
+    async fn read_by_uid(&self, uid: i64) -> Result<Option<AuthUser>> {
+        Ok(self.store.tables().accounts.get(&uid).map(auth_user))
+    }
+
+    async fn read_by_email(&self, email: &str) -> Result<Option<AuthUser>> {
+        Ok(self.find(|account| account.email == email))
+    }
+
+    async fn read_unverified(&self) -> Result<Vec<AuthUser>> {
+        Ok(self
+            .store
+            .tables()
+            .accounts
+            .values()
+            .filter(|account| !account.email_verified)
+            .map(auth_user)
+            .collect())
+    }
+
+    async fn delete_by_uid(&self, uid: i64) -> Result<()> {
+        self.store.tables().delete_account(uid);
+
+        Ok(())
+    }
+
+    async fn update_email_verification(&self, uid: i64, email_verification: bool) -> Result<()> {
+        if let Some(account) = self.store.tables().accounts.get_mut(&uid) {
+            account.email_verified = email_verification;
+            // the first verification is kept, unverifying forgets it
+            account.email_verified_at = if email_verification {
+                account.email_verified_at.or(Some(self.store.now()))
+            } else {
+                None
+            };
+            account.updated_at = self.store.now();
+        }
+
+        Ok(())
+    }
+
+    async fn update_password(&self, uid: i64, password_hash: &str) -> Result<()> {
+        if let Some(account) = self.store.tables().accounts.get_mut(&uid) {
+            account.password_hash = password_hash.to_string();
+            account.updated_at = self.store.now();
+        }
+
+        Ok(())
+    }
+
+    async fn update_last_login(&self, uid: i64) -> Result<()> {
+        if let Some(account) = self.store.tables().accounts.get_mut(&uid) {
+            account.last_login_at = Some(self.store.now());
+        }
+
+        Ok(())
+    }
+}
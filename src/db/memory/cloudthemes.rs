@@ -0,0 +1,240 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Result};
+
+use super::MemoryStore;
+use crate::db::api::cloudthemes::{
+    cloudthemes::{CloudThemeConflict, CloudThemeDb},
+    status::CloudThemeStatusDb,
+};
+use crate::models::api::cloudtheme::{CloudTheme, CloudThemesStatus, Theme};
+use crate::util::snowflake::generate_uid;
+
+pub struct MemoryCloudThemeDatabase {
+    store: MemoryStore,
+}
+
+impl MemoryCloudThemeDatabase {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+impl CloudThemeDb for MemoryCloudThemeDatabase {
+    async fn insert(
+        &self,
+        uid: i64,
+        name: &str,
+        theme: &Theme,
+        active: bool,
+        limit: usize,
+    ) -> Result<CloudTheme> {
+        let mut tables = self.store.tables();
+
+        if !tables.accounts.contains_key(&uid) {
+            bail!("there is no account with the uid {}", uid);
+        }
+        if tables
+            .cloudthemes
+            .values()
+            .filter(|cloudtheme| cloudtheme.uid == uid)
+            .count()
+            >= limit
+        {
+            return Err(CloudThemeConflict::TooManyThemes(limit).into());
+        }
+        // the unique index on (uid, name)
+        if tables
+            .cloudthemes
+            .values()
+            .any(|cloudtheme| cloudtheme.uid == uid && cloudtheme.name == name)
+        {
+            return Err(CloudThemeConflict::NameTaken(name.to_string()).into());
+        }
+
+        if active {
+            deactivate(&mut tables.cloudthemes, uid);
+        }
+
+        let now = self.store.now();
+        let cloudtheme = CloudTheme {
+            id: generate_uid(),
+            uid,
+            name: name.to_string(),
+            active,
+            theme: theme.clone(),
+            created_at: now,
+            updated_at: now,
+        };
+        tables.cloudthemes.insert(cloudtheme.id, cloudtheme.clone());
+
+        Ok(cloudtheme)
+    }
+
+    async fn read_active(&self, uid: i64) -> Result<Option<CloudTheme>> {
+        Ok(self
+            .store
+            .tables()
+            .cloudthemes
+            .values()
+            .find(|cloudtheme| cloudtheme.uid == uid && cloudtheme.active)
+            .cloned())
+    }
+
+    async fn read_all(&self, uid: i64) -> Result<Vec<CloudTheme>> {
+        let mut cloudthemes: Vec<CloudTheme> = self
+            .store
+            .tables()
+            .cloudthemes
+            .values()
+            .filter(|cloudtheme| cloudtheme.uid == uid)
+            .cloned()
+            .collect();
+
+        cloudthemes.sort_by_key(|cloudtheme| (cloudtheme.created_at, cloudtheme.id));
+
+        Ok(cloudthemes)
+    }
+
+    async fn read_by_id(&self, uid: i64, id: i64) -> Result<Option<CloudTheme>> {
+        Ok(self
+            .store
+            .tables()
+            .cloudthemes
+            .get(&id)
+            .filter(|cloudtheme| cloudtheme.uid == uid)
+            .cloned())
+    }
+
+    async fn update_theme(&self, uid: i64, id: i64, theme: &Theme) -> Result<bool> {
+        let mut tables = self.store.tables();
+
+        match tables
+            .cloudthemes
+            .get_mut(&id)
+            .filter(|cloudtheme| cloudtheme.uid == uid)
+        {
+            Some(cloudtheme) => {
+                cloudtheme.theme = theme.clone();
+                cloudtheme.updated_at = self.store.now();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn rename(&self, uid: i64, id: i64, name: &str) -> Result<bool> {
+        let mut tables = self.store.tables();
+
+        if tables.cloudthemes.values().any(|cloudtheme| {
+            cloudtheme.uid == uid && cloudtheme.id != id && cloudtheme.name == name
+        }) {
+            return Err(CloudThemeConflict::NameTaken(name.to_string()).into());
+        }
+
+        match tables
+            .cloudthemes
+            .get_mut(&id)
+            .filter(|cloudtheme| cloudtheme.uid == uid)
+        {
+            Some(cloudtheme) => {
+                cloudtheme.name = name.to_string();
+                cloudtheme.updated_at = self.store.now();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn activate(&self, uid: i64, id: i64) -> Result<bool> {
+        let mut tables = self.store.tables();
+
+        if tables
+            .cloudthemes
+            .get(&id)
+            .is_none_or(|cloudtheme| cloudtheme.uid != uid)
+        {
+            return Ok(false);
+        }
+
+        deactivate(&mut tables.cloudthemes, uid);
+        if let Some(cloudtheme) = tables.cloudthemes.get_mut(&id) {
+            cloudtheme.active = true;
+        }
+
+        Ok(true)
+    }
+
+    async fn delete(&self, uid: i64, id: i64) -> Result<bool> {
+        let mut tables = self.store.tables();
+
+        if tables
+            .cloudthemes
+            .get(&id)
+            .is_none_or(|cloudtheme| cloudtheme.uid != uid)
+        {
+            return Ok(false);
+        }
+
+        if tables
+            .cloudthemes
+            .remove(&id)
+            .is_some_and(|deleted| deleted.active)
+        {
+            if let Some(cloudtheme) = tables
+                .cloudthemes
+                .values_mut()
+                .filter(|cloudtheme| cloudtheme.uid == uid)
+                .max_by_key(|cloudtheme| (cloudtheme.updated_at, cloudtheme.id))
+            {
+                cloudtheme.active = true;
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+fn deactivate(cloudthemes: &mut BTreeMap<i64, CloudTheme>, uid: i64) {
+    for cloudtheme in cloudthemes.values_mut() {
+        if cloudtheme.uid == uid {
+            cloudtheme.active = false;
+        }
+    }
+}
+
+pub struct MemoryCloudThemeStatusDatabase {
+    store: MemoryStore,
+}
+
+impl MemoryCloudThemeStatusDatabase {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+impl CloudThemeStatusDb for MemoryCloudThemeStatusDatabase {
+    async fn update_status(&self, uid: i64, enabled: bool) -> Result<()> {
+        let mut tables = self.store.tables();
+
+        if !tables.accounts.contains_key(&uid) {
+            bail!("there is no account with the uid {}", uid);
+        }
+
+        tables.cloudthemes_status.insert(uid, enabled);
+
+        Ok(())
+    }
+
+    async fn read_by_uid(&self, uid: i64) -> Result<CloudThemesStatus> {
+        let enabled = self
+            .store
+            .tables()
+            .cloudthemes_status
+            .get(&uid)
+            .copied()
+            .unwrap_or(false);
+
+        Ok(CloudThemesStatus { enabled })
+    }
+}
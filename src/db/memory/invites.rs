@@ -0,0 +1,129 @@
+use anyhow::{bail, Result};
+
+use super::MemoryStore;
+use crate::db::api::invites::InviteDb;
+use crate::models::api::invites::Invite;
+
+pub struct MemoryInviteDatabase {
+    store: MemoryStore,
+}
+
+impl MemoryInviteDatabase {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+impl InviteDb for MemoryInviteDatabase {
+    async fn insert(&self, invite: &Invite) -> Result<()> {
+        let mut tables = self.store.tables();
+
+        if tables.invites.contains_key(&invite.code) {
+            bail!("an invite with this code already exists");
+        }
+
+        tables.invites.insert(invite.code.clone(), invite.clone());
+
+        Ok(())
+    }
+
+    async fn read_by_creator(&self, created_by: i64) -> Result<Vec<Invite>> {
+        Ok(self
+            .store
+            .tables()
+            .invites
+            .values()
+            .filter(|invite| invite.created_by == created_by)
+            .cloned()
+            .collect())
+    }
+
+    async fn read_all(&self) -> Result<Vec<Invite>> {
+        Ok(self.store.tables().invites.values().cloned().collect())
+    }
+
+    async fn delete(&self, code: &str) -> Result<bool> {
+        Ok(self.store.tables().invites.remove(code).is_some())
+    }
+
+    async fn is_redeemable(&self, code: &str) -> Result<bool> {
+        let now = self.store.now();
+
+        Ok(self.store.tables().invites.get(code).is_some_and(|invite| {
+            invite.uses < invite.max_uses
+                && invite.expires_at.is_none_or(|expires_at| expires_at > now)
+        }))
+    }
+
+    async fn redeem(&self, code: &str) -> Result<Option<Invite>> {
+        let mut tables = self.store.tables();
+        let now = self.store.now();
+
+        match tables.invites.get_mut(code) {
+            Some(invite)
+                if invite.uses < invite.max_uses
+                    && invite.expires_at.is_none_or(|expires_at| expires_at > now) =>
+            {
+                invite.uses += 1;
+                Ok(Some(invite.clone()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn release(&self, code: &str) -> Result<()> {
+        if let Some(invite) = self.store.tables().invites.get_mut(code) {
+            if invite.uses > 0 {
+                invite.uses -= 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn record_redemption(&self, uid: i64, invite: &Invite) -> Result<()> {
+        let mut tables = self.store.tables();
+
+        // the foreign key on accounts
+        if !tables.accounts.contains_key(&uid) {
+            bail!("there is no account with the uid {}", uid);
+        }
+        tables.invite_redemptions.insert(uid, invite.code.clone());
+
+        Ok(())
+    }
+
+    async fn read_quota(&self, uid: i64) -> Result<i32> {
+        Ok(self
+            .store
+            .tables()
+            .invite_quotas
+            .get(&uid)
+            .copied()
+            .unwrap_or(0))
+    }
+
+    async fn set_quota(&self, uid: i64, remaining: i32) -> Result<()> {
+        self.store.tables().invite_quotas.insert(uid, remaining);
+
+        Ok(())
+    }
+
+    async fn take_quota(&self, uid: i64) -> Result<bool> {
+        match self.store.tables().invite_quotas.get_mut(&uid) {
+            Some(remaining) if *remaining > 0 => {
+                *remaining -= 1;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn give_back_quota(&self, uid: i64) -> Result<()> {
+        if let Some(remaining) = self.store.tables().invite_quotas.get_mut(&uid) {
+            *remaining += 1;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use chrono::{DateTime, Utc};
+
+use crate::util::clock::Clock;
+
+use crate::models::api::{
+    announcements::{Announcement, Audience},
+    cloudtheme::CloudTheme,
+    invites::Invite,
+    notification_preferences::NotificationPreferences,
+    notifications::Notification,
+    outbox::OutboxMessage,
+    signup_cleanup::SignupCleanupEntry,
+    theme_gallery::GalleryTheme,
+};
+
+pub mod announcements;
+pub mod auth;
+pub mod cloudthemes;
+pub mod invites;
+pub mod notification_preferences;
+pub mod notifications;
+pub mod outbox;
+pub mod signup_cleanup;
+pub mod theme_gallery;
+pub mod tokens;
+pub mod users;
+
+// keeps every table in memory, the repositories of one app state share it the way the postgres
+// ones share the pool. the lock is only held while a repository method runs, never across an
+// await, so the repositories can be used from any thread
+#[derive(Clone)]
+pub struct MemoryStore {
+    tables: Arc<Mutex<Tables>>,
+    // the clock of the app state, it stands in for now() in the queries
+    clock: Arc<dyn Clock>,
+}
+
+impl MemoryStore {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            tables: Arc::default(),
+            clock,
+        }
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    pub fn tables(&self) -> MutexGuard<'_, Tables> {
+        // a panic while the lock was held can't leave a table half written, every method
+        // changes the tables only after all its checks passed
+        self.tables.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// an account together with its credentials
+#[derive(Debug, Clone)]
+pub struct AccountRow {
+    pub uid: i64,
+    pub username: String,
+    pub email: String,
+    pub email_verified: bool,
+    pub owner: bool,
+    pub locale: Option<String>,
+    pub password_hash: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub last_login_at: Option<DateTime<Utc>>,
+    pub email_verified_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenRow {
+    pub uid: i64,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Default)]
+pub struct Tables {
+    // ordered by uid like the queries which page through accounts
+    pub accounts: BTreeMap<i64, AccountRow>,
+    // by jti
+    pub auth_tokens: HashMap<String, TokenRow>,
+    // by id
+    pub cloudthemes: BTreeMap<i64, CloudTheme>,
+    pub cloudthemes_status: HashMap<i64, bool>,
+    pub invites: BTreeMap<String, Invite>,
+    // the code each invited user registered with
+    pub invite_redemptions: HashMap<i64, String>,
+    pub invite_quotas: HashMap<i64, i32>,
+    pub email_outbox: BTreeMap<i64, OutboxMessage>,
+    pub notification_preferences: HashMap<i64, NotificationPreferences>,
+    pub signup_cleanup_log: BTreeMap<i64, SignupCleanupEntry>,
+    pub announcements: BTreeMap<i64, Announcement>,
+    pub notifications: BTreeMap<i64, Notification>,
+    // by id
+    pub theme_gallery: BTreeMap<i64, GalleryTheme>,
+    // (gallery id, uid) of every install
+    pub theme_gallery_installs: HashSet<(i64, i64)>,
+}
+
+impl Tables {
+    // what the foreign keys on accounts do in postgres
+    pub fn delete_account(&mut self, uid: i64) {
+        self.accounts.remove(&uid);
+        self.auth_tokens.retain(|_, token| token.uid != uid);
+        self.cloudthemes
+            .retain(|_, cloudtheme| cloudtheme.uid != uid);
+        self.cloudthemes_status.remove(&uid);
+        self.invite_redemptions.remove(&uid);
+        self.invite_quotas.remove(&uid);
+        self.notification_preferences.remove(&uid);
+        self.notifications
+            .retain(|_, notification| notification.uid != uid);
+        self.announcements
+            .retain(|_, announcement| announcement.created_by != uid);
+        self.theme_gallery.retain(|_, entry| entry.uid != uid);
+        for entry in self.theme_gallery.values_mut() {
+            if entry.unpublished_by == Some(uid) {
+                entry.unpublished_by = None;
+            }
+        }
+        let theme_gallery = &self.theme_gallery;
+        self.theme_gallery_installs
+            .retain(|(id, installed_by)| *installed_by != uid && theme_gallery.contains_key(id));
+    }
+
+    // same as audience_filter in db::api::announcements
+    pub fn in_audience(&self, account: &AccountRow, audience: Audience) -> bool {
+        account.email_verified
+            && match audience {
+                Audience::All => true,
+                Audience::Owners => account.owner,
+                Audience::Cloudthemes => self
+                    .cloudthemes_status
+                    .get(&account.uid)
+                    .copied()
+                    .unwrap_or(false),
+            }
+    }
+}
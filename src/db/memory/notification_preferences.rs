@@ -0,0 +1,39 @@
+use anyhow::Result;
+
+use super::MemoryStore;
+use crate::db::api::notification_preferences::NotificationPreferencesDb;
+use crate::models::api::notification_preferences::NotificationPreferences;
+
+pub struct MemoryNotificationPreferencesDatabase {
+    store: MemoryStore,
+}
+
+impl MemoryNotificationPreferencesDatabase {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+impl NotificationPreferencesDb for MemoryNotificationPreferencesDatabase {
+    async fn read_by_uid(&self, uid: i64) -> Result<NotificationPreferences> {
+        Ok(self
+            .store
+            .tables()
+            .notification_preferences
+            .get(&uid)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn upsert(&self, uid: i64, preferences: &NotificationPreferences) -> Result<()> {
+        self.store.tables().notification_preferences.insert(
+            uid,
+            NotificationPreferences {
+                security: true,
+                ..preferences.clone()
+            },
+        );
+
+        Ok(())
+    }
+}
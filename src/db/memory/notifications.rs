@@ -0,0 +1,94 @@
+use anyhow::Result;
+use chrono::Utc;
+
+use super::MemoryStore;
+use crate::db::api::notifications::NotificationDb;
+use crate::models::api::announcements::Announcement;
+use crate::models::api::notifications::Notification;
+use crate::util::snowflake::generate_uid;
+
+pub struct MemoryNotificationDatabase {
+    store: MemoryStore,
+}
+
+impl MemoryNotificationDatabase {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+impl NotificationDb for MemoryNotificationDatabase {
+    async fn insert_announcement(&self, announcement: &Announcement) -> Result<u64> {
+        let mut tables = self.store.tables();
+
+        let audience: Vec<i64> = tables
+            .accounts
+            .values()
+            .filter(|account| tables.in_audience(account, announcement.audience))
+            .map(|account| account.uid)
+            .collect();
+
+        for uid in &audience {
+            let id = generate_uid();
+
+            tables.notifications.insert(
+                id,
+                Notification {
+                    id,
+                    uid: *uid,
+                    title: announcement.subject.clone(),
+                    body: announcement.body.clone(),
+                    announcement_id: Some(announcement.id),
+                    read_at: None,
+                    created_at: self.store.now(),
+                },
+            );
+        }
+
+        Ok(audience.len() as u64)
+    }
+
+    async fn read_by_uid(
+        &self,
+        uid: i64,
+        unread_only: bool,
+        limit: i64,
+    ) -> Result<Vec<Notification>> {
+        Ok(self
+            .store
+            .tables()
+            .notifications
+            .values()
+            .rev()
+            .filter(|notification| {
+                notification.uid == uid && (!unread_only || notification.read_at.is_none())
+            })
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn mark_read(&self, uid: i64, id: i64) -> Result<bool> {
+        match self.store.tables().notifications.get_mut(&id) {
+            Some(notification) if notification.uid == uid => {
+                notification.read_at.get_or_insert_with(Utc::now);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn mark_all_read(&self, uid: i64) -> Result<u64> {
+        let now = self.store.now();
+        let mut marked = 0;
+
+        for notification in self.store.tables().notifications.values_mut() {
+            if notification.uid == uid && notification.read_at.is_none() {
+                notification.read_at = Some(now);
+                marked += 1;
+            }
+        }
+
+        Ok(marked)
+    }
+}
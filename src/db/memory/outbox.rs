@@ -0,0 +1,174 @@
+use std::cmp::Reverse;
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+
+use super::MemoryStore;
+use crate::db::api::outbox::OutboxDb;
+use crate::mailer::Email;
+use crate::models::api::outbox::{
+    OutboxMessage, OutboxSummary, STATUS_DEAD, STATUS_PENDING, STATUS_SENT,
+};
+use crate::util::snowflake::generate_uid;
+
+pub struct MemoryOutboxDatabase {
+    store: MemoryStore,
+}
+
+impl MemoryOutboxDatabase {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+
+    fn update(&self, id: i64, change: impl FnOnce(&mut OutboxMessage)) {
+        if let Some(message) = self.store.tables().email_outbox.get_mut(&id) {
+            change(message);
+        }
+    }
+}
+
+impl OutboxDb for MemoryOutboxDatabase {
+    async fn enqueue(&self, email: &Email) -> Result<i64> {
+        let id = generate_uid();
+        let now = self.store.now();
+
+        self.store.tables().email_outbox.insert(
+            id,
+            OutboxMessage {
+                id,
+                recipient: email.to.clone(),
+                subject: email.subject.clone(),
+                html: email.html.clone(),
+                status: STATUS_PENDING.to_string(),
+                attempts: 0,
+                next_attempt_at: now,
+                last_error: None,
+                created_at: now,
+                sent_at: None,
+                text: email.text.clone(),
+                unsubscribe_url: email.unsubscribe_url.clone(),
+            },
+        );
+
+        Ok(id)
+    }
+
+    // the same lease as the postgres outbox, the claimed messages are due again in 5 minutes
+    async fn claim_due(&self, limit: i64) -> Result<Vec<OutboxMessage>> {
+        let now = self.store.now();
+        let mut tables = self.store.tables();
+
+        let mut due: Vec<&mut OutboxMessage> = tables
+            .email_outbox
+            .values_mut()
+            .filter(|message| message.status == STATUS_PENDING && message.next_attempt_at <= now)
+            .collect();
+
+        due.sort_by_key(|message| message.next_attempt_at);
+
+        Ok(due
+            .into_iter()
+            .take(limit.max(0) as usize)
+            .map(|message| {
+                message.next_attempt_at = now + Duration::minutes(5);
+                message.clone()
+            })
+            .collect())
+    }
+
+    async fn mark_sent(&self, id: i64) -> Result<()> {
+        self.update(id, |message| {
+            message.status = STATUS_SENT.to_string();
+            message.sent_at = Some(self.store.now());
+            message.last_error = None;
+        });
+
+        Ok(())
+    }
+
+    async fn mark_failed(
+        &self,
+        id: i64,
+        attempts: i32,
+        error: &str,
+        next_attempt_at: DateTime<Utc>,
+    ) -> Result<()> {
+        self.update(id, |message| {
+            message.attempts = attempts;
+            message.last_error = Some(error.to_string());
+            message.next_attempt_at = next_attempt_at;
+        });
+
+        Ok(())
+    }
+
+    async fn mark_dead(&self, id: i64, attempts: i32, error: &str) -> Result<()> {
+        self.update(id, |message| {
+            message.status = STATUS_DEAD.to_string();
+            message.attempts = attempts;
+            message.last_error = Some(error.to_string());
+        });
+
+        Ok(())
+    }
+
+    async fn postpone(&self, id: i64, next_attempt_at: DateTime<Utc>) -> Result<()> {
+        self.update(id, |message| message.next_attempt_at = next_attempt_at);
+
+        Ok(())
+    }
+
+    async fn count_sent_to(&self, recipient: &str, since: DateTime<Utc>) -> Result<i64> {
+        Ok(self
+            .store
+            .tables()
+            .email_outbox
+            .values()
+            .filter(|message| {
+                message.recipient == recipient
+                    && message.status == STATUS_SENT
+                    && message.sent_at.is_some_and(|sent_at| sent_at > since)
+            })
+            .count() as i64)
+    }
+
+    async fn read_failed(&self, status: &str, limit: i64) -> Result<Vec<OutboxSummary>> {
+        let mut messages: Vec<OutboxSummary> = self
+            .store
+            .tables()
+            .email_outbox
+            .values()
+            .filter(|message| message.status == status && message.attempts > 0)
+            .map(OutboxSummary::from)
+            .collect();
+
+        messages.sort_by_key(|message| Reverse(message.created_at));
+        messages.truncate(limit.max(0) as usize);
+
+        Ok(messages)
+    }
+
+    async fn retry(&self, id: i64) -> Result<bool> {
+        match self.store.tables().email_outbox.get_mut(&id) {
+            Some(message) if message.status != STATUS_SENT => {
+                message.status = STATUS_PENDING.to_string();
+                message.attempts = 0;
+                message.next_attempt_at = self.store.now();
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn delete_sent_before(&self, before: DateTime<Utc>) -> Result<u64> {
+        let mut tables = self.store.tables();
+
+        let count = tables.email_outbox.len();
+        tables.email_outbox.retain(|_, message| {
+            !(message.status == STATUS_SENT
+                && message.sent_at.is_some_and(|sent_at| sent_at < before))
+        });
+
+        Ok((count - tables.email_outbox.len()) as u64)
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+use super::MemoryStore;
+use crate::db::api::signup_cleanup::SignupCleanupDb;
+use crate::models::api::signup_cleanup::{SignupCleanupEntry, ACTION_REMINDER};
+use crate::util::snowflake::generate_uid;
+
+pub struct MemorySignupCleanupDatabase {
+    store: MemoryStore,
+}
+
+impl MemorySignupCleanupDatabase {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+impl SignupCleanupDb for MemorySignupCleanupDatabase {
+    async fn insert(
+        &self,
+        run_id: i64,
+        action: &str,
+        uid: i64,
+        username: &str,
+        reminder_day: Option<i32>,
+        dry_run: bool,
+    ) -> Result<()> {
+        let id = generate_uid();
+
+        self.store.tables().signup_cleanup_log.insert(
+            id,
+            SignupCleanupEntry {
+                id,
+                run_id,
+                action: action.to_string(),
+                uid,
+                username: username.to_string(),
+                reminder_day,
+                dry_run,
+                created_at: self.store.now(),
+            },
+        );
+
+        Ok(())
+    }
+
+    async fn sent_reminder_days(&self, uid: i64) -> Result<Vec<i32>> {
+        Ok(self
+            .store
+            .tables()
+            .signup_cleanup_log
+            .values()
+            .filter(|entry| entry.uid == uid && entry.action == ACTION_REMINDER && !entry.dry_run)
+            .filter_map(|entry| entry.reminder_day)
+            .collect())
+    }
+
+    async fn dry_run_logged_since(
+        &self,
+        uid: i64,
+        action: &str,
+        since: DateTime<Utc>,
+    ) -> Result<bool> {
+        Ok(self
+            .store
+            .tables()
+            .signup_cleanup_log
+            .values()
+            .any(|entry| {
+                entry.uid == uid
+                    && entry.action == action
+                    && entry.dry_run
+                    && entry.created_at >= since
+            }))
+    }
+
+    async fn read_recent(&self, limit: i64) -> Result<Vec<SignupCleanupEntry>> {
+        Ok(self
+            .store
+            .tables()
+            .signup_cleanup_log
+            .values()
+            .rev()
+            .take(limit.max(0) as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn read_by_run(&self, run_id: i64) -> Result<Vec<SignupCleanupEntry>> {
+        Ok(self
+            .store
+            .tables()
+            .signup_cleanup_log
+            .values()
+            .filter(|entry| entry.run_id == run_id)
+            .cloned()
+            .collect())
+    }
+}
@@ -0,0 +1,210 @@
+use std::cmp::Reverse;
+
+use anyhow::{bail, Result};
+
+use super::{MemoryStore, Tables};
+use crate::db::api::theme_gallery::{GalleryConflict, NewGalleryEntry, ThemeGalleryDb};
+use crate::models::api::theme_gallery::{GallerySort, GalleryTheme};
+use crate::util::snowflake::generate_uid;
+
+pub struct MemoryThemeGalleryDatabase {
+    store: MemoryStore,
+}
+
+impl MemoryThemeGalleryDatabase {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+impl ThemeGalleryDb for MemoryThemeGalleryDatabase {
+    async fn insert(&self, entry: &NewGalleryEntry<'_>, limit: usize) -> Result<GalleryTheme> {
+        let NewGalleryEntry {
+            uid,
+            source_id,
+            share_code,
+            title,
+            description,
+            listed,
+            theme,
+        } = *entry;
+        let mut tables = self.store.tables();
+
+        let Some(account) = tables.accounts.get(&uid) else {
+            bail!("there is no account with the uid {}", uid);
+        };
+        let entries = || {
+            tables
+                .theme_gallery
+                .values()
+                .filter(|entry| entry.uid == uid)
+        };
+
+        if entries().any(|entry| entry.source_id == source_id && entry.moderated()) {
+            return Err(GalleryConflict::Moderated.into());
+        }
+        if entries().filter(|entry| entry.published).count() >= limit {
+            return Err(GalleryConflict::TooManyPublished(limit).into());
+        }
+        // the unique constraint on share_code
+        if tables
+            .theme_gallery
+            .values()
+            .any(|entry| entry.share_code == share_code)
+        {
+            bail!(
+                "there already is an entry with the share code {}",
+                share_code
+            );
+        }
+
+        let entry = GalleryTheme {
+            id: generate_uid(),
+            share_code: share_code.to_string(),
+            uid,
+            source_id,
+            author: account.username.clone(),
+            title: title.to_string(),
+            description: description.to_string(),
+            listed,
+            theme: theme.clone(),
+            installs: 0,
+            published: true,
+            unpublished_by: None,
+            unpublish_reason: None,
+            unpublished_at: None,
+            created_at: self.store.now(),
+        };
+        tables.theme_gallery.insert(entry.id, entry.clone());
+
+        Ok(entry)
+    }
+
+    async fn read_gallery(
+        &self,
+        sort: GallerySort,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<GalleryTheme>> {
+        let tables = self.store.tables();
+
+        Ok(
+            sorted(&tables, |entry| entry.published && entry.listed, sort)
+                .into_iter()
+                .skip(offset.max(0) as usize)
+                .take(limit.max(0) as usize)
+                .collect(),
+        )
+    }
+
+    async fn read_by_share_code(&self, share_code: &str) -> Result<Option<GalleryTheme>> {
+        let tables = self.store.tables();
+
+        Ok(tables
+            .theme_gallery
+            .values()
+            .find(|entry| entry.share_code == share_code)
+            .map(|entry| with_author(&tables, entry)))
+    }
+
+    async fn read_by_uid(&self, uid: i64) -> Result<Vec<GalleryTheme>> {
+        let tables = self.store.tables();
+
+        Ok(sorted(
+            &tables,
+            |entry| entry.uid == uid,
+            GallerySort::Newest,
+        ))
+    }
+
+    async fn read_all(&self, limit: i64) -> Result<Vec<GalleryTheme>> {
+        let tables = self.store.tables();
+
+        Ok(sorted(&tables, |_| true, GallerySort::Newest)
+            .into_iter()
+            .take(limit.max(0) as usize)
+            .collect())
+    }
+
+    async fn unpublish(&self, id: i64, by: i64, reason: Option<&str>) -> Result<bool> {
+        let mut tables = self.store.tables();
+
+        match tables
+            .theme_gallery
+            .get_mut(&id)
+            .filter(|entry| entry.published)
+        {
+            Some(entry) => {
+                entry.published = false;
+                entry.unpublished_by = Some(by);
+                entry.unpublish_reason = reason.map(str::to_string);
+                entry.unpublished_at = Some(self.store.now());
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn republish(&self, id: i64) -> Result<bool> {
+        let mut tables = self.store.tables();
+
+        match tables.theme_gallery.get_mut(&id) {
+            Some(entry) => {
+                entry.published = true;
+                entry.unpublished_by = None;
+                entry.unpublish_reason = None;
+                entry.unpublished_at = None;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn record_install(&self, id: i64, uid: i64) -> Result<bool> {
+        let mut tables = self.store.tables();
+
+        if !tables.theme_gallery.contains_key(&id) {
+            bail!("there is no gallery entry with the id {}", id);
+        }
+        if !tables.theme_gallery_installs.insert((id, uid)) {
+            return Ok(false);
+        }
+        if let Some(entry) = tables.theme_gallery.get_mut(&id) {
+            entry.installs += 1;
+        }
+
+        Ok(true)
+    }
+}
+
+// the author is the current username, like the join on accounts
+fn with_author(tables: &Tables, entry: &GalleryTheme) -> GalleryTheme {
+    let mut entry = entry.clone();
+    if let Some(account) = tables.accounts.get(&entry.uid) {
+        entry.author = account.username.clone();
+    }
+    entry
+}
+
+// same as gallery_order in db::api::theme_gallery
+fn sorted(
+    tables: &Tables,
+    filter: impl Fn(&GalleryTheme) -> bool,
+    sort: GallerySort,
+) -> Vec<GalleryTheme> {
+    let mut entries: Vec<GalleryTheme> = tables
+        .theme_gallery
+        .values()
+        .filter(|entry| filter(entry))
+        .map(|entry| with_author(tables, entry))
+        .collect();
+
+    match sort {
+        GallerySort::Newest => entries.sort_by_key(|entry| Reverse((entry.created_at, entry.id))),
+        GallerySort::MostInstalled => {
+            entries.sort_by_key(|entry| Reverse((entry.installs, entry.created_at, entry.id)))
+        }
+    }
+
+    entries
+}
@@ -0,0 +1,70 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+
+use super::{MemoryStore, TokenRow};
+use crate::db::auth::tokens::TokenDb;
+
+pub struct MemoryTokenDatabase {
+    store: MemoryStore,
+}
+
+impl MemoryTokenDatabase {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+impl TokenDb for MemoryTokenDatabase {
+    async fn insert(&self, user_id: i64, jti: &str, expires_at: DateTime<Utc>) -> Result<()> {
+        let mut tables = self.store.tables();
+
+        if !tables.accounts.contains_key(&user_id) {
+            bail!("there is no account with the uid {}", user_id);
+        }
+        if tables.auth_tokens.contains_key(jti) {
+            bail!("a token with this jti already exists");
+        }
+
+        tables.auth_tokens.insert(
+            jti.to_string(),
+            TokenRow {
+                uid: user_id,
+                expires_at,
+            },
+        );
+
+        Ok(())
+    }
+
+    async fn read_by_uid(&self, user_id: i64) -> Result<Vec<String>> {
+        let now = self.store.now();
+
+        Ok(self
+            .store
+            .tables()
+            .auth_tokens
+            .iter()
+            .filter(|(_, token)| token.uid == user_id && token.expires_at > now)
+            .map(|(jti, _)| jti.clone())
+            .collect())
+    }
+
+    async fn delete_expired(&self) -> Result<u64> {
+        let now = self.store.now();
+        let mut tables = self.store.tables();
+
+        let before = tables.auth_tokens.len();
+        tables.auth_tokens.retain(|_, token| token.expires_at > now);
+
+        Ok((before - tables.auth_tokens.len()) as u64)
+    }
+
+    async fn delete_by_uid(&self, user_id: i64) -> Result<()> {
+        self.store
+            .tables()
+            .auth_tokens
+            .retain(|_, token| token.uid != user_id);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,41 @@
+use anyhow::Result;
+
+use super::MemoryStore;
+use crate::db::api::users::UserDb;
+use crate::models::api::users::User;
+
+pub struct MemoryUserDatabase {
+    store: MemoryStore,
+}
+
+impl MemoryUserDatabase {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+impl UserDb for MemoryUserDatabase {
+    async fn read_by_uid(&self, uid: i64) -> Result<Option<User>> {
+        Ok(self.store.tables().accounts.get(&uid).map(|account| User {
+            uid: account.uid,
+            email: account.email.clone(),
+            owner: account.owner,
+            email_verified: account.email_verified,
+            username: account.username.clone(),
+            locale: account.locale.clone(),
+            created_at: account.created_at,
+            updated_at: account.updated_at,
+            last_login_at: account.last_login_at,
+            email_verified_at: account.email_verified_at,
+        }))
+    }
+
+    async fn update_locale(&self, uid: i64, locale: Option<&str>) -> Result<()> {
+        if let Some(account) = self.store.tables().accounts.get_mut(&uid) {
+            account.locale = locale.map(str::to_string);
+            account.updated_at = self.store.now();
+        }
+
+        Ok(())
+    }
+}
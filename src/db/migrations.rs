@@ -0,0 +1,311 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgPool, Row, SqliteConnection, SqlitePool};
+use std::time::Instant;
+
+// the schema lives in migrations/, every file is applied once, in order and in its own
+// transaction. the schema_migrations table remembers which ones ran together with a checksum,
+// so a migration which was edited after it ran is noticed instead of silently skipped.
+// migrations are never changed once released, changes go into a new file. every migration is
+// written for postgres and, in migrations/sqlite/, for sqlite
+
+use super::Backend;
+
+#[derive(Clone, Copy)]
+pub enum Dialect {
+    Postgres,
+    Sqlite,
+}
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub postgres: &'static str,
+    pub sqlite: &'static str,
+}
+
+impl Migration {
+    pub fn sql(&self, dialect: Dialect) -> &'static str {
+        match dialect {
+            Dialect::Postgres => self.postgres,
+            Dialect::Sqlite => self.sqlite,
+        }
+    }
+
+    pub fn checksum(&self, dialect: Dialect) -> String {
+        format!("{:x}", Sha256::digest(self.sql(dialect).as_bytes()))
+    }
+}
+
+macro_rules! migration {
+    ($version:literal, $name:literal) => {
+        Migration {
+            version: $version,
+            name: $name,
+            postgres: include_str!(concat!("../../migrations/", $name, ".sql")),
+            sqlite: include_str!(concat!("../../migrations/sqlite/", $name, ".sql")),
+        }
+    };
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    migration!(1, "0001_initial_schema"),
+    migration!(2, "0002_accounts"),
+    migration!(3, "0003_account_timestamps"),
+    migration!(4, "0004_named_cloudthemes"),
+    migration!(5, "0005_theme_gallery"),
+];
+
+// any number works, it only has to be the same for every instance of the backend
+const MIGRATION_LOCK_ID: i64 = 0x6163_6964_3473;
+
+#[derive(Debug)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub name: String,
+    pub applied_at: Option<DateTime<Utc>>,
+}
+
+struct AppliedMigration {
+    version: i64,
+    checksum: String,
+    applied_at: DateTime<Utc>,
+}
+
+async fn create_history_table(conn: &mut PgConnection) -> Result<()> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version BIGINT PRIMARY KEY,
+            name TEXT NOT NULL,
+            checksum TEXT NOT NULL,
+            applied_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            execution_ms BIGINT NOT NULL
+        )",
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn read_applied(conn: &mut PgConnection) -> Result<Vec<AppliedMigration>> {
+    let rows =
+        sqlx::query("SELECT version, checksum, applied_at FROM schema_migrations ORDER BY version")
+            .fetch_all(&mut *conn)
+            .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(AppliedMigration {
+                version: row.try_get(0)?,
+                checksum: row.try_get(1)?,
+                applied_at: row.try_get(2)?,
+            })
+        })
+        .collect()
+}
+
+// fails if the database was migrated by a build which knows migrations this one doesn't,
+// or if an applied migration was changed since
+fn check_history(applied: &[AppliedMigration], dialect: Dialect) -> Result<()> {
+    for entry in applied {
+        match MIGRATIONS.iter().find(|m| m.version == entry.version) {
+            Some(migration) if migration.checksum(dialect) != entry.checksum => bail!(
+                "migration {} ({}) was changed after it was applied",
+                migration.version,
+                migration.name
+            ),
+            Some(_) => (),
+            None => bail!(
+                "the database has migration {} applied which this build doesn't know, is it outdated?",
+                entry.version
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+// the migrations this build knows which aren't in `applied` yet, in order
+fn pending(applied: &[AppliedMigration]) -> impl Iterator<Item = &'static Migration> + '_ {
+    MIGRATIONS.iter().filter(|migration| {
+        !applied
+            .iter()
+            .any(|entry| entry.version == migration.version)
+    })
+}
+
+async fn apply_pending(conn: &mut PgConnection) -> Result<Vec<i64>> {
+    create_history_table(conn).await?;
+
+    let applied = read_applied(conn).await?;
+    check_history(&applied, Dialect::Postgres)?;
+
+    let mut newly_applied = Vec::new();
+
+    for migration in pending(&applied) {
+        let started = Instant::now();
+        let mut txn = sqlx::Connection::begin(&mut *conn).await?;
+
+        sqlx::raw_sql(migration.postgres).execute(&mut *txn).await?;
+
+        sqlx::query(
+            "INSERT INTO schema_migrations (version, name, checksum, execution_ms)
+            VALUES ($1, $2, $3, $4)",
+        )
+        .bind(migration.version)
+        .bind(migration.name)
+        .bind(migration.checksum(Dialect::Postgres))
+        .bind(started.elapsed().as_millis() as i64)
+        .execute(&mut *txn)
+        .await?;
+
+        txn.commit().await?;
+
+        println!(
+            "applied migration {} ({})",
+            migration.version, migration.name
+        );
+        newly_applied.push(migration.version);
+    }
+
+    Ok(newly_applied)
+}
+
+// several instances starting at the same time wait for each other through an advisory lock
+async fn run_postgres(pool: &PgPool) -> Result<Vec<i64>> {
+    let mut conn = pool.acquire().await?;
+
+    sqlx::query("SELECT pg_advisory_lock($1)")
+        .bind(MIGRATION_LOCK_ID)
+        .execute(&mut *conn)
+        .await?;
+
+    let result = apply_pending(&mut conn).await;
+
+    sqlx::query("SELECT pg_advisory_unlock($1)")
+        .bind(MIGRATION_LOCK_ID)
+        .execute(&mut *conn)
+        .await?;
+
+    result
+}
+
+// the history table of sqlite keeps applied_at as text, like every sqlite timestamp
+async fn create_sqlite_history_table(conn: &mut SqliteConnection) -> Result<()> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version BIGINT PRIMARY KEY,
+            name TEXT NOT NULL,
+            checksum TEXT NOT NULL,
+            applied_at TEXT NOT NULL,
+            execution_ms BIGINT NOT NULL
+        )",
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+async fn read_sqlite_applied(conn: &mut SqliteConnection) -> Result<Vec<AppliedMigration>> {
+    let rows =
+        sqlx::query("SELECT version, checksum, applied_at FROM schema_migrations ORDER BY version")
+            .fetch_all(&mut *conn)
+            .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(AppliedMigration {
+                version: row.try_get(0)?,
+                checksum: row.try_get(1)?,
+                applied_at: row.try_get(2)?,
+            })
+        })
+        .collect()
+}
+
+// sqlite has no advisory locks. it only lets one transaction write at a time though, and a
+// second instance which tries to apply the same migration fails on the primary key of the
+// history table and rolls back
+async fn run_sqlite(pool: &SqlitePool) -> Result<Vec<i64>> {
+    let mut conn = pool.acquire().await?;
+
+    create_sqlite_history_table(&mut conn).await?;
+
+    let applied = read_sqlite_applied(&mut conn).await?;
+    check_history(&applied, Dialect::Sqlite)?;
+
+    let mut newly_applied = Vec::new();
+
+    for migration in pending(&applied) {
+        let started = Instant::now();
+        let mut txn = sqlx::Connection::begin(&mut *conn).await?;
+
+        sqlx::raw_sql(migration.sqlite).execute(&mut *txn).await?;
+
+        sqlx::query(
+            "INSERT INTO schema_migrations (version, name, checksum, applied_at, execution_ms)
+            VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(migration.version)
+        .bind(migration.name)
+        .bind(migration.checksum(Dialect::Sqlite))
+        .bind(super::sqlite::now())
+        .bind(started.elapsed().as_millis() as i64)
+        .execute(&mut *txn)
+        .await?;
+
+        txn.commit().await?;
+
+        println!(
+            "applied migration {} ({})",
+            migration.version, migration.name
+        );
+        newly_applied.push(migration.version);
+    }
+
+    Ok(newly_applied)
+}
+
+// applies every pending migration, returns their versions
+pub async fn run(backend: &Backend) -> Result<Vec<i64>> {
+    match backend {
+        Backend::Postgres(pool) => run_postgres(pool).await,
+        Backend::Sqlite(pool) => run_sqlite(pool).await,
+        // the in-memory tables exist from the start and are gone on restart, nothing to migrate
+        Backend::Memory(_) => Ok(Vec::new()),
+    }
+}
+
+// every migration this build knows and when it was applied, None if it is still pending
+pub async fn status(backend: &Backend) -> Result<Vec<MigrationStatus>> {
+    let (applied, dialect) = match backend {
+        Backend::Postgres(pool) => {
+            let mut conn = pool.acquire().await?;
+            create_history_table(&mut conn).await?;
+            (read_applied(&mut conn).await?, Dialect::Postgres)
+        }
+        Backend::Sqlite(pool) => {
+            let mut conn = pool.acquire().await?;
+            create_sqlite_history_table(&mut conn).await?;
+            (read_sqlite_applied(&mut conn).await?, Dialect::Sqlite)
+        }
+        Backend::Memory(_) => bail!("the in-memory backend has no migrations"),
+    };
+
+    check_history(&applied, dialect)?;
+
+    Ok(MIGRATIONS
+        .iter()
+        .map(|migration| MigrationStatus {
+            version: migration.version,
+            name: migration.name.to_string(),
+            applied_at: applied
+                .iter()
+                .find(|entry| entry.version == migration.version)
+                .map(|entry| entry.applied_at),
+        })
+        .collect())
+}
@@ -1,27 +1,240 @@
+use crate::secrets::SECRETS;
+use anyhow::{anyhow, Context, Result};
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions, PgSslMode},
+    PgPool, SqlitePool,
+};
+use std::{str::FromStr, time::Duration};
+
+use memory::MemoryStore;
+
+// declares the repository type handlers and jobs work with for a Db trait. it holds the
+// implementation of the trait for the backend the app state was built with and forwards every
+// call to it through the circuit breaker, see resilience. methods marked #[retry] are idempotent
+// reads which are tried again after a transient failure, their arguments have to be Clone
+macro_rules! repository {
+    (@call $self:ident, [retry], $method:ident($($arg:ident),*)) => {
+        $crate::db::resilience::retry(&$self.breaker, || {
+            $(let $arg = $arg.clone();)*
+            async move { repository!(@dispatch $self, $method($($arg),*)) }
+        })
+        .await
+    };
+    (@call $self:ident, [], $method:ident($($arg:ident),*)) => {
+        $crate::db::resilience::guard(&$self.breaker, async {
+            repository!(@dispatch $self, $method($($arg),*))
+        })
+        .await
+    };
+    (@dispatch $self:ident, $method:ident($($arg:ident),*)) => {
+        match &$self.db {
+            $crate::db::Implementation::Postgres(db) => db.$method($($arg),*).await,
+            $crate::db::Implementation::Sqlite(db) => db.$method($($arg),*).await,
+            $crate::db::Implementation::Memory(db) => db.$method($($arg),*).await,
+        }
+    };
+    (
+        $name:ident: $db_trait:ident {
+            postgres: $postgres:ident,
+            sqlite: $sqlite:ident,
+            memory: $memory:ident,
+            $($(#[$marker:ident])? fn $method:ident(&self $(, $arg:ident: $arg_ty:ty)* $(,)?) -> $ret:ty;)*
+        }
+    ) => {
+        pub struct $name {
+            db: $crate::db::Implementation<$postgres, $sqlite, $memory>,
+            breaker: std::sync::Arc<$crate::db::resilience::CircuitBreaker>,
+        }
+
+        impl $name {
+            pub fn new(
+                backend: &$crate::db::Backend,
+                breaker: &std::sync::Arc<$crate::db::resilience::CircuitBreaker>,
+            ) -> Self {
+                let db = match backend {
+                    $crate::db::Backend::Postgres(pool) => {
+                        $crate::db::Implementation::Postgres($postgres::new(pool.clone()))
+                    }
+                    $crate::db::Backend::Sqlite(pool) => {
+                        $crate::db::Implementation::Sqlite($sqlite::new(pool.clone()))
+                    }
+                    $crate::db::Backend::Memory(store) => {
+                        $crate::db::Implementation::Memory($memory::new(store.clone()))
+                    }
+                };
+
+                Self {
+                    db,
+                    breaker: breaker.clone(),
+                }
+            }
+        }
+
+        impl $db_trait for $name {
+            $(
+                #[allow(clippy::clone_on_copy)]
+                async fn $method(&self $(, $arg: $arg_ty)*) -> $ret {
+                    repository!(@call self, [$($marker)?], $method($($arg),*))
+                }
+            )*
+        }
+    };
+}
+
 pub mod api;
 pub mod auth;
-use crate::secrets::SECRETS;
-use anyhow::Result;
-use once_cell::sync::Lazy;
-use sqlx::{Error, PgPool};
+pub mod memory;
+pub mod migrations;
+pub mod resilience;
+pub mod sqlite;
+
+// where the repositories keep their data. sqlite keeps everything in one file and needs nothing
+// installed, the in-memory backend lives as long as the app state and is meant for tests
+#[derive(Clone)]
+pub enum Backend {
+    Postgres(PgPool),
+    Sqlite(SqlitePool),
+    Memory(MemoryStore),
+}
+
+// the implementation of a repository, see repository!
+pub enum Implementation<P, S, M> {
+    Postgres(P),
+    Sqlite(S),
+    Memory(M),
+}
+
+impl Backend {
+    // the cheapest query there is, the readiness check uses it to see if the database answers
+    pub async fn ping(&self) -> Result<()> {
+        match self {
+            Backend::Postgres(pool) => {
+                sqlx::query("SELECT 1").execute(pool).await?;
+            }
+            Backend::Sqlite(pool) => {
+                sqlx::query("SELECT 1").execute(pool).await?;
+            }
+            Backend::Memory(_) => (),
+        }
+
+        Ok(())
+    }
+}
+
+const DEFAULT_POOL_MAX_CONNECTIONS: u32 = 10;
+const DEFAULT_POOL_MIN_CONNECTIONS: u32 = 0;
+const DEFAULT_ACQUIRE_TIMEOUT_SECS: u64 = 5;
+const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 10 * 60;
+const DEFAULT_MAX_LIFETIME_SECS: u64 = 30 * 60;
+
+// the default if `key` isn't set, a value which isn't a number stops the startup
+fn setting<T: std::str::FromStr>(key: &str, default: T) -> Result<T> {
+    match SECRETS.get(key) {
+        Some(value) => value
+            .parse()
+            .map_err(|_| anyhow!("{} is not a number, got '{}'", key, value)),
+        None => Ok(default),
+    }
+}
+
+const DEFAULT_DB_HOST: &str = "localhost";
+const DEFAULT_DB_USER: &str = "postgres";
+const DEFAULT_APPLICATION_NAME: &str = "acid4sigmas-backend";
+
+fn password() -> Result<Option<String>> {
+    if let Some(path) = SECRETS.get("DB_PASSWORD_FILE") {
+        let password = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read DB_PASSWORD_FILE {}", path))?;
+        // files written with echo or an editor end with a newline which isn't part of the password
+        return Ok(Some(password.trim_end_matches(['\r', '\n']).to_string()));
+    }
 
-pub struct Database {
-    pub pool: PgPool,
+    Ok(SECRETS.get("DB_PW").cloned())
 }
 
-impl Database {
-    pub async fn new() -> Result<Self> {
-        let url = format!(
-            "postgresql://postgres:{}@localhost:{}/{}",
-            SECRETS.get("DB_PW").unwrap(),
-            SECRETS.get("DB_PORT").unwrap(),
-            SECRETS.get("DB_NAME").unwrap()
-        );
-        let pool = sqlx::postgres::PgPool::connect(&url).await?;
-        Ok(Self { pool })
+// the settings are handed to sqlx one by one instead of being formatted into a url, so the
+// password can contain characters like @, / or # without being escaped. DATABASE_URL is the
+// base if it is set, the single DB_* settings override its parts
+pub fn connect_options() -> Result<PgConnectOptions> {
+    let mut options = match SECRETS.get("DATABASE_URL") {
+        Some(url) => PgConnectOptions::from_str(url).context("DATABASE_URL is invalid")?,
+        None => PgConnectOptions::new_without_pgpass()
+            .host(DEFAULT_DB_HOST)
+            .username(DEFAULT_DB_USER),
+    };
+
+    if let Some(host) = SECRETS.get("DB_HOST") {
+        options = options.host(host);
+    }
+    if let Some(port) = SECRETS.get("DB_PORT") {
+        options = options.port(port.parse().context("DB_PORT is not a port")?);
+    }
+    if let Some(user) = SECRETS.get("DB_USER") {
+        options = options.username(user);
+    }
+    if let Some(password) = password()? {
+        options = options.password(&password);
+    }
+    if let Some(name) = SECRETS.get("DB_NAME") {
+        options = options.database(name);
+    }
+    if let Some(mode) = SECRETS.get("DB_SSL_MODE") {
+        options = options.ssl_mode(PgSslMode::from_str(mode).context(
+            "DB_SSL_MODE must be disable, allow, prefer, require, verify-ca or verify-full",
+        )?);
+    }
+    if let Some(path) = SECRETS.get("DB_SSL_ROOT_CERT") {
+        options = options.ssl_root_cert(path);
     }
 
-    pub async fn get_pool() -> Result<PgPool> {
-        Database::new().await.map(|db| db.pool)
+    // shows up in pg_stat_activity, so the connections of the backend are easy to tell apart
+    match SECRETS.get("DB_APPLICATION_NAME") {
+        Some(name) => options = options.application_name(name),
+        None if options.get_application_name().is_none() => {
+            options = options.application_name(DEFAULT_APPLICATION_NAME)
+        }
+        None => (),
     }
+
+    // a query running longer than this is cancelled by postgres, 0 turns the limit off
+    if let Some(timeout) = SECRETS.get("DB_STATEMENT_TIMEOUT_MS") {
+        let timeout: u64 = timeout
+            .parse()
+            .context("DB_STATEMENT_TIMEOUT_MS is not a number")?;
+        options = options.options([("statement_timeout", timeout.to_string())]);
+    }
+
+    Ok(options)
+}
+
+// the one pool of the backend, every repository borrows its connections from it
+pub async fn connect() -> Result<PgPool> {
+    let options = connect_options()?;
+
+    let pool = PgPoolOptions::new()
+        .max_connections(setting(
+            "DB_POOL_MAX_CONNECTIONS",
+            DEFAULT_POOL_MAX_CONNECTIONS,
+        )?)
+        .min_connections(setting(
+            "DB_POOL_MIN_CONNECTIONS",
+            DEFAULT_POOL_MIN_CONNECTIONS,
+        )?)
+        // how long a request waits for a free connection before it fails
+        .acquire_timeout(Duration::from_secs(setting(
+            "DB_ACQUIRE_TIMEOUT_SECS",
+            DEFAULT_ACQUIRE_TIMEOUT_SECS,
+        )?))
+        .idle_timeout(Duration::from_secs(setting(
+            "DB_IDLE_TIMEOUT_SECS",
+            DEFAULT_IDLE_TIMEOUT_SECS,
+        )?))
+        .max_lifetime(Duration::from_secs(setting(
+            "DB_MAX_LIFETIME_SECS",
+            DEFAULT_MAX_LIFETIME_SECS,
+        )?))
+        .connect_with(options)
+        .await?;
+
+    Ok(pool)
 }
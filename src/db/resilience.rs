@@ -0,0 +1,236 @@
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use rand::Rng;
+use serde::Serialize;
+use sqlx::{postgres::PgDatabaseError, sqlite::SqliteError};
+use thiserror::Error;
+
+use super::setting;
+use crate::util::clock::Clock;
+
+// when the database is down every request used to wait for the acquire timeout and fail on its
+// own. every repository call goes through the circuit breaker below instead: after a few
+// transient failures in a row it opens and calls fail right away with DatabaseUnavailable, after
+// the cooldown the next calls are let through again to find out if the database is back.
+// idempotent reads are retried a couple of times before they count as failed
+
+const DEFAULT_BREAKER_THRESHOLD: u32 = 5;
+const DEFAULT_BREAKER_COOLDOWN_SECS: i64 = 30;
+const DEFAULT_READ_RETRIES: u32 = 2;
+const RETRY_BASE_DELAY_MS: u64 = 50;
+
+// postgres codes which are worth another try on top of the connection exceptions (class 08):
+// serialization failure, deadlock, too many connections and the server shutting down or starting
+const TRANSIENT_PG_CODES: &[&str] = &["40001", "40P01", "53300", "57P01", "57P02", "57P03"];
+// SQLITE_BUSY and SQLITE_LOCKED, the extended codes keep them in the lowest byte
+const TRANSIENT_SQLITE_CODES: &[i32] = &[5, 6];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    // the same call can succeed a moment later, e.g. the connection dropped or the pool ran dry
+    Transient,
+    // the call itself is wrong, e.g. a violated constraint, and fails again every time
+    Permanent,
+}
+
+pub fn classify(error: &anyhow::Error) -> ErrorKind {
+    match error.downcast_ref::<sqlx::Error>() {
+        Some(error) => classify_sqlx(error),
+        None if error.is::<DatabaseUnavailable>() => ErrorKind::Transient,
+        None => ErrorKind::Permanent,
+    }
+}
+
+fn classify_sqlx(error: &sqlx::Error) -> ErrorKind {
+    match error {
+        sqlx::Error::Io(_)
+        | sqlx::Error::Tls(_)
+        | sqlx::Error::Protocol(_)
+        | sqlx::Error::PoolTimedOut
+        | sqlx::Error::PoolClosed
+        | sqlx::Error::WorkerCrashed => ErrorKind::Transient,
+        sqlx::Error::Database(error) => {
+            let transient = if let Some(error) = error.try_downcast_ref::<PgDatabaseError>() {
+                error.code().starts_with("08") || TRANSIENT_PG_CODES.contains(&error.code())
+            } else if error.try_downcast_ref::<SqliteError>().is_some() {
+                error
+                    .code()
+                    .and_then(|code| code.parse::<i32>().ok())
+                    .is_some_and(|code| TRANSIENT_SQLITE_CODES.contains(&(code & 0xff)))
+            } else {
+                false
+            };
+
+            if transient {
+                ErrorKind::Transient
+            } else {
+                ErrorKind::Permanent
+            }
+        }
+        _ => ErrorKind::Permanent,
+    }
+}
+
+// returned instead of calling the database while the breaker is open
+#[derive(Debug, Error)]
+#[error("the database is unavailable, try again in {retry_after} seconds")]
+pub struct DatabaseUnavailable {
+    pub retry_after: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    Open,
+    // the cooldown is over, the next call decides whether the breaker closes or opens again
+    HalfOpen,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BreakerStatus {
+    pub state: BreakerState,
+    pub consecutive_failures: u32,
+    pub retry_after: Option<u64>,
+}
+
+struct Failures {
+    consecutive: u32,
+    opened_at: Option<DateTime<Utc>>,
+}
+
+pub struct CircuitBreaker {
+    failures: Mutex<Failures>,
+    threshold: u32,
+    cooldown: ChronoDuration,
+    // how often `retry` tries a read again
+    read_retries: u32,
+    clock: Arc<dyn Clock>,
+}
+
+impl CircuitBreaker {
+    // fails if one of the DB_BREAKER_* settings or DB_READ_RETRIES isn't a number
+    pub fn new(clock: Arc<dyn Clock>) -> Result<Self> {
+        Ok(Self {
+            failures: Mutex::new(Failures {
+                consecutive: 0,
+                opened_at: None,
+            }),
+            threshold: setting("DB_BREAKER_THRESHOLD", DEFAULT_BREAKER_THRESHOLD)?.max(1),
+            cooldown: ChronoDuration::seconds(setting(
+                "DB_BREAKER_COOLDOWN_SECS",
+                DEFAULT_BREAKER_COOLDOWN_SECS,
+            )?),
+            read_retries: setting("DB_READ_RETRIES", DEFAULT_READ_RETRIES)?,
+            clock,
+        })
+    }
+
+    pub fn status(&self) -> BreakerStatus {
+        let failures = self.failures.lock().unwrap();
+        let (state, retry_after) = self.state_of(&failures);
+
+        BreakerStatus {
+            state,
+            consecutive_failures: failures.consecutive,
+            retry_after,
+        }
+    }
+
+    // Err while the breaker is open, the database isn't asked at all then
+    pub fn check(&self) -> Result<(), DatabaseUnavailable> {
+        let failures = self.failures.lock().unwrap();
+
+        match self.state_of(&failures) {
+            (BreakerState::Open, Some(retry_after)) => Err(DatabaseUnavailable { retry_after }),
+            _ => Ok(()),
+        }
+    }
+
+    // permanent errors count as success, the database answered after all
+    pub fn record<T>(&self, result: &Result<T>) {
+        let mut failures = self.failures.lock().unwrap();
+
+        match result {
+            Err(error) if classify(error) == ErrorKind::Transient => {
+                failures.consecutive += 1;
+                // a failed call while half open opens the breaker for another cooldown
+                if failures.consecutive >= self.threshold {
+                    failures.opened_at = Some(self.clock.now());
+                }
+            }
+            _ => {
+                failures.consecutive = 0;
+                failures.opened_at = None;
+            }
+        }
+    }
+
+    fn state_of(&self, failures: &Failures) -> (BreakerState, Option<u64>) {
+        let Some(opened_at) = failures.opened_at else {
+            return (BreakerState::Closed, None);
+        };
+
+        let remaining = opened_at + self.cooldown - self.clock.now();
+        if remaining > ChronoDuration::zero() {
+            // rounded up, a client which waits Retry-After seconds never comes back too early
+            let retry_after = (remaining.num_milliseconds() as u64).div_ceil(1000);
+            (BreakerState::Open, Some(retry_after.max(1)))
+        } else {
+            (BreakerState::HalfOpen, None)
+        }
+    }
+}
+
+// for calls which mustn't run twice, they get one attempt
+pub async fn guard<T>(
+    breaker: &CircuitBreaker,
+    call: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    breaker.check()?;
+
+    let result = call.await;
+    breaker.record(&result);
+
+    result
+}
+
+// for idempotent reads, a transient failure is tried again after a growing and slightly random
+// delay. only the last attempt counts for the breaker
+pub async fn retry<T, F, Fut>(breaker: &CircuitBreaker, mut call: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let retries = breaker.read_retries;
+    let mut attempt = 0;
+
+    loop {
+        breaker.check()?;
+
+        match call().await {
+            Err(error) if attempt < retries && classify(&error) == ErrorKind::Transient => {
+                attempt += 1;
+                tokio::time::sleep(backoff(attempt)).await;
+            }
+            result => {
+                breaker.record(&result);
+                return result;
+            }
+        }
+    }
+}
+
+// 50ms, 100ms, 200ms, ... plus up to 50ms so retrying requests don't hit the database at once
+fn backoff(attempt: u32) -> Duration {
+    let delay = RETRY_BASE_DELAY_MS << (attempt - 1).min(6);
+    let jitter = rand::thread_rng().gen_range(0..RETRY_BASE_DELAY_MS);
+
+    Duration::from_millis(delay + jitter)
+}
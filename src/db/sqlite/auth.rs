@@ -0,0 +1,163 @@
+use anyhow::Result;
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+
+use super::now;
+use crate::db::auth::auth::{AuthDb, AuthUser, NewAccount};
+
+pub struct SqliteAuthDatabase {
+    pool: SqlitePool,
+}
+
+impl SqliteAuthDatabase {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    async fn read_where(&self, condition: &str, value: &str) -> Result<Option<AuthUser>> {
+        let row = sqlx::query(&format!("{} WHERE {} = $1", SELECT_AUTH_USER, condition))
+            .bind(value)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        row.map(parse_auth_user_record).transpose()
+    }
+}
+
+// the columns in the order parse_auth_user_record expects them
+const SELECT_AUTH_USER: &str =
+    "SELECT a.uid, a.email, a.email_verified, a.username, c.password_hash, a.created_at
+    FROM accounts a
+    JOIN credentials c ON c.uid = a.uid";
+
+impl AuthDb for SqliteAuthDatabase {
+    async fn insert(&self, account: &NewAccount<'_>) -> Result<()> {
+        let mut txn = self.pool.begin().await?;
+        let now = now();
+
+        sqlx::query(
+            "INSERT INTO accounts (
+                uid,
+                username,
+                email,
+                owner,
+                locale,
+                created_at,
+                updated_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $6)",
+        )
+        .bind(account.uid)
+        .bind(account.username)
+        .bind(account.email)
+        .bind(account.owner)
+        .bind(account.locale)
+        .bind(&now)
+        .execute(&mut *txn)
+        .await?;
+
+        sqlx::query("INSERT INTO credentials (uid, password_hash) VALUES ($1, $2)")
+            .bind(account.uid)
+            .bind(account.password_hash)
+            .execute(&mut *txn)
+            .await?;
+
+        txn.commit().await?;
+
+        Ok(())
+    }
+
+    async fn read_by_username(&self, username: &str) -> Result<Option<AuthUser>> {
+        self.read_where("a.username", username).await
+    }
+
+    async fn read_by_uid(&self, uid: i64) -> Result<Option<AuthUser>> {
+        let row = sqlx::query(&format!("{} WHERE a.uid = $1", SELECT_AUTH_USER))
+            .bind(uid)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        row.map(parse_auth_user_record).transpose()
+    }
+
+    async fn read_by_email(&self, email: &str) -> Result<Option<AuthUser>> {
+        self.read_where("a.email", email).await
+    }
+
+    async fn read_unverified(&self) -> Result<Vec<AuthUser>> {
+        let rows = sqlx::query(&format!(
+            "{} WHERE a.email_verified = FALSE",
+            SELECT_AUTH_USER
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(parse_auth_user_record).collect()
+    }
+
+    // the foreign keys remove everything else of the account
+    async fn delete_by_uid(&self, uid: i64) -> Result<()> {
+        sqlx::query("DELETE FROM accounts WHERE uid = $1")
+            .bind(uid)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn update_email_verification(&self, uid: i64, email_verification: bool) -> Result<()> {
+        sqlx::query(
+            "UPDATE accounts SET
+                email_verified = $1,
+                email_verified_at = CASE WHEN $1 THEN COALESCE(email_verified_at, $2) END,
+                updated_at = $2
+            WHERE uid = $3",
+        )
+        .bind(email_verification)
+        .bind(now())
+        .bind(uid)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn update_password(&self, uid: i64, password_hash: &str) -> Result<()> {
+        let mut txn = self.pool.begin().await?;
+
+        sqlx::query("UPDATE credentials SET password_hash = $1 WHERE uid = $2")
+            .bind(password_hash)
+            .bind(uid)
+            .execute(&mut *txn)
+            .await?;
+
+        sqlx::query("UPDATE accounts SET updated_at = $1 WHERE uid = $2")
+            .bind(now())
+            .bind(uid)
+            .execute(&mut *txn)
+            .await?;
+
+        txn.commit().await?;
+
+        Ok(())
+    }
+
+    async fn update_last_login(&self, uid: i64) -> Result<()> {
+        sqlx::query("UPDATE accounts SET last_login_at = $1 WHERE uid = $2")
+            .bind(now())
+            .bind(uid)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+fn parse_auth_user_record(row: SqliteRow) -> Result<AuthUser> {
+    Ok(AuthUser {
+        uid: row.try_get(0)?,
+        email: row.try_get(1)?,
+        email_verified: row.try_get(2)?,
+        username: row.try_get(3)?,
+        password_hash: row.try_get(4)?,
+        created_at: row.try_get(5)?,
+    })
+}
@@ -0,0 +1,325 @@
+use anyhow::Result;
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+
+use super::now;
+use crate::db::api::cloudthemes::{
+    cloudthemes::{CloudThemeConflict, CloudThemeDb, CLOUDTHEME_COLUMNS},
+    status::CloudThemeStatusDb,
+};
+use crate::models::api::cloudtheme::{CloudTheme, CloudThemesStatus, Theme};
+use crate::util::snowflake::generate_uid;
+
+pub struct SqliteCloudThemeDatabase {
+    pool: SqlitePool,
+}
+
+impl SqliteCloudThemeDatabase {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+impl CloudThemeDb for SqliteCloudThemeDatabase {
+    async fn insert(
+        &self,
+        uid: i64,
+        name: &str,
+        theme: &Theme,
+        active: bool,
+        limit: usize,
+    ) -> Result<CloudTheme> {
+        let mut txn = self.pool.begin().await?;
+
+        // sqlite lets one transaction write at a time, a second insert which counted before this
+        // one committed fails instead of going past the limit
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM cloudthemes WHERE uid = $1")
+            .bind(uid)
+            .fetch_one(&mut *txn)
+            .await?;
+
+        if count as usize >= limit {
+            return Err(CloudThemeConflict::TooManyThemes(limit).into());
+        }
+
+        if active {
+            sqlx::query("UPDATE cloudthemes SET active = FALSE WHERE uid = $1 AND active")
+                .bind(uid)
+                .execute(&mut *txn)
+                .await?;
+        }
+
+        let row = sqlx::query(&format!(
+            "INSERT INTO cloudthemes (
+                id,
+                uid,
+                name,
+                active,
+                primary_color_text,
+                primary_color,
+                secondary_color,
+                background_color_primary,
+                background_color_secondary,
+                background_color_tertiary,
+                primary_grey,
+                secondary_grey,
+                font_size,
+                transparency,
+                transparency_value,
+                transparency_blur,
+                created_at,
+                updated_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $17)
+            RETURNING {}",
+            CLOUDTHEME_COLUMNS
+        ))
+        .bind(generate_uid())
+        .bind(uid)
+        .bind(name)
+        .bind(active)
+        .bind(&theme.primary_color_text)
+        .bind(&theme.primary_color)
+        .bind(&theme.secondary_color)
+        .bind(&theme.background_color_primary)
+        .bind(&theme.background_color_secondary)
+        .bind(&theme.background_color_tertiary)
+        .bind(&theme.primary_grey)
+        .bind(&theme.secondary_grey)
+        .bind(&theme.font_size)
+        .bind(theme.transparency)
+        .bind(theme.transparency_value)
+        .bind(&theme.transparency_blur)
+        .bind(now())
+        .fetch_one(&mut *txn)
+        .await
+        .map_err(|e| name_taken(e, name))?;
+
+        txn.commit().await?;
+
+        parse_cloudtheme_record(row)
+    }
+
+    async fn read_active(&self, uid: i64) -> Result<Option<CloudTheme>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM cloudthemes WHERE uid = $1 AND active",
+            CLOUDTHEME_COLUMNS
+        ))
+        .bind(uid)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(parse_cloudtheme_record).transpose()
+    }
+
+    async fn read_all(&self, uid: i64) -> Result<Vec<CloudTheme>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM cloudthemes WHERE uid = $1 ORDER BY created_at, id",
+            CLOUDTHEME_COLUMNS
+        ))
+        .bind(uid)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(parse_cloudtheme_record).collect()
+    }
+
+    async fn read_by_id(&self, uid: i64, id: i64) -> Result<Option<CloudTheme>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM cloudthemes WHERE uid = $1 AND id = $2",
+            CLOUDTHEME_COLUMNS
+        ))
+        .bind(uid)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(parse_cloudtheme_record).transpose()
+    }
+
+    async fn update_theme(&self, uid: i64, id: i64, theme: &Theme) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE cloudthemes SET
+                primary_color_text = $3,
+                primary_color = $4,
+                secondary_color = $5,
+                background_color_primary = $6,
+                background_color_secondary = $7,
+                background_color_tertiary = $8,
+                primary_grey = $9,
+                secondary_grey = $10,
+                font_size = $11,
+                transparency = $12,
+                transparency_value = $13,
+                transparency_blur = $14,
+                updated_at = $15
+            WHERE uid = $1 AND id = $2",
+        )
+        .bind(uid)
+        .bind(id)
+        .bind(&theme.primary_color_text)
+        .bind(&theme.primary_color)
+        .bind(&theme.secondary_color)
+        .bind(&theme.background_color_primary)
+        .bind(&theme.background_color_secondary)
+        .bind(&theme.background_color_tertiary)
+        .bind(&theme.primary_grey)
+        .bind(&theme.secondary_grey)
+        .bind(&theme.font_size)
+        .bind(theme.transparency)
+        .bind(theme.transparency_value)
+        .bind(&theme.transparency_blur)
+        .bind(now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn rename(&self, uid: i64, id: i64, name: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE cloudthemes SET name = $3, updated_at = $4 WHERE uid = $1 AND id = $2",
+        )
+        .bind(uid)
+        .bind(id)
+        .bind(name)
+        .bind(now())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| name_taken(e, name))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn activate(&self, uid: i64, id: i64) -> Result<bool> {
+        let mut txn = self.pool.begin().await?;
+
+        sqlx::query("UPDATE cloudthemes SET active = FALSE WHERE uid = $1 AND active AND id <> $2")
+            .bind(uid)
+            .bind(id)
+            .execute(&mut *txn)
+            .await?;
+
+        let result = sqlx::query("UPDATE cloudthemes SET active = TRUE WHERE uid = $1 AND id = $2")
+            .bind(uid)
+            .bind(id)
+            .execute(&mut *txn)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            txn.rollback().await?;
+            return Ok(false);
+        }
+
+        txn.commit().await?;
+
+        Ok(true)
+    }
+
+    async fn delete(&self, uid: i64, id: i64) -> Result<bool> {
+        let mut txn = self.pool.begin().await?;
+
+        let row =
+            sqlx::query("DELETE FROM cloudthemes WHERE uid = $1 AND id = $2 RETURNING active")
+                .bind(uid)
+                .bind(id)
+                .fetch_optional(&mut *txn)
+                .await?;
+
+        let Some(row) = row else {
+            return Ok(false);
+        };
+
+        if row.try_get::<bool, _>(0)? {
+            sqlx::query(
+                "UPDATE cloudthemes SET active = TRUE WHERE id = (
+                    SELECT id FROM cloudthemes WHERE uid = $1 ORDER BY updated_at DESC, id DESC LIMIT 1
+                )",
+            )
+            .bind(uid)
+            .execute(&mut *txn)
+            .await?;
+        }
+
+        txn.commit().await?;
+
+        Ok(true)
+    }
+}
+
+// see the postgres one, sqlite only names the columns of the violated index
+fn name_taken(error: sqlx::Error, name: &str) -> anyhow::Error {
+    match &error {
+        sqlx::Error::Database(e)
+            if e.is_unique_violation() && e.message().contains("cloudthemes.name") =>
+        {
+            CloudThemeConflict::NameTaken(name.to_string()).into()
+        }
+        _ => error.into(),
+    }
+}
+
+fn parse_cloudtheme_record(row: SqliteRow) -> Result<CloudTheme> {
+    Ok(CloudTheme {
+        id: row.try_get(0)?,
+        uid: row.try_get(1)?,
+        name: row.try_get(2)?,
+        active: row.try_get(3)?,
+        theme: Theme {
+            primary_color_text: row.try_get(4)?,
+            primary_color: row.try_get(5)?,
+            secondary_color: row.try_get(6)?,
+            background_color_primary: row.try_get(7)?,
+            background_color_secondary: row.try_get(8)?,
+            background_color_tertiary: row.try_get(9)?,
+            primary_grey: row.try_get(10)?,
+            secondary_grey: row.try_get(11)?,
+            font_size: row.try_get(12)?,
+            transparency: row.try_get(13)?,
+            transparency_value: row.try_get(14)?,
+            transparency_blur: row.try_get(15)?,
+        },
+        created_at: row.try_get(16)?,
+        updated_at: row.try_get(17)?,
+    })
+}
+
+pub struct SqliteCloudThemeStatusDatabase {
+    pool: SqlitePool,
+}
+
+impl SqliteCloudThemeStatusDatabase {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+impl CloudThemeStatusDb for SqliteCloudThemeStatusDatabase {
+    async fn update_status(&self, uid: i64, enabled: bool) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO cloudthemes_status (uid, enabled) VALUES ($1, $2)
+            ON CONFLICT (uid) DO UPDATE SET enabled = EXCLUDED.enabled",
+        )
+        .bind(uid)
+        .bind(enabled)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // like the postgres one it writes the default for users without a row
+    async fn read_by_uid(&self, uid: i64) -> Result<CloudThemesStatus> {
+        sqlx::query("INSERT INTO cloudthemes_status (uid, enabled) VALUES ($1, FALSE) ON CONFLICT (uid) DO NOTHING")
+            .bind(uid)
+            .execute(&self.pool)
+            .await?;
+
+        let row = sqlx::query("SELECT enabled FROM cloudthemes_status WHERE uid = $1")
+            .bind(uid)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(CloudThemesStatus {
+            enabled: row.try_get(0)?,
+        })
+    }
+}
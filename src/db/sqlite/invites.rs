@@ -0,0 +1,177 @@
+use anyhow::Result;
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+
+use super::{now, timestamp};
+use crate::db::api::invites::InviteDb;
+use crate::models::api::invites::Invite;
+
+pub struct SqliteInviteDatabase {
+    pool: SqlitePool,
+}
+
+impl SqliteInviteDatabase {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+impl InviteDb for SqliteInviteDatabase {
+    async fn insert(&self, invite: &Invite) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO invites (
+                code,
+                created_by,
+                max_uses,
+                uses,
+                expires_at,
+                role
+            ) VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(&invite.code)
+        .bind(invite.created_by)
+        .bind(invite.max_uses)
+        .bind(invite.uses)
+        .bind(invite.expires_at.map(timestamp))
+        .bind(&invite.role)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn read_by_creator(&self, created_by: i64) -> Result<Vec<Invite>> {
+        let rows = sqlx::query("SELECT * FROM invites WHERE created_by = $1")
+            .bind(created_by)
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter().map(parse_invite_record).collect()
+    }
+
+    async fn read_all(&self) -> Result<Vec<Invite>> {
+        let rows = sqlx::query("SELECT * FROM invites")
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter().map(parse_invite_record).collect()
+    }
+
+    async fn delete(&self, code: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM invites WHERE code = $1")
+            .bind(code)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn is_redeemable(&self, code: &str) -> Result<bool> {
+        let row = sqlx::query(
+            "SELECT 1 FROM invites
+            WHERE code = $1
+                AND uses < max_uses
+                AND (expires_at IS NULL OR expires_at > $2)",
+        )
+        .bind(code)
+        .bind(now())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.is_some())
+    }
+
+    async fn redeem(&self, code: &str) -> Result<Option<Invite>> {
+        let row = sqlx::query(
+            "UPDATE invites SET uses = uses + 1
+            WHERE code = $1
+                AND uses < max_uses
+                AND (expires_at IS NULL OR expires_at > $2)
+            RETURNING *",
+        )
+        .bind(code)
+        .bind(now())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(parse_invite_record).transpose()
+    }
+
+    async fn release(&self, code: &str) -> Result<()> {
+        sqlx::query("UPDATE invites SET uses = uses - 1 WHERE code = $1 AND uses > 0")
+            .bind(code)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn record_redemption(&self, uid: i64, invite: &Invite) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO invite_redemptions (uid, code, invited_by, redeemed_at) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(uid)
+        .bind(&invite.code)
+        .bind(invite.created_by)
+        .bind(now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn read_quota(&self, uid: i64) -> Result<i32> {
+        let row = sqlx::query("SELECT remaining FROM invite_quotas WHERE uid = $1")
+            .bind(uid)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match row {
+            Some(row) => Ok(row.try_get(0)?),
+            None => Ok(0),
+        }
+    }
+
+    async fn set_quota(&self, uid: i64, remaining: i32) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO invite_quotas (uid, remaining) VALUES ($1, $2)
+            ON CONFLICT (uid) DO UPDATE SET remaining = EXCLUDED.remaining",
+        )
+        .bind(uid)
+        .bind(remaining)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn take_quota(&self, uid: i64) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE invite_quotas SET remaining = remaining - 1 WHERE uid = $1 AND remaining > 0",
+        )
+        .bind(uid)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn give_back_quota(&self, uid: i64) -> Result<()> {
+        sqlx::query("UPDATE invite_quotas SET remaining = remaining + 1 WHERE uid = $1")
+            .bind(uid)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+fn parse_invite_record(row: SqliteRow) -> Result<Invite> {
+    Ok(Invite {
+        code: row.try_get(0)?,
+        created_by: row.try_get(1)?,
+        max_uses: row.try_get(2)?,
+        uses: row.try_get(3)?,
+        expires_at: row.try_get(4)?,
+        role: row.try_get(5)?,
+    })
+}
@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+    SqlitePool,
+};
+
+use super::{setting, DEFAULT_ACQUIRE_TIMEOUT_SECS, DEFAULT_POOL_MAX_CONNECTIONS};
+use crate::secrets::SECRETS;
+
+pub mod announcements;
+pub mod auth;
+pub mod cloudthemes;
+pub mod invites;
+pub mod notification_preferences;
+pub mod notifications;
+pub mod outbox;
+pub mod signup_cleanup;
+pub mod theme_gallery;
+pub mod tokens;
+pub mod users;
+
+const DEFAULT_SQLITE_PATH: &str = "acid4sigmas.db";
+// how long a write waits for the one writer sqlite allows before it fails
+const BUSY_TIMEOUT_SECS: u64 = 5;
+
+// sqlite has no timestamp type, they are stored as text and compared as text. every timestamp
+// the repositories write goes through here, so they all have the same width and sort by time.
+// the migrations write their defaults with strftime('%Y-%m-%dT%H:%M:%fZ', 'now'), the same format
+pub fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+pub fn now() -> String {
+    timestamp(Utc::now())
+}
+
+// opens (and creates) the database file at SQLITE_PATH
+pub async fn connect() -> Result<SqlitePool> {
+    let path = SECRETS
+        .get("SQLITE_PATH")
+        .map(String::as_str)
+        .unwrap_or(DEFAULT_SQLITE_PATH);
+
+    let options = SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(true)
+        // readers don't block the writer and the other way around
+        .journal_mode(SqliteJournalMode::Wal)
+        .busy_timeout(Duration::from_secs(BUSY_TIMEOUT_SECS))
+        .foreign_keys(true);
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(setting(
+            "DB_POOL_MAX_CONNECTIONS",
+            DEFAULT_POOL_MAX_CONNECTIONS,
+        )?)
+        .acquire_timeout(Duration::from_secs(setting(
+            "DB_ACQUIRE_TIMEOUT_SECS",
+            DEFAULT_ACQUIRE_TIMEOUT_SECS,
+        )?))
+        .connect_with(options)
+        .await?;
+
+    Ok(pool)
+}
@@ -0,0 +1,57 @@
+use anyhow::Result;
+use sqlx::{Row, SqlitePool};
+
+use crate::db::api::notification_preferences::NotificationPreferencesDb;
+use crate::models::api::notification_preferences::NotificationPreferences;
+
+pub struct SqliteNotificationPreferencesDatabase {
+    pool: SqlitePool,
+}
+
+impl SqliteNotificationPreferencesDatabase {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+impl NotificationPreferencesDb for SqliteNotificationPreferencesDatabase {
+    async fn read_by_uid(&self, uid: i64) -> Result<NotificationPreferences> {
+        let row = sqlx::query(
+            "SELECT reminders, login_alerts, announcements FROM notification_preferences WHERE uid = $1",
+        )
+        .bind(uid)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let preferences = match row {
+            Some(row) => NotificationPreferences {
+                security: true,
+                reminders: row.try_get(0)?,
+                login_alerts: row.try_get(1)?,
+                announcements: row.try_get(2)?,
+            },
+            None => NotificationPreferences::default(),
+        };
+
+        Ok(preferences)
+    }
+
+    async fn upsert(&self, uid: i64, preferences: &NotificationPreferences) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO notification_preferences (uid, reminders, login_alerts, announcements)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (uid) DO UPDATE SET
+                reminders = EXCLUDED.reminders,
+                login_alerts = EXCLUDED.login_alerts,
+                announcements = EXCLUDED.announcements",
+        )
+        .bind(uid)
+        .bind(preferences.reminders)
+        .bind(preferences.login_alerts)
+        .bind(preferences.announcements)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+use anyhow::Result;
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+
+use super::now;
+use crate::db::api::announcements::audience_filter;
+use crate::db::api::notifications::NotificationDb;
+use crate::models::api::announcements::Announcement;
+use crate::models::api::notifications::Notification;
+use crate::util::snowflake::generate_uid;
+
+pub struct SqliteNotificationDatabase {
+    pool: SqlitePool,
+}
+
+impl SqliteNotificationDatabase {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+impl NotificationDb for SqliteNotificationDatabase {
+    async fn insert_announcement(&self, announcement: &Announcement) -> Result<u64> {
+        let mut txn = self.pool.begin().await?;
+        let now = now();
+
+        let rows = sqlx::query(&format!(
+            "SELECT u.uid FROM accounts u WHERE {}",
+            audience_filter(announcement.audience)
+        ))
+        .fetch_all(&mut *txn)
+        .await?;
+
+        let mut inserted = 0;
+
+        for row in rows {
+            let uid: i64 = row.try_get(0)?;
+
+            sqlx::query(
+                "INSERT INTO notifications (
+                    id,
+                    uid,
+                    title,
+                    body,
+                    announcement_id,
+                    created_at
+                ) VALUES ($1, $2, $3, $4, $5, $6)",
+            )
+            .bind(generate_uid())
+            .bind(uid)
+            .bind(&announcement.subject)
+            .bind(&announcement.body)
+            .bind(announcement.id)
+            .bind(&now)
+            .execute(&mut *txn)
+            .await?;
+
+            inserted += 1;
+        }
+
+        txn.commit().await?;
+
+        Ok(inserted)
+    }
+
+    async fn read_by_uid(
+        &self,
+        uid: i64,
+        unread_only: bool,
+        limit: i64,
+    ) -> Result<Vec<Notification>> {
+        let rows = sqlx::query(
+            "SELECT * FROM notifications
+            WHERE uid = $1 AND ($2 = FALSE OR read_at IS NULL)
+            ORDER BY id DESC
+            LIMIT $3",
+        )
+        .bind(uid)
+        .bind(unread_only)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(parse_notification_record).collect()
+    }
+
+    async fn mark_read(&self, uid: i64, id: i64) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE notifications SET read_at = COALESCE(read_at, $1) WHERE id = $2 AND uid = $3",
+        )
+        .bind(now())
+        .bind(id)
+        .bind(uid)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn mark_all_read(&self, uid: i64) -> Result<u64> {
+        let result =
+            sqlx::query("UPDATE notifications SET read_at = $1 WHERE uid = $2 AND read_at IS NULL")
+                .bind(now())
+                .bind(uid)
+                .execute(&self.pool)
+                .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+fn parse_notification_record(row: SqliteRow) -> Result<Notification> {
+    Ok(Notification {
+        id: row.try_get(0)?,
+        uid: row.try_get(1)?,
+        title: row.try_get(2)?,
+        body: row.try_get(3)?,
+        announcement_id: row.try_get(4)?,
+        read_at: row.try_get(5)?,
+        created_at: row.try_get(6)?,
+    })
+}
@@ -0,0 +1,119 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+
+use super::{now, timestamp};
+use crate::db::api::signup_cleanup::SignupCleanupDb;
+use crate::models::api::signup_cleanup::{SignupCleanupEntry, ACTION_REMINDER};
+use crate::util::snowflake::generate_uid;
+
+pub struct SqliteSignupCleanupDatabase {
+    pool: SqlitePool,
+}
+
+impl SqliteSignupCleanupDatabase {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+impl SignupCleanupDb for SqliteSignupCleanupDatabase {
+    async fn insert(
+        &self,
+        run_id: i64,
+        action: &str,
+        uid: i64,
+        username: &str,
+        reminder_day: Option<i32>,
+        dry_run: bool,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO signup_cleanup_log (
+                id,
+                run_id,
+                action,
+                uid,
+                username,
+                reminder_day,
+                dry_run,
+                created_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        )
+        .bind(generate_uid())
+        .bind(run_id)
+        .bind(action)
+        .bind(uid)
+        .bind(username)
+        .bind(reminder_day)
+        .bind(dry_run)
+        .bind(now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn sent_reminder_days(&self, uid: i64) -> Result<Vec<i32>> {
+        let rows = sqlx::query(
+            "SELECT reminder_day FROM signup_cleanup_log
+            WHERE uid = $1 AND action = $2 AND dry_run = FALSE AND reminder_day IS NOT NULL",
+        )
+        .bind(uid)
+        .bind(ACTION_REMINDER)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(|row| Ok(row.try_get(0)?)).collect()
+    }
+
+    async fn dry_run_logged_since(
+        &self,
+        uid: i64,
+        action: &str,
+        since: DateTime<Utc>,
+    ) -> Result<bool> {
+        let row = sqlx::query(
+            "SELECT 1 FROM signup_cleanup_log
+            WHERE uid = $1 AND action = $2 AND dry_run = TRUE AND created_at >= $3
+            LIMIT 1",
+        )
+        .bind(uid)
+        .bind(action)
+        .bind(timestamp(since))
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.is_some())
+    }
+
+    async fn read_recent(&self, limit: i64) -> Result<Vec<SignupCleanupEntry>> {
+        let rows = sqlx::query("SELECT * FROM signup_cleanup_log ORDER BY id DESC LIMIT $1")
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter().map(parse_cleanup_record).collect()
+    }
+
+    async fn read_by_run(&self, run_id: i64) -> Result<Vec<SignupCleanupEntry>> {
+        let rows = sqlx::query("SELECT * FROM signup_cleanup_log WHERE run_id = $1 ORDER BY id")
+            .bind(run_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter().map(parse_cleanup_record).collect()
+    }
+}
+
+fn parse_cleanup_record(row: SqliteRow) -> Result<SignupCleanupEntry> {
+    Ok(SignupCleanupEntry {
+        id: row.try_get(0)?,
+        run_id: row.try_get(1)?,
+        action: row.try_get(2)?,
+        uid: row.try_get(3)?,
+        username: row.try_get(4)?,
+        reminder_day: row.try_get(5)?,
+        dry_run: row.try_get(6)?,
+        created_at: row.try_get(7)?,
+    })
+}
@@ -0,0 +1,57 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::{Row, SqlitePool};
+
+use super::{now, timestamp};
+use crate::db::auth::tokens::TokenDb;
+
+pub struct SqliteTokenDatabase {
+    pool: SqlitePool,
+}
+
+impl SqliteTokenDatabase {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+impl TokenDb for SqliteTokenDatabase {
+    async fn insert(&self, user_id: i64, jti: &str, expires_at: DateTime<Utc>) -> Result<()> {
+        sqlx::query("INSERT INTO auth_tokens (jti, uid, expires_at) VALUES ($1, $2, $3)")
+            .bind(jti)
+            .bind(user_id)
+            .bind(timestamp(expires_at))
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn read_by_uid(&self, user_id: i64) -> Result<Vec<String>> {
+        let rows = sqlx::query("SELECT jti FROM auth_tokens WHERE uid = $1 AND expires_at > $2")
+            .bind(user_id)
+            .bind(now())
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter().map(|row| Ok(row.try_get(0)?)).collect()
+    }
+
+    async fn delete_expired(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM auth_tokens WHERE expires_at <= $1")
+            .bind(now())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_by_uid(&self, user_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM auth_tokens WHERE uid = $1")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use anyhow::Result;
+use sqlx::{Row, SqlitePool};
+
+use super::now;
+use crate::db::api::users::UserDb;
+use crate::models::api::users::User;
+
+pub struct SqliteUserDatabase {
+    pool: SqlitePool,
+}
+
+impl SqliteUserDatabase {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+impl UserDb for SqliteUserDatabase {
+    async fn read_by_uid(&self, uid: i64) -> Result<Option<User>> {
+        let row = sqlx::query(
+            "SELECT uid, email, owner, email_verified, username, locale, created_at, updated_at,
+                last_login_at, email_verified_at
+            FROM accounts WHERE uid = $1",
+        )
+        .bind(uid)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let user = match row {
+            Some(row) => Some(User {
+                uid: row.try_get(0)?,
+                email: row.try_get(1)?,
+                owner: row.try_get(2)?,
+                email_verified: row.try_get(3)?,
+                username: row.try_get(4)?,
+                locale: row.try_get(5)?,
+                created_at: row.try_get(6)?,
+                updated_at: row.try_get(7)?,
+                last_login_at: row.try_get(8)?,
+                email_verified_at: row.try_get(9)?,
+            }),
+            None => None,
+        };
+
+        Ok(user)
+    }
+
+    async fn update_locale(&self, uid: i64, locale: Option<&str>) -> Result<()> {
+        sqlx::query("UPDATE accounts SET locale = $1, updated_at = $2 WHERE uid = $3")
+            .bind(locale)
+            .bind(now())
+            .bind(uid)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -1,7 +1,9 @@
-use actix_web::{HttpResponse, ResponseError};
+use actix_web::{http::header::RETRY_AFTER, HttpResponse, ResponseError};
 use serde_json::json;
 use thiserror::Error;
 
+use crate::{api::cloudthemes::util::InvalidTheme, db::resilience::DatabaseUnavailable};
+
 
 #[derive(Debug, Error)]
 pub enum ActixError {
@@ -14,6 +16,9 @@ pub enum ActixError {
     #[error("Code gen error: {0}")]
     CodeGenError(String),
 
+    #[error("Challenge error: {0}")]
+    ChallengeError(String),
+
 }  
 
 impl ResponseError for ActixError {
@@ -23,6 +28,7 @@ impl ResponseError for ActixError {
             ActixError::DatabaseError(err) => format!("Internal Server Error: {}", err),
             ActixError::JsonError(err) => format!("Bad Request: {}", err),
             ActixError::CodeGenError(err) => format!("Conflict: {}", err),
+            ActixError::ChallengeError(err) => format!("Forbidden: {}", err),
         };
 
         HttpResponse::build(self.status_code()).json(json!({
@@ -35,6 +41,35 @@ impl ResponseError for ActixError {
             ActixError::DatabaseError(_) => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
             ActixError::JsonError(_) => actix_web::http::StatusCode::BAD_REQUEST,
             ActixError::CodeGenError(_) => actix_web::http::StatusCode::CONFLICT,
+            ActixError::ChallengeError(_) => actix_web::http::StatusCode::FORBIDDEN,
         }
     }
 }
+
+// 503 with Retry-After, so clients and load balancers back off until the breaker lets calls through again
+impl ResponseError for DatabaseUnavailable {
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        HttpResponse::build(self.status_code())
+            .insert_header((RETRY_AFTER, self.retry_after.to_string()))
+            .json(json!({
+                "error": self.to_string()
+            }))
+    }
+
+    fn status_code(&self) -> actix_web::http::StatusCode {
+        actix_web::http::StatusCode::SERVICE_UNAVAILABLE
+    }
+}
+
+impl ResponseError for InvalidTheme {
+    fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
+        HttpResponse::build(self.status_code()).json(json!({
+            "error": self.to_string(),
+            "fields": self.fields
+        }))
+    }
+
+    fn status_code(&self) -> actix_web::http::StatusCode {
+        actix_web::http::StatusCode::BAD_REQUEST
+    }
+}
@@ -0,0 +1,75 @@
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    get,
+    http::StatusCode,
+    web, Error, HttpResponse, ResponseError,
+};
+use actix_web_lab::middleware::Next;
+use serde_json::json;
+
+use crate::{
+    db::resilience::{self, DatabaseUnavailable},
+    error_response,
+    state::AppState,
+};
+
+// liveness, answers as long as the process does and shows the state of the circuit breaker
+#[get("/health")]
+pub async fn health(state: web::Data<AppState>) -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "status": "ok",
+        "database": state.breaker.status(),
+    }))
+}
+
+// readiness, asks the database. while the breaker is open it doesn't and answers 503 right away,
+// once the cooldown is over this check is one of the calls which close it again
+#[get("/health/ready")]
+pub async fn ready(state: web::Data<AppState>) -> HttpResponse {
+    match resilience::guard(&state.breaker, state.backend.ping()).await {
+        Ok(()) => HttpResponse::Ok().json(json!({
+            "status": "ok",
+            "database": state.breaker.status(),
+        })),
+        Err(e) => match e.downcast_ref::<DatabaseUnavailable>() {
+            Some(unavailable) => unavailable.error_response(),
+            None => error_response!(503, e.to_string()),
+        },
+    }
+}
+
+// wraps the scopes which need the database. while the breaker is open requests fail with 503
+// before they reach a handler, and a 500 caused by the outage becomes a 503 with Retry-After too
+pub async fn database_available_mw<B>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<BoxBody>, Error>
+where
+    B: MessageBody + 'static,
+{
+    let state = match AppState::of(req.request()) {
+        Ok(state) => state.clone(),
+        Err(e) => {
+            let http_res = error_response!(500, e.to_string()).map_into_boxed_body();
+            let (req, _pl) = req.into_parts();
+            return Ok(ServiceResponse::new(req, http_res));
+        }
+    };
+
+    if let Err(unavailable) = state.breaker.check() {
+        let (req, _pl) = req.into_parts();
+        return Ok(ServiceResponse::new(req, unavailable.error_response()));
+    }
+
+    let res = next.call(req).await?;
+
+    if res.status() == StatusCode::INTERNAL_SERVER_ERROR {
+        if let Err(unavailable) = state.breaker.check() {
+            let (req, _res) = res.into_parts();
+            return Ok(ServiceResponse::new(req, unavailable.error_response()));
+        }
+    }
+
+    Ok(res.map_body(|_, body| BoxBody::new(body)))
+}
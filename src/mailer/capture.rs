@@ -0,0 +1,42 @@
+use std::sync::Mutex;
+
+use super::{build_message, Email, Mailer};
+
+// keeps every email in memory instead of sending it, used by the tests
+#[derive(Default)]
+pub struct CaptureMailer {
+    sent: Mutex<Vec<Email>>,
+}
+
+impl CaptureMailer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[cfg(test)]
+    pub fn sent(&self) -> Vec<Email> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    #[cfg(test)]
+    pub fn last_to(&self, to: &str) -> Option<Email> {
+        self.sent
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|email| email.to == to)
+            .cloned()
+    }
+}
+
+impl Mailer for CaptureMailer {
+    fn send(&self, email: &Email) -> anyhow::Result<()> {
+        // build the message anyways so invalid addresses fail the same way as with smtp
+        build_message(email)?;
+
+        self.sent.lock().unwrap().push(email.clone());
+
+        Ok(())
+    }
+}
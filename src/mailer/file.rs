@@ -0,0 +1,40 @@
+use std::{fs, path::PathBuf, sync::Arc};
+
+use uuid::Uuid;
+
+use super::{build_message, Email, Mailer};
+use crate::util::clock::Clock;
+
+// development transport, every email ends up as an .eml file which any mail client can open
+pub struct FileMailer {
+    dir: PathBuf,
+    // the same clock as the app state, names the files
+    clock: Arc<dyn Clock>,
+}
+
+impl FileMailer {
+    pub fn new(dir: impl Into<PathBuf>, clock: Arc<dyn Clock>) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        Ok(FileMailer { dir, clock })
+    }
+}
+
+impl Mailer for FileMailer {
+    fn send(&self, email: &Email) -> anyhow::Result<()> {
+        let message = build_message(email)?;
+
+        let path = self.dir.join(format!(
+            "{}-{}.eml",
+            self.clock.now().format("%Y%m%d%H%M%S"),
+            Uuid::new_v4()
+        ));
+
+        fs::write(&path, message.formatted())?;
+
+        println!("email to {} written to {:?}", email.to, path);
+
+        Ok(())
+    }
+}
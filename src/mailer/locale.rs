@@ -0,0 +1,77 @@
+use actix_web::HttpRequest;
+
+use crate::{db::api::users::UserDb, state::AppState};
+
+// the languages the email templates exist in, same as the faith book
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+}
+
+impl Locale {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::De => "de",
+        }
+    }
+
+    // accepts plain language codes and language tags like "de-AT"
+    pub fn parse(tag: &str) -> Option<Self> {
+        let language = tag.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+
+        match language.as_str() {
+            "en" => Some(Locale::En),
+            "de" => Some(Locale::De),
+            _ => None,
+        }
+    }
+
+    // picks the supported language with the highest q value, e.g. "fr-CH, de;q=0.9, en;q=0.8" is de
+    pub fn from_accept_language(header: &str) -> Option<Self> {
+        let mut languages: Vec<(&str, f32)> = header
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map(|q| q.trim().parse().unwrap_or(0.0))
+                    .unwrap_or(1.0);
+
+                Some((tag, quality))
+            })
+            .filter(|(_, quality)| *quality > 0.0)
+            .collect();
+
+        // stable sort, so equal q values keep the order of the header
+        languages.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        languages.into_iter().find_map(|(tag, _)| Self::parse(tag))
+    }
+
+    pub fn from_request(req: &HttpRequest) -> Self {
+        req.headers()
+            .get("Accept-Language")
+            .and_then(|header| header.to_str().ok())
+            .and_then(Self::from_accept_language)
+            .unwrap_or_default()
+    }
+
+    // the language the user picked on /api/me/locale
+    pub async fn stored(state: &AppState, uid: i64) -> Option<Self> {
+        let user = state.users().read_by_uid(uid).await.ok()??;
+
+        user.locale.as_deref().and_then(Self::parse)
+    }
+
+    // the stored language of the user, falls back to the Accept-Language of the request
+    pub async fn for_user(state: &AppState, uid: i64, req: &HttpRequest) -> Self {
+        match Self::stored(state, uid).await {
+            Some(locale) => locale,
+            None => Self::from_request(req),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::sync::Arc;
+
+use lettre::{
+    message::{
+        header::{HeaderName, HeaderValue},
+        MultiPart, SinglePart,
+    },
+    Message,
+};
+
+use crate::{secrets::SECRETS, util::clock::Clock};
+
+pub mod capture;
+pub mod file;
+pub mod locale;
+pub mod notifications;
+pub mod queue;
+pub mod smtp;
+pub mod templates;
+
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub html: String,
+    // text/plain alternative of the html, emails without one are sent as html only
+    pub text: String,
+    // set for optional emails, adds the one click unsubscribe headers from RFC 8058
+    pub unsubscribe_url: Option<String>,
+}
+
+// everything that sends emails goes through this trait, so the transport can be swapped
+// for development (file) or tests (capture) without touching the auth flows
+pub trait Mailer: Send + Sync {
+    fn send(&self, email: &Email) -> anyhow::Result<()>;
+}
+
+pub fn no_reply_address() -> String {
+    SECRETS
+        .get("NO_REPLY_EMAIL")
+        .cloned()
+        .unwrap_or_else(|| String::from("no-reply@localhost"))
+}
+
+pub fn build_message(email: &Email) -> anyhow::Result<Message> {
+    let mut builder = Message::builder()
+        .from(no_reply_address().parse()?)
+        .to(email.to.parse()?)
+        .subject(&email.subject);
+
+    if let Some(url) = &email.unsubscribe_url {
+        builder = builder
+            .raw_header(HeaderValue::new(
+                HeaderName::new_from_ascii_str("List-Unsubscribe"),
+                format!("<{}>", url),
+            ))
+            .raw_header(HeaderValue::new(
+                HeaderName::new_from_ascii_str("List-Unsubscribe-Post"),
+                String::from("List-Unsubscribe=One-Click"),
+            ));
+    }
+
+    let message = if email.text.is_empty() {
+        builder.singlepart(SinglePart::html(email.html.clone()))?
+    } else {
+        builder.multipart(MultiPart::alternative_plain_html(
+            email.text.clone(),
+            email.html.clone(),
+        ))?
+    };
+
+    Ok(message)
+}
+
+// picks the transport configured with MAIL_TRANSPORT, smtp is the default
+pub fn from_config(clock: Arc<dyn Clock>) -> anyhow::Result<Arc<dyn Mailer>> {
+    let transport = SECRETS
+        .get("MAIL_TRANSPORT")
+        .map(|transport| transport.as_str())
+        .unwrap_or("smtp");
+
+    match transport {
+        "smtp" => Ok(Arc::new(smtp::SmtpMailer::from_config()?)),
+        "file" => {
+            let dir = SECRETS
+                .get("MAIL_DIR")
+                .cloned()
+                .unwrap_or_else(|| String::from("mail"));
+            Ok(Arc::new(file::FileMailer::new(dir, clock)?))
+        }
+        "capture" => Ok(Arc::new(capture::CaptureMailer::new())),
+        other => Err(anyhow::anyhow!("unknown MAIL_TRANSPORT '{}'", other)),
+    }
+}
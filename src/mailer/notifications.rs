@@ -0,0 +1,106 @@
+use anyhow::Result;
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use minijinja::{context, Value};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::links::public_url, db::api::notification_preferences::NotificationPreferencesDb,
+    models::api::notification_preferences::NotificationCategory, secrets::SECRETS, state::AppState,
+};
+
+use super::{locale::Locale, queue, templates};
+
+// emails the user can opt out of. they only go out if the category is enabled in the
+// notification preferences and carry a signed unsubscribe link (also as List-Unsubscribe header)
+
+#[derive(Debug, Serialize, Deserialize)]
+struct UnsubscribeClaims {
+    purpose: String,
+    uid: String,
+    category: NotificationCategory,
+}
+
+const UNSUBSCRIBE_PURPOSE: &str = "unsubscribe";
+
+fn get_secret_key() -> Vec<u8> {
+    SECRETS
+        .get("SECRET_KEY")
+        .expect("SECRET_KEY not found")
+        .as_bytes()
+        .to_vec()
+}
+
+// unsubscribe links don't expire, they can only ever turn a category off
+pub fn unsubscribe_token(uid: i64, category: NotificationCategory) -> String {
+    let claims = UnsubscribeClaims {
+        purpose: UNSUBSCRIBE_PURPOSE.to_string(),
+        uid: uid.to_string(),
+        category,
+    };
+
+    encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(&get_secret_key()),
+    )
+    .expect("Failed to generate unsubscribe token")
+}
+
+pub fn decode_unsubscribe_token(token: &str) -> Option<(i64, NotificationCategory)> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.required_spec_claims.clear();
+    validation.validate_exp = false;
+
+    let claims = decode::<UnsubscribeClaims>(
+        token,
+        &DecodingKey::from_secret(&get_secret_key()),
+        &validation,
+    )
+    .ok()?
+    .claims;
+
+    if claims.purpose != UNSUBSCRIBE_PURPOSE || claims.category == NotificationCategory::Security {
+        return None;
+    }
+
+    Some((claims.uid.parse().ok()?, claims.category))
+}
+
+pub fn unsubscribe_url(uid: i64, category: NotificationCategory) -> String {
+    format!(
+        "{}/pub_api/unsubscribe?token={}",
+        public_url(),
+        unsubscribe_token(uid, category)
+    )
+}
+
+// renders and queues an optional email, returns false if the user turned the category off
+pub async fn send_notification(
+    state: &AppState,
+    uid: i64,
+    to: &str,
+    category: NotificationCategory,
+    template: &str,
+    locale: Locale,
+    ctx: Value,
+) -> Result<bool> {
+    let db = state.notification_preferences();
+
+    if !db.read_by_uid(uid).await?.allows(category) {
+        return Ok(false);
+    }
+
+    let url = unsubscribe_url(uid, category);
+
+    let mut email = templates::render(
+        to,
+        template,
+        locale,
+        context! { unsubscribe_url => url.clone(), ..ctx },
+    )?;
+    email.unsubscribe_url = Some(url);
+
+    queue::enqueue(state, email).await?;
+
+    Ok(true)
+}
@@ -0,0 +1,92 @@
+use anyhow::Result;
+use chrono::Duration;
+
+use crate::{db::api::outbox::OutboxDb, secrets::SECRETS, state::AppState};
+
+use super::Email;
+
+// handlers only put emails into the outbox table, the `deliver_email_outbox` job sends them
+
+const BATCH_SIZE: i64 = 20;
+const MAX_ATTEMPTS: i32 = 8;
+const BASE_RETRY_DELAY_SECS: i64 = 30;
+const MAX_RETRY_DELAY_SECS: i64 = 6 * 60 * 60;
+const DEFAULT_RATE_LIMIT_PER_HOUR: i64 = 10;
+
+pub async fn enqueue(state: &AppState, email: Email) -> Result<()> {
+    let db = state.outbox();
+
+    db.enqueue(&email).await?;
+
+    Ok(())
+}
+
+fn rate_limit_per_hour() -> i64 {
+    SECRETS
+        .get("MAIL_RATE_LIMIT_PER_HOUR")
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(DEFAULT_RATE_LIMIT_PER_HOUR)
+}
+
+// 30s, 1m, 2m, 4m, ... capped at 6 hours
+fn retry_delay(attempts: i32) -> Duration {
+    let delay = BASE_RETRY_DELAY_SECS.saturating_mul(1 << (attempts - 1).clamp(0, 20));
+
+    Duration::seconds(delay.min(MAX_RETRY_DELAY_SECS))
+}
+
+pub async fn deliver_due(state: AppState) -> Result<()> {
+    let db = state.outbox();
+    let now = state.clock.now();
+
+    let rate_limit = rate_limit_per_hour();
+
+    for message in db.claim_due(BATCH_SIZE).await? {
+        let sent_last_hour = db
+            .count_sent_to(&message.recipient, now - Duration::hours(1))
+            .await?;
+
+        if sent_last_hour >= rate_limit {
+            // not a failed attempt, just wait until the recipient is below the limit again
+            db.postpone(message.id, now + Duration::minutes(10)).await?;
+            continue;
+        }
+
+        let email = Email {
+            to: message.recipient.clone(),
+            subject: message.subject.clone(),
+            html: message.html.clone(),
+            text: message.text.clone(),
+            unsubscribe_url: message.unsubscribe_url.clone(),
+        };
+
+        // the transports are blocking, keep them off the async workers
+        let mailer = state.mailer.clone();
+        let result = tokio::task::spawn_blocking(move || mailer.send(&email)).await?;
+
+        match result {
+            Ok(()) => db.mark_sent(message.id).await?,
+            Err(e) => {
+                let attempts = message.attempts + 1;
+
+                if attempts >= MAX_ATTEMPTS {
+                    println!(
+                        "email {} to {} failed {} times, giving up: {}",
+                        message.id, message.recipient, attempts, e
+                    );
+                    db.mark_dead(message.id, attempts, &e.to_string()).await?;
+                } else {
+                    db.mark_failed(
+                        message.id,
+                        attempts,
+                        &e.to_string(),
+                        now + retry_delay(attempts),
+                    )
+                    .await?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
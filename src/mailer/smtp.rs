@@ -0,0 +1,37 @@
+use lettre::{transport::smtp::authentication::Credentials, SmtpTransport, Transport};
+
+use crate::secrets::SECRETS;
+
+use super::{build_message, Email, Mailer};
+
+// the transport is built once and keeps a pool of connections to the relay
+pub struct SmtpMailer {
+    transport: SmtpTransport,
+}
+
+impl SmtpMailer {
+    pub fn from_config() -> anyhow::Result<Self> {
+        let get = |key: &str| {
+            SECRETS
+                .get(key)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("{} not found", key))
+        };
+
+        let creds = Credentials::new(get("SMTP_USERNAME")?, get("SMTP_PASSWORD")?);
+
+        let transport = SmtpTransport::relay(&get("SMTP_RELAY")?)?
+            .credentials(creds)
+            .build();
+
+        Ok(SmtpMailer { transport })
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&self, email: &Email) -> anyhow::Result<()> {
+        self.transport.send(&build_message(email)?)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use anyhow::Result;
+use minijinja::{context, Environment, Value};
+use once_cell::sync::Lazy;
+
+use crate::util::html_utils::html_to_text;
+
+use super::{locale::Locale, Email};
+
+const CONTACT_EMAIL: &str = "klover@acid4sigmas.systems";
+
+macro_rules! template {
+    ($name:literal) => {
+        ($name, include_str!(concat!("templates/", $name)))
+    };
+}
+
+// every locale has a layout extending base.html, the emails extend the layout of their locale
+// and define a `subject` and a `content` block
+const TEMPLATES: &[(&str, &str)] = &[
+    template!("base.html"),
+    template!("en/layout.html"),
+    template!("en/verify_email.html"),
+    template!("en/password_reset.html"),
+    template!("en/password_changed.html"),
+    template!("en/registration_attempt.html"),
+    template!("en/magic_link.html"),
+    template!("en/verification_reminder.html"),
+    template!("en/login_alert.html"),
+    template!("en/announcement.html"),
+    template!("de/layout.html"),
+    template!("de/verify_email.html"),
+    template!("de/password_reset.html"),
+    template!("de/password_changed.html"),
+    template!("de/registration_attempt.html"),
+    template!("de/magic_link.html"),
+    template!("de/verification_reminder.html"),
+    template!("de/login_alert.html"),
+    template!("de/announcement.html"),
+];
+
+// .html templates are autoescaped, so values like usernames can't inject markup
+static ENVIRONMENT: Lazy<Environment<'static>> = Lazy::new(|| {
+    let mut env = Environment::new();
+
+    for (name, source) in TEMPLATES {
+        env.add_template(name, source)
+            .unwrap_or_else(|e| panic!("invalid email template {}: {}", name, e));
+    }
+
+    env.add_global("contact_email", CONTACT_EMAIL);
+
+    env
+});
+
+// renders `{locale}/{template}.html` into an email with a generated text/plain part
+pub fn render(to: &str, template: &str, locale: Locale, ctx: Value) -> Result<Email> {
+    let template = ENVIRONMENT.get_template(&format!("{}/{}.html", locale.code(), template))?;
+    let ctx = context! { locale => locale.code(), ..ctx };
+
+    // the block is autoescaped like the rest, the subject header wants the plain text
+    let subject = html_to_text(&template.eval_to_state(&ctx)?.render_block("subject")?);
+    let html = template.render(&ctx)?;
+
+    Ok(Email {
+        to: to.to_string(),
+        subject,
+        text: html_to_text(&html),
+        html,
+        unsubscribe_url: None,
+    })
+}
@@ -1,37 +1,27 @@
 #![allow(deprecated)]
 
-use actix_web::{get, web, App, HttpResponse, HttpServer, Responder};
+use std::sync::Arc;
 
+use actix_web::{web, HttpServer};
+
+mod admin;
 mod api;
+mod app;
 mod auth;
 mod cache;
 mod db;
 mod error;
+mod health;
+mod mailer;
 mod models;
 mod pub_api;
+mod scheduler;
 mod secrets;
+mod state;
 mod util;
 
-use api::{
-    cloudthemes::{
-        cloudthemes::{get_cloudthemes, set_cloudtheme},
-        status::{get_cloudthemes_status, post_cloudthemes_status},
-    },
-    me::me,
-};
-use auth::{
-    auth_middleware::check_auth_mw,
-    login,
-    password_reset::{request_reset_password, reset_password},
-    register, send_verifiaction_email, verify_email,
-};
-
-use actix_files as fs;
-
-use actix_cors::Cors;
-use actix_web_lab::middleware::from_fn;
-use pub_api::faith::book::faith_book;
-use pub_api::github::get_repo_;
+#[cfg(test)]
+mod tests;
 
 #[macro_export]
 macro_rules! error_response {
@@ -57,56 +47,84 @@ macro_rules! token_response {
     }
 }
 
-#[get("/")]
-async fn index() -> impl Responder {
-    HttpResponse::Ok()
-        .content_type("text/html")
-        .body(include_str!("../static/index.html"))
-}
+// `migrate` applies pending migrations and exits, `migrate status` lists them
+async fn migrate_command(backend: &db::Backend, args: &[String]) -> anyhow::Result<()> {
+    match args.first().map(String::as_str) {
+        None => {
+            let applied = db::migrations::run(backend).await?;
+            println!("{} migrations applied", applied.len());
+        }
+        Some("status") => {
+            for migration in db::migrations::status(backend).await? {
+                let applied_at = match migration.applied_at {
+                    Some(applied_at) => applied_at.to_rfc3339(),
+                    None => String::from("pending"),
+                };
+                println!(
+                    "{:>4} {:<40} {}",
+                    migration.version, migration.name, applied_at
+                );
+            }
+        }
+        Some(other) => anyhow::bail!("unknown migrate command '{}'", other),
+    }
 
-async fn nested_hello() -> impl Responder {
-    HttpResponse::Ok().body("Hello from the nested route!")
+    Ok(())
 }
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    HttpServer::new(|| {
-        let cors = Cors::default()
-            .allow_any_header()
-            .allow_any_origin()
-            .allow_any_method();
-
-        App::new()
-            .wrap(cors)
-            .service(fs::Files::new("/static", "static").show_files_listing())
-            .service(fs::Files::new("/assets", "assets").show_files_listing())
-            .service(
-                web::scope("/api")
-                    .wrap(from_fn(check_auth_mw))
-                    .route("/nested", web::get().to(nested_hello))
-                    .service(me)
-                    .service(set_cloudtheme)
-                    .service(get_cloudthemes)
-                    .service(get_cloudthemes_status)
-                    .service(post_cloudthemes_status),
-            )
-            .service(
-                web::scope("/pub_api")
-                    .service(get_repo_)
-                    .service(faith_book),
-            )
-            .service(
-                web::scope("/auth")
-                    .service(register)
-                    .service(login)
-                    .service(send_verifiaction_email)
-                    .service(verify_email)
-                    .service(request_reset_password)
-                    .service(reset_password),
-            )
-            .service(index)
-    })
-    .bind("127.0.0.1:8080")?
-    .run()
-    .await
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let clock: Arc<dyn util::clock::Clock> = Arc::new(util::clock::SystemClock);
+
+    if let Err(e) = util::snowflake::worker_id() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+
+    // DB_BACKEND="memory" runs the backend without a database, nothing survives a restart.
+    // DB_BACKEND="sqlite" keeps everything in a single file, see SQLITE_PATH
+    let backend = match secrets::SECRETS.get("DB_BACKEND").map(String::as_str) {
+        Some("memory") => db::Backend::Memory(db::memory::MemoryStore::new(clock.clone())),
+        Some("sqlite") => db::Backend::Sqlite(
+            db::sqlite::connect()
+                .await
+                .expect("failed to open the sqlite database"),
+        ),
+        _ => db::Backend::Postgres(
+            db::connect()
+                .await
+                .expect("failed to connect to the database"),
+        ),
+    };
+
+    if args.first().map(String::as_str) == Some("migrate") {
+        if let Err(e) = migrate_command(&backend, &args[1..]).await {
+            eprintln!("migration failed: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if secrets::SECRETS
+        .get("MIGRATE_ON_STARTUP")
+        .map(String::as_str)
+        != Some("false")
+    {
+        db::migrations::run(&backend)
+            .await
+            .expect("failed to apply the database migrations");
+    }
+
+    let mailer = mailer::from_config(clock.clone()).expect("failed to set up the mailer");
+    let state = state::AppState::new(backend, mailer, clock).expect("invalid database settings");
+
+    scheduler::jobs::maintenance_scheduler(state.clone()).start();
+
+    let state = web::Data::new(state);
+
+    HttpServer::new(move || app::build_app(state.clone()))
+        .bind("127.0.0.1:8080")?
+        .run()
+        .await
 }
@@ -0,0 +1,52 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// who an announcement goes to, always only users with a verified email
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Audience {
+    All,
+    Owners,
+    // users who turned cloudthemes on
+    Cloudthemes,
+}
+
+impl Audience {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Audience::All => "all",
+            Audience::Owners => "owners",
+            Audience::Cloudthemes => "cloudthemes",
+        }
+    }
+
+    pub fn parse(audience: &str) -> Option<Self> {
+        match audience {
+            "all" => Some(Audience::All),
+            "owners" => Some(Audience::Owners),
+            "cloudthemes" => Some(Audience::Cloudthemes),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Announcement {
+    pub id: i64,
+    pub created_by: i64,
+    pub subject: String,
+    pub body: String,
+    pub audience: Audience,
+    pub send_email: bool,
+    // the emails go out in batches ordered by uid, this is the last uid which got one
+    pub email_cursor: i64,
+    pub email_done: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recipient {
+    pub uid: i64,
+    pub email: String,
+    pub username: String,
+}
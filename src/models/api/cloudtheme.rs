@@ -1,9 +1,16 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+// one of the named themes of a user, exactly one of them is active while the user has any
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CloudTheme {
+    pub id: i64,
     pub uid: i64,
+    pub name: String,
+    pub active: bool,
     pub theme: Theme,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
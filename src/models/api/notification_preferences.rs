@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationCategory {
+    // codes, password changes and other account security emails, can't be turned off
+    Security,
+    Reminders,
+    LoginAlerts,
+    Announcements,
+}
+
+impl NotificationCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationCategory::Security => "security",
+            NotificationCategory::Reminders => "reminders",
+            NotificationCategory::LoginAlerts => "login_alerts",
+            NotificationCategory::Announcements => "announcements",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationPreferences {
+    // always true, only here so the client sees every category
+    pub security: bool,
+    pub reminders: bool,
+    pub login_alerts: bool,
+    pub announcements: bool,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        NotificationPreferences {
+            security: true,
+            reminders: true,
+            login_alerts: true,
+            announcements: true,
+        }
+    }
+}
+
+impl NotificationPreferences {
+    pub fn allows(&self, category: NotificationCategory) -> bool {
+        match category {
+            NotificationCategory::Security => true,
+            NotificationCategory::Reminders => self.reminders,
+            NotificationCategory::LoginAlerts => self.login_alerts,
+            NotificationCategory::Announcements => self.announcements,
+        }
+    }
+
+    pub fn set(&mut self, category: NotificationCategory, enabled: bool) {
+        match category {
+            NotificationCategory::Security => (),
+            NotificationCategory::Reminders => self.reminders = enabled,
+            NotificationCategory::LoginAlerts => self.login_alerts = enabled,
+            NotificationCategory::Announcements => self.announcements = enabled,
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_SENT: &str = "sent";
+pub const STATUS_DEAD: &str = "dead";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxMessage {
+    pub id: i64,
+    pub recipient: String,
+    pub subject: String,
+    pub html: String,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub text: String,
+    pub unsubscribe_url: Option<String>,
+}
+
+// what owners get to see of a message. the bodies hold codes and login links, so they stay out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxSummary {
+    pub id: i64,
+    pub recipient: String,
+    pub subject: String,
+    pub status: String,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+}
+
+impl From<&OutboxMessage> for OutboxSummary {
+    fn from(message: &OutboxMessage) -> Self {
+        Self {
+            id: message.id,
+            recipient: message.recipient.clone(),
+            subject: message.subject.clone(),
+            status: message.status.clone(),
+            attempts: message.attempts,
+            last_error: message.last_error.clone(),
+            next_attempt_at: message.next_attempt_at,
+            created_at: message.created_at,
+            sent_at: message.sent_at,
+        }
+    }
+}
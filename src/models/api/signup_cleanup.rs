@@ -0,0 +1,45 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub const ACTION_REMINDER: &str = "reminder";
+pub const ACTION_DELETE: &str = "delete";
+
+// one line of the audit log, written for every reminder and deletion (also in dry runs)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignupCleanupEntry {
+    pub id: i64,
+    pub run_id: i64,
+    pub action: String,
+    pub uid: i64,
+    pub username: String,
+    pub reminder_day: Option<i32>,
+    pub dry_run: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedReminder {
+    pub uid: i64,
+    pub username: String,
+    pub email: String,
+    pub created_at: DateTime<Utc>,
+    // the reminder this is, e.g. 3 for the one sent 3 days after registering
+    pub reminder_day: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedDeletion {
+    pub uid: i64,
+    pub username: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// what a cleanup run does (or would do in a dry run)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignupCleanupReport {
+    pub run_id: i64,
+    pub dry_run: bool,
+    pub generated_at: DateTime<Utc>,
+    pub reminders: Vec<PlannedReminder>,
+    pub deletions: Vec<PlannedDeletion>,
+}
@@ -0,0 +1,56 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::cloudtheme::Theme;
+
+// the orders the public gallery can be browsed in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GallerySort {
+    #[default]
+    Newest,
+    MostInstalled,
+}
+
+// a published copy of a cloudtheme, the author is the username of uid
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GalleryTheme {
+    pub id: i64,
+    pub share_code: String,
+    pub uid: i64,
+    // the id of the cloudtheme it was published from
+    pub source_id: i64,
+    pub author: String,
+    pub title: String,
+    pub description: String,
+    // unlisted entries are only found by their share code
+    pub listed: bool,
+    pub theme: Theme,
+    pub installs: i64,
+    pub published: bool,
+    pub unpublished_by: Option<i64>,
+    pub unpublish_reason: Option<String>,
+    pub unpublished_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl GalleryTheme {
+    // taken down by someone else than the author, i.e. an owner
+    pub fn moderated(&self) -> bool {
+        !self.published && self.unpublished_by != Some(self.uid)
+    }
+
+    // the colors the gallery shows as swatches, most prominent first
+    pub fn preview(&self) -> [&str; 8] {
+        [
+            &self.theme.background_color_primary,
+            &self.theme.background_color_secondary,
+            &self.theme.background_color_tertiary,
+            &self.theme.primary_color,
+            &self.theme.secondary_color,
+            &self.theme.primary_color_text,
+            &self.theme.primary_grey,
+            &self.theme.secondary_grey,
+        ]
+    }
+}
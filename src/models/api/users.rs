@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -7,4 +8,11 @@ pub struct User {
     pub owner: bool,
     pub email_verified: bool,
     pub username: String,
+    // preferred language for emails, None means the Accept-Language of the request is used
+    pub locale: Option<String>,
+    pub created_at: DateTime<Utc>,
+    // the last change of the account itself, logins don't count
+    pub updated_at: DateTime<Utc>,
+    pub last_login_at: Option<DateTime<Utc>>,
+    pub email_verified_at: Option<DateTime<Utc>>,
 }
@@ -37,15 +37,9 @@ lazy_static::lazy_static! {
     static ref CACHE_REFRESH_TIMESTAMP: SharedTimestamp = Arc::new(Mutex::new(Utc::now()));
 }
 
-#[get("/repo")]
-pub async fn get_repo_() -> HttpResponse {
-    let now = Utc::now();
-
-    let cache_timestamp = CACHE_REFRESH_TIMESTAMP.clone();
-
-    let mut cached_timestamp = cache_timestamp.lock().await;
-
-    let cache = &*GITHUB_REPO_CACHE;
+// fetches all configured repos from github and stores them in the cache for the next 10 minutes
+pub async fn refresh_repo_cache() -> anyhow::Result<Vec<RepoInfo>> {
+    *CACHE_REFRESH_TIMESTAMP.lock().await = Utc::now() + Duration::minutes(10);
 
     let repos: Vec<String> = SECRETS
         .get("REPO")
@@ -54,44 +48,31 @@ pub async fn get_repo_() -> HttpResponse {
         .map(|s| s.trim().to_string())
         .collect();
 
-    if now > *cached_timestamp {
-        *cached_timestamp = now + Duration::minutes(10);
+    let mut repos_vec: Vec<RepoInfo> = Vec::new();
 
-        drop(cached_timestamp);
+    for repo in repos {
+        repos_vec.push(get_repo_info(SECRETS.get("OWNER").unwrap(), &repo).await?);
+    }
 
-        let mut repos_vec: Vec<RepoInfo> = Vec::new();
+    GITHUB_REPO_CACHE.insert(0, repos_vec.clone());
 
-        for repo in repos {
-            let repo = match get_repo_info(SECRETS.get("OWNER").unwrap(), &repo).await {
-                Ok(repo) => repo,
-                Err(e) => return error_response!(500, e.to_string()),
-            };
+    Ok(repos_vec)
+}
 
-            repos_vec.push(repo);
-        }
+#[get("/repo")]
+pub async fn get_repo_() -> HttpResponse {
+    let now = Utc::now();
 
-        cache.insert(0, repos_vec.clone());
+    let cache_expired = now > *CACHE_REFRESH_TIMESTAMP.lock().await;
 
-        return HttpResponse::Ok().json(repos_vec);
-    } else {
-        drop(cached_timestamp);
-        if let Some(cache) = cache.get(&0) {
+    if !cache_expired {
+        if let Some(cache) = GITHUB_REPO_CACHE.get(&0) {
             return HttpResponse::Ok().json(cache);
-        } else {
-            let mut repos_vec: Vec<RepoInfo> = Vec::new();
-
-            for repo in repos {
-                let repo = match get_repo_info(SECRETS.get("OWNER").unwrap(), &repo).await {
-                    Ok(repo) => repo,
-                    Err(e) => return error_response!(500, e.to_string()),
-                };
-
-                repos_vec.push(repo);
-            }
-
-            cache.insert(0, repos_vec.clone());
-
-            return HttpResponse::Ok().json(repos_vec);
         }
     }
+
+    match refresh_repo_cache().await {
+        Ok(repos) => HttpResponse::Ok().json(repos),
+        Err(e) => error_response!(500, e.to_string()),
+    }
 }
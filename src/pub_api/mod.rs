@@ -1,2 +1,4 @@
 pub mod github;
-pub mod faith;
\ No newline at end of file
+pub mod faith;
+pub mod themes;
+pub mod unsubscribe;
\ No newline at end of file
@@ -0,0 +1,74 @@
+use actix_web::{get, web, HttpResponse};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    api::cloudthemes::gallery::normalize_share_code,
+    db::api::theme_gallery::{ThemeGalleryDatabase, ThemeGalleryDb},
+    error_response,
+    models::api::theme_gallery::{GallerySort, GalleryTheme},
+};
+
+// the public theme gallery, works without being logged in. installing an entry needs an account,
+// see api::cloudthemes::gallery
+
+const DEFAULT_PAGE_SIZE: i64 = 24;
+const MAX_PAGE_SIZE: i64 = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct GalleryQuery {
+    #[serde(default)]
+    sort: GallerySort,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+// what anyone can see of an entry, who moderated it stays with the author and the owners
+fn public_entry(entry: &GalleryTheme) -> Value {
+    json!({
+        "share_code": entry.share_code,
+        "author": entry.author,
+        "title": entry.title,
+        "description": entry.description,
+        "preview": entry.preview(),
+        "theme": entry.theme,
+        "installs": entry.installs,
+        "created_at": entry.created_at,
+    })
+}
+
+// `?sort=newest` (the default) or `?sort=most_installed`, paged with limit and offset
+#[get("/themes")]
+pub async fn get_gallery(
+    query: web::Query<GalleryQuery>,
+    gallery: ThemeGalleryDatabase,
+) -> HttpResponse {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    match gallery.read_gallery(query.sort, limit, offset).await {
+        Ok(entries) => {
+            HttpResponse::Ok().json(entries.iter().map(public_entry).collect::<Vec<_>>())
+        }
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
+
+// unlisted entries are found here too, that is what the share code is for
+#[get("/themes/{share_code}")]
+pub async fn get_gallery_theme(
+    path: web::Path<String>,
+    gallery: ThemeGalleryDatabase,
+) -> HttpResponse {
+    match gallery
+        .read_by_share_code(&normalize_share_code(&path.into_inner()))
+        .await
+    {
+        Ok(Some(entry)) if entry.published => HttpResponse::Ok().json(public_entry(&entry)),
+        Ok(_) => error_response!(404, "couldnt find a theme with this share code"),
+        Err(e) => error_response!(500, e.to_string()),
+    }
+}
@@ -0,0 +1,76 @@
+use actix_web::{get, post, web, HttpResponse};
+use serde::Deserialize;
+
+use crate::{
+    db::api::notification_preferences::{
+        NotificationPreferencesDatabase, NotificationPreferencesDb,
+    },
+    mailer::notifications::decode_unsubscribe_token,
+};
+
+// the unsubscribe link from optional emails, works without being logged in.
+// GET only asks for confirmation so link scanners can't unsubscribe anyone,
+// POST unsubscribes and is also what mail clients send for the one click List-Unsubscribe-Post
+
+#[derive(Debug, Deserialize)]
+pub struct UnsubscribeQuery {
+    token: String,
+}
+
+fn html_page(body: &str) -> HttpResponse {
+    HttpResponse::Ok().content_type("text/html; charset=utf-8").body(format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>Unsubscribe</title></head><body>{}</body></html>",
+        body
+    ))
+}
+
+#[get("/unsubscribe")]
+pub async fn unsubscribe_page(query: web::Query<UnsubscribeQuery>) -> HttpResponse {
+    let category = match decode_unsubscribe_token(&query.token) {
+        Some((_, category)) => category,
+        None => {
+            return HttpResponse::BadRequest()
+                .content_type("text/html; charset=utf-8")
+                .body("this unsubscribe link is invalid.")
+        }
+    };
+
+    // the token is only base64 and dots, it can go into the form as is
+    html_page(&format!(
+        "<p>Do you want to stop receiving <strong>{}</strong> emails from acid4sigmas?</p>\
+        <form method=\"post\" action=\"unsubscribe?token={}\"><button type=\"submit\">Unsubscribe</button></form>",
+        category.as_str().replace('_', " "),
+        query.token
+    ))
+}
+
+#[post("/unsubscribe")]
+pub async fn unsubscribe(
+    query: web::Query<UnsubscribeQuery>,
+    db: NotificationPreferencesDatabase,
+) -> HttpResponse {
+    let (uid, category) = match decode_unsubscribe_token(&query.token) {
+        Some(result) => result,
+        None => {
+            return HttpResponse::BadRequest()
+                .content_type("text/html; charset=utf-8")
+                .body("this unsubscribe link is invalid.")
+        }
+    };
+
+    let mut preferences = match db.read_by_uid(uid).await {
+        Ok(preferences) => preferences,
+        Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+    };
+
+    preferences.set(category, false);
+
+    if let Err(e) = db.upsert(uid, &preferences).await {
+        return HttpResponse::InternalServerError().body(e.to_string());
+    }
+
+    html_page(&format!(
+        "<p>You won't receive <strong>{}</strong> emails anymore. You can turn them back on in your account settings.</p>",
+        category.as_str().replace('_', " ")
+    ))
+}
@@ -0,0 +1,91 @@
+use anyhow::Result;
+use minijinja::context;
+
+use crate::{
+    db::api::announcements::AnnouncementDb,
+    mailer::{locale::Locale, notifications::send_notification},
+    models::api::notification_preferences::NotificationCategory,
+    secrets::secret,
+    state::AppState,
+};
+
+// announcement emails go out a batch per run, so a big audience doesn't fill the outbox at once
+
+const DEFAULT_ANNOUNCEMENT_BATCH_SIZE: i64 = 50;
+
+fn batch_size() -> i64 {
+    secret("ANNOUNCEMENT_BATCH_SIZE")
+        .and_then(|size| size.parse().ok())
+        .filter(|size| *size > 0)
+        .unwrap_or(DEFAULT_ANNOUNCEMENT_BATCH_SIZE)
+}
+
+pub async fn send_emails(state: AppState) -> Result<()> {
+    let db = state.announcements();
+
+    let mut remaining = batch_size();
+
+    for announcement in db.read_pending_emails().await? {
+        if remaining == 0 {
+            break;
+        }
+
+        let recipients = db
+            .read_recipients(announcement.audience, announcement.email_cursor, remaining)
+            .await?;
+
+        let done = (recipients.len() as i64) < remaining;
+        let mut cursor = announcement.email_cursor;
+
+        // blank lines separate the paragraphs of the body
+        let paragraphs: Vec<&str> = announcement
+            .body
+            .split("\n\n")
+            .map(str::trim)
+            .filter(|paragraph| !paragraph.is_empty())
+            .collect();
+
+        for recipient in recipients {
+            let locale = Locale::stored(&state, recipient.uid)
+                .await
+                .unwrap_or_default();
+
+            // one address which can't get the email doesn't hold up everybody after it, it is skipped
+            if let Err(e) = send_notification(
+                &state,
+                recipient.uid,
+                &recipient.email,
+                NotificationCategory::Announcements,
+                "announcement",
+                locale,
+                context! {
+                    subject => announcement.subject,
+                    paragraphs,
+                    username => recipient.username,
+                },
+            )
+            .await
+            {
+                println!(
+                    "failed to queue announcement {} for {}: {}",
+                    announcement.id, recipient.uid, e
+                );
+            }
+
+            cursor = recipient.uid;
+            remaining -= 1;
+
+            // keeps the progress if a later email fails, so nobody gets it twice
+            db.update_email_progress(announcement.id, cursor, false)
+                .await?;
+        }
+
+        if done {
+            db.update_email_progress(announcement.id, cursor, true)
+                .await?;
+            println!("sent all emails of announcement {}", announcement.id);
+        }
+    }
+
+    Ok(())
+}
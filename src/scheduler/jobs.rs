@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Duration as ChronoDuration;
+
+use crate::{
+    auth::utils::CodeStorage,
+    db::{api::outbox::OutboxDb, auth::tokens::TokenDb},
+    mailer::queue::deliver_due,
+    pub_api::github::refresh_repo_cache,
+    state::AppState,
+};
+
+use super::{
+    announcements,
+    runner::{Job, Scheduler},
+    signup_cleanup,
+};
+
+// the jobs get their own clone of the state, it only holds handles to the shared pool
+pub fn maintenance_scheduler(state: AppState) -> Scheduler {
+    Scheduler::new()
+        .add(Job::every(
+            "deliver_email_outbox",
+            Duration::from_secs(5),
+            {
+                let state = state.clone();
+                move || deliver_due(state.clone())
+            },
+        ))
+        .add(
+            Job::cron("prune_sent_emails", "0 30 3 * * *", {
+                let state = state.clone();
+                move || prune_sent_emails(state.clone())
+            })
+            .unwrap(),
+        )
+        .add(
+            Job::every("prune_expired_tokens", Duration::from_secs(60 * 60), {
+                let state = state.clone();
+                move || prune_expired_tokens(state.clone())
+            })
+            .with_jitter(Duration::from_secs(60)),
+        )
+        .add(Job::every(
+            "prune_expired_codes",
+            Duration::from_secs(5 * 60),
+            {
+                let state = state.clone();
+                move || prune_expired_codes(state.clone())
+            },
+        ))
+        .add(
+            Job::every("clean_up_signups", Duration::from_secs(60 * 60), {
+                let state = state.clone();
+                move || signup_cleanup::run(state.clone())
+            })
+            .with_jitter(Duration::from_secs(5 * 60)),
+        )
+        .add(Job::every(
+            "send_announcement_emails",
+            Duration::from_secs(60),
+            move || announcements::send_emails(state.clone()),
+        ))
+        .add(
+            Job::every(
+                "refresh_github_cache",
+                Duration::from_secs(10 * 60),
+                refresh_github_cache,
+            )
+            .with_jitter(Duration::from_secs(30)),
+        )
+}
+
+async fn prune_expired_tokens(state: AppState) -> Result<()> {
+    let db = state.auth_tokens();
+
+    let removed = db.delete_expired().await?;
+
+    if removed > 0 {
+        println!("pruned {} expired tokens", removed);
+    }
+
+    Ok(())
+}
+
+async fn prune_expired_codes(state: AppState) -> Result<()> {
+    let clock = state.clock.as_ref();
+
+    CodeStorage::EmailVerificationCodes.prune_expired(clock);
+    CodeStorage::PasswordResetCodes.prune_expired(clock);
+    CodeStorage::MagicLinkCodes.prune_expired(clock);
+
+    Ok(())
+}
+
+async fn prune_sent_emails(state: AppState) -> Result<()> {
+    let db = state.outbox();
+
+    db.delete_sent_before(state.clock.now() - ChronoDuration::days(7))
+        .await?;
+
+    Ok(())
+}
+
+async fn refresh_github_cache() -> Result<()> {
+    refresh_repo_cache().await?;
+
+    Ok(())
+}
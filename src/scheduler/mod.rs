@@ -0,0 +1,4 @@
+pub mod announcements;
+pub mod jobs;
+pub mod runner;
+pub mod signup_cleanup;
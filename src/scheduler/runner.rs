@@ -0,0 +1,174 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::Serialize;
+
+type JobFuture = Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send>>;
+type JobFn = Arc<dyn Fn() -> JobFuture + Send + Sync>;
+
+pub enum Schedule {
+    Every(Duration),
+    // cron expression with seconds, e.g. "0 0 3 * * *" for every day at 03:00 UTC
+    Cron(Box<cron::Schedule>),
+}
+
+impl Schedule {
+    fn next_delay(&self) -> Duration {
+        match self {
+            Schedule::Every(interval) => *interval,
+            Schedule::Cron(schedule) => schedule
+                .upcoming(Utc)
+                .next()
+                .and_then(|next| (next - Utc::now()).to_std().ok())
+                .unwrap_or(Duration::from_secs(60 * 60)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct JobMetrics {
+    pub runs: u64,
+    pub failures: u64,
+    // ticks that were skipped because the previous run was still going
+    pub skipped: u64,
+    pub running: bool,
+    pub last_started_at: Option<DateTime<Utc>>,
+    pub last_duration_ms: Option<u128>,
+    pub last_error: Option<String>,
+}
+
+lazy_static::lazy_static! {
+    pub static ref JOB_METRICS: Mutex<HashMap<&'static str, JobMetrics>> = Mutex::new(HashMap::new());
+}
+
+pub struct Job {
+    name: &'static str,
+    schedule: Schedule,
+    jitter: Duration,
+    run: JobFn,
+}
+
+impl Job {
+    pub fn every<F, Fut>(name: &'static str, interval: Duration, run: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        Job {
+            name,
+            schedule: Schedule::Every(interval),
+            jitter: Duration::ZERO,
+            run: Arc::new(move || Box::pin(run())),
+        }
+    }
+
+    pub fn cron<F, Fut>(name: &'static str, expression: &str, run: F) -> anyhow::Result<Self>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        Ok(Job {
+            name,
+            schedule: Schedule::Cron(Box::new(cron::Schedule::from_str(expression)?)),
+            jitter: Duration::ZERO,
+            run: Arc::new(move || Box::pin(run())),
+        })
+    }
+
+    // delays every run by a random amount up to `jitter`, so jobs don't all fire at once
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    fn next_delay(&self) -> Duration {
+        let jitter_ms = self.jitter.as_millis() as u64;
+        let jitter = if jitter_ms > 0 {
+            Duration::from_millis(rand::thread_rng().gen_range(0..=jitter_ms))
+        } else {
+            Duration::ZERO
+        };
+
+        self.schedule.next_delay() + jitter
+    }
+}
+
+#[derive(Default)]
+pub struct Scheduler {
+    jobs: Vec<Job>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Scheduler { jobs: Vec::new() }
+    }
+
+    pub fn add(mut self, job: Job) -> Self {
+        self.jobs.push(job);
+        self
+    }
+
+    pub fn start(self) {
+        for job in self.jobs {
+            JOB_METRICS
+                .lock()
+                .unwrap()
+                .insert(job.name, JobMetrics::default());
+
+            tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(job.next_delay()).await;
+
+                    {
+                        let mut metrics = JOB_METRICS.lock().unwrap();
+                        let metrics = metrics.entry(job.name).or_default();
+
+                        if metrics.running {
+                            metrics.skipped += 1;
+                            continue;
+                        }
+
+                        metrics.running = true;
+                        metrics.last_started_at = Some(Utc::now());
+                    }
+
+                    tokio::spawn(run_job(job.name, job.run.clone()));
+                }
+            });
+        }
+    }
+}
+
+async fn run_job(name: &'static str, run: JobFn) {
+    let started = Instant::now();
+
+    // running the job in its own task keeps a panic from leaving it marked as running forever
+    let result = match tokio::spawn(run()).await {
+        Ok(result) => result,
+        Err(e) => Err(anyhow::anyhow!("job panicked: {}", e)),
+    };
+
+    let mut metrics = JOB_METRICS.lock().unwrap();
+    let metrics = metrics.entry(name).or_default();
+
+    metrics.running = false;
+    metrics.runs += 1;
+    metrics.last_duration_ms = Some(started.elapsed().as_millis());
+
+    match result {
+        Ok(()) => metrics.last_error = None,
+        Err(e) => {
+            println!("job '{}' failed: {}", name, e);
+            metrics.failures += 1;
+            metrics.last_error = Some(e.to_string());
+        }
+    }
+}
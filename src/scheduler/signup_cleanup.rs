@@ -0,0 +1,225 @@
+use anyhow::Result;
+use chrono::{Duration, NaiveTime};
+use minijinja::context;
+
+use crate::{
+    auth::{links, utils::CodeStorage},
+    cache::init_caches::{USER_CACHE, USER_ME_CACHE},
+    db::{api::signup_cleanup::SignupCleanupDb, auth::auth::AuthDb},
+    mailer::{locale::Locale, notifications::send_notification},
+    models::api::notification_preferences::NotificationCategory,
+    models::api::signup_cleanup::{
+        PlannedDeletion, PlannedReminder, SignupCleanupReport, ACTION_DELETE, ACTION_REMINDER,
+    },
+    secrets::SECRETS,
+    state::AppState,
+    util::snowflake::generate_uid,
+};
+
+// accounts which never verified their email get reminded a few times and are deleted in the end,
+// so their username and email become available again
+
+const DEFAULT_REMINDER_DAYS: [i64; 2] = [1, 3];
+const DEFAULT_UNVERIFIED_ACCOUNT_MAX_AGE_DAYS: i64 = 30;
+
+// days after the registration a reminder is sent, e.g. VERIFICATION_REMINDER_DAYS = [1, 3]
+fn reminder_days() -> Vec<i64> {
+    match SECRETS.get("VERIFICATION_REMINDER_DAYS") {
+        Some(days) => days
+            .split(',')
+            .filter_map(|day| day.trim().parse().ok())
+            .collect(),
+        None => DEFAULT_REMINDER_DAYS.to_vec(),
+    }
+}
+
+fn unverified_account_max_age() -> Duration {
+    let days = SECRETS
+        .get("UNVERIFIED_ACCOUNT_MAX_AGE_DAYS")
+        .and_then(|days| days.parse().ok())
+        .unwrap_or(DEFAULT_UNVERIFIED_ACCOUNT_MAX_AGE_DAYS);
+
+    Duration::days(days)
+}
+
+// with SIGNUP_CLEANUP_DRY_RUN = true the job only writes what it would do into the log
+fn dry_run_enabled() -> bool {
+    SECRETS
+        .get("SIGNUP_CLEANUP_DRY_RUN")
+        .map(|dry_run| dry_run == "true")
+        .unwrap_or(false)
+}
+
+// works out which reminders and deletions are due, without changing anything
+pub async fn plan(state: &AppState, dry_run: bool) -> Result<SignupCleanupReport> {
+    let now = state.clock.now();
+    let max_age = unverified_account_max_age();
+    let reminder_days = reminder_days();
+
+    let auth_db = state.auth_users();
+    let log_db = state.signup_cleanup();
+
+    let mut reminders = Vec::new();
+    let mut deletions = Vec::new();
+
+    for user in auth_db.read_unverified().await? {
+        let created_at = user.created_at;
+        let age = now - created_at;
+
+        if age >= max_age {
+            deletions.push(PlannedDeletion {
+                uid: user.uid,
+                username: user.username,
+                created_at,
+            });
+            continue;
+        }
+
+        // only the latest due reminder is sent, so a missed day 1 doesn't arrive together with day 3
+        let due = reminder_days
+            .iter()
+            .filter(|day| age >= Duration::days(**day))
+            .max();
+
+        if let Some(day) = due {
+            let day = *day as i32;
+
+            if !log_db.sent_reminder_days(user.uid).await?.contains(&day) {
+                reminders.push(PlannedReminder {
+                    uid: user.uid,
+                    username: user.username,
+                    email: user.email,
+                    created_at,
+                    reminder_day: day,
+                });
+            }
+        }
+    }
+
+    Ok(SignupCleanupReport {
+        run_id: generate_uid(),
+        dry_run,
+        generated_at: now,
+        reminders,
+        deletions,
+    })
+}
+
+// sends the reminders and deletes the accounts of a report, every step ends up in the log.
+// in a dry run only the log is written, once a day per account and action since the job runs
+// every hour and would log the same accounts again each time
+pub async fn apply(state: &AppState, report: &SignupCleanupReport) -> Result<()> {
+    let auth_db = state.auth_users();
+    let log_db = state.signup_cleanup();
+
+    let delete_after = unverified_account_max_age();
+    let start_of_day = report
+        .generated_at
+        .date_naive()
+        .and_time(NaiveTime::MIN)
+        .and_utc();
+
+    for reminder in &report.reminders {
+        if report.dry_run {
+            if log_db
+                .dry_run_logged_since(reminder.uid, ACTION_REMINDER, start_of_day)
+                .await?
+            {
+                continue;
+            }
+        } else {
+            // the user might have verified since the report was made
+            let user = match auth_db.read_by_uid(reminder.uid).await? {
+                Some(user) if !user.email_verified => user,
+                _ => continue,
+            };
+
+            let code = match CodeStorage::EmailVerificationCodes
+                .create(state.clock.as_ref(), &user.uid.to_string())
+            {
+                Ok(code) => code,
+                // a code was requested a moment ago, the next run tries again
+                Err(_) => continue,
+            };
+
+            let link = links::verify_email_link(user.uid, &user.password_hash, &code);
+            let locale = Locale::stored(state, user.uid).await.unwrap_or_default();
+            let deleted_on = (reminder.created_at + delete_after)
+                .format("%Y-%m-%d")
+                .to_string();
+
+            // users who turned reminders off still get deleted in the end, they just aren't reminded
+            send_notification(
+                state,
+                user.uid,
+                &user.email,
+                NotificationCategory::Reminders,
+                "verification_reminder",
+                locale,
+                context! { code, link, username => user.username, deleted_on },
+            )
+            .await?;
+        }
+
+        log_db
+            .insert(
+                report.run_id,
+                ACTION_REMINDER,
+                reminder.uid,
+                &reminder.username,
+                Some(reminder.reminder_day),
+                report.dry_run,
+            )
+            .await?;
+    }
+
+    for deletion in &report.deletions {
+        if report.dry_run {
+            if log_db
+                .dry_run_logged_since(deletion.uid, ACTION_DELETE, start_of_day)
+                .await?
+            {
+                continue;
+            }
+        } else {
+            match auth_db.read_by_uid(deletion.uid).await? {
+                Some(user) if !user.email_verified => (),
+                _ => continue,
+            }
+
+            auth_db.delete_by_uid(deletion.uid).await?;
+
+            USER_CACHE.remove(&deletion.uid);
+            USER_ME_CACHE.remove(&deletion.uid);
+        }
+
+        log_db
+            .insert(
+                report.run_id,
+                ACTION_DELETE,
+                deletion.uid,
+                &deletion.username,
+                None,
+                report.dry_run,
+            )
+            .await?;
+    }
+
+    if !report.reminders.is_empty() || !report.deletions.is_empty() {
+        println!(
+            "signup cleanup run {}{}: {} reminders, {} deletions",
+            report.run_id,
+            if report.dry_run { " (dry run)" } else { "" },
+            report.reminders.len(),
+            report.deletions.len()
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn run(state: AppState) -> Result<()> {
+    let report = plan(&state, dry_run_enabled()).await?;
+
+    apply(&state, &report).await
+}
@@ -1,14 +1,34 @@
 use std::collections::HashMap;
 
+#[cfg(test)]
+use std::cell::RefCell;
+
+// the tests read their own secrets, SECRETS_FILE points somewhere else for both
+fn secrets_file() -> String {
+    let default = if cfg!(test) {
+        "src/tests/Secrets.toml"
+    } else {
+        "Secrets.toml"
+    };
+
+    std::env::var("SECRETS_FILE").unwrap_or_else(|_| default.to_string())
+}
+
 lazy_static::lazy_static! {
     pub static ref SECRETS: HashMap<String, String> = {
-        let contents = std::fs::read_to_string("Secrets.toml").unwrap();
+        let contents = std::fs::read_to_string(secrets_file()).unwrap();
         let data: toml::Value = contents.parse().unwrap();
         let mut secrets = HashMap::new();
         secrets.insert("SECRET_KEY".to_string(), data["SECRET_KEY"].as_str().unwrap().to_string());
-        secrets.insert("DB_NAME".to_string(), data["DB_NAME"].as_str().unwrap().to_string());
-        secrets.insert("DB_PW".to_string(), data["DB_PW"].as_str().unwrap().to_string());
-        secrets.insert("DB_PORT".to_string(), data["DB_PORT"].as_str().unwrap().to_string());
+        for key in ["DB_BACKEND", "DATABASE_URL", "DB_HOST", "DB_USER", "DB_NAME", "DB_PW", "DB_PASSWORD_FILE", "DB_SSL_MODE", "DB_SSL_ROOT_CERT", "DB_APPLICATION_NAME", "SQLITE_PATH"] {
+            if let Some(value) = data.get(key).and_then(|val| val.as_str()) {
+                secrets.insert(key.to_string(), value.to_string());
+            }
+        }
+        // the port used to be a string, numbers work too
+        if let Some(port) = data.get("DB_PORT").and_then(|val| val.as_str().map(str::to_string).or_else(|| val.as_integer().map(|port| port.to_string()))) {
+            secrets.insert("DB_PORT".to_string(), port);
+        }
         secrets.insert("NO_REPLY_EMAIL".to_string(), data["NO_REPLY_EMAIL"].as_str().unwrap().to_string());
         secrets.insert("SMTP_USERNAME".to_string(), data["SMTP_USERNAME"].as_str().unwrap().to_string());
         secrets.insert("SMTP_PASSWORD".to_string(), data["SMTP_PASSWORD"].as_str().unwrap().to_string());
@@ -22,6 +42,71 @@ lazy_static::lazy_static! {
             secrets.insert("REPO".to_string(), repos);
         }
         secrets.insert("OWNER".to_string(), data["OWNER"].as_str().unwrap().to_string());
+        if let Some(mode) = data.get("REGISTRATION_MODE").and_then(|val| val.as_str()) {
+            secrets.insert("REGISTRATION_MODE".to_string(), mode.to_string());
+        }
+        if let Some(hardened) = data.get("HARDENED_AUTH").and_then(|val| val.as_bool()) {
+            secrets.insert("HARDENED_AUTH".to_string(), hardened.to_string());
+        }
+        if let Some(enabled) = data.get("MAGIC_LINK_LOGIN").and_then(|val| val.as_bool()) {
+            secrets.insert("MAGIC_LINK_LOGIN".to_string(), enabled.to_string());
+        }
+        if let Some(worker_id) = data.get("WORKER_ID").and_then(|val| val.as_integer()) {
+            secrets.insert("WORKER_ID".to_string(), worker_id.to_string());
+        }
+        if let Some(days) = data.get("VERIFICATION_REMINDER_DAYS").and_then(|val| val.as_array()) {
+            let days = days
+                .iter()
+                .filter_map(|day| day.as_integer())
+                .map(|day| day.to_string())
+                .collect::<Vec<String>>()
+                .join(",");
+            secrets.insert("VERIFICATION_REMINDER_DAYS".to_string(), days);
+        }
+        if let Some(dry_run) = data.get("SIGNUP_CLEANUP_DRY_RUN").and_then(|val| val.as_bool()) {
+            secrets.insert("SIGNUP_CLEANUP_DRY_RUN".to_string(), dry_run.to_string());
+        }
+        if let Some(days) = data.get("UNVERIFIED_ACCOUNT_MAX_AGE_DAYS").and_then(|val| val.as_integer()) {
+            secrets.insert("UNVERIFIED_ACCOUNT_MAX_AGE_DAYS".to_string(), days.to_string());
+        }
+        for key in ["MAIL_TRANSPORT", "MAIL_DIR", "PUBLIC_URL", "FRONTEND_URL"] {
+            if let Some(value) = data.get(key).and_then(|val| val.as_str()) {
+                secrets.insert(key.to_string(), value.to_string());
+            }
+        }
+        if let Some(limit) = data.get("MAIL_RATE_LIMIT_PER_HOUR").and_then(|val| val.as_integer()) {
+            secrets.insert("MAIL_RATE_LIMIT_PER_HOUR".to_string(), limit.to_string());
+        }
+        if let Some(size) = data.get("ANNOUNCEMENT_BATCH_SIZE").and_then(|val| val.as_integer()) {
+            secrets.insert("ANNOUNCEMENT_BATCH_SIZE".to_string(), size.to_string());
+        }
+        for key in ["DB_POOL_MAX_CONNECTIONS", "DB_POOL_MIN_CONNECTIONS", "DB_ACQUIRE_TIMEOUT_SECS", "DB_IDLE_TIMEOUT_SECS", "DB_MAX_LIFETIME_SECS", "DB_STATEMENT_TIMEOUT_MS", "DB_READ_RETRIES", "DB_BREAKER_THRESHOLD", "DB_BREAKER_COOLDOWN_SECS"] {
+            if let Some(value) = data.get(key).and_then(|val| val.as_integer()) {
+                secrets.insert(key.to_string(), value.to_string());
+            }
+        }
+        if let Some(migrate) = data.get("MIGRATE_ON_STARTUP").and_then(|val| val.as_bool()) {
+            secrets.insert("MIGRATE_ON_STARTUP".to_string(), migrate.to_string());
+        }
+        if let Some(difficulty) = data.get("POW_DIFFICULTY").and_then(|val| val.as_integer()) {
+            secrets.insert("POW_DIFFICULTY".to_string(), difficulty.to_string());
+        }
         secrets
     };
 }
+
+#[cfg(test)]
+thread_local! {
+    // settings a test changed for itself, every test runs on its own thread
+    pub static OVERRIDES: RefCell<HashMap<String, String>> = RefCell::new(HashMap::new());
+}
+
+// a value of the secrets file, the tests can change it for the thread they run on
+pub fn secret(key: &str) -> Option<String> {
+    #[cfg(test)]
+    if let Some(value) = OVERRIDES.with(|overrides| overrides.borrow().get(key).cloned()) {
+        return Some(value);
+    }
+
+    SECRETS.get(key).cloned()
+}
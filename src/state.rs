@@ -0,0 +1,145 @@
+use std::{
+    future::{ready, Ready},
+    sync::Arc,
+};
+
+use actix_web::{dev::Payload, error::ErrorInternalServerError, web, FromRequest, HttpRequest};
+
+use crate::{
+    auth::utils::TokenHandler,
+    db::{
+        api::{
+            announcements::AnnouncementDatabase,
+            cloudthemes::{cloudthemes::CloudThemeDatabase, status::CloudThemeStatusDatabase},
+            invites::InviteDatabase,
+            notification_preferences::NotificationPreferencesDatabase,
+            notifications::NotificationDatabase,
+            outbox::OutboxDatabase,
+            signup_cleanup::SignupCleanupDatabase,
+            theme_gallery::ThemeGalleryDatabase,
+            users::UserDatabase,
+        },
+        auth::{auth::Database as AuthDatabase, tokens::Database as TokenDatabase},
+        resilience::CircuitBreaker,
+        Backend,
+    },
+    mailer::Mailer,
+    util::clock::Clock,
+};
+
+// everything the handlers and jobs share. it is built once in main (or by a test) and handed to
+// actix with web::Data, the repositories only hold a handle to the one pool or store, so building
+// them is cheap
+#[derive(Clone)]
+pub struct AppState {
+    pub backend: Backend,
+    pub mailer: Arc<dyn Mailer>,
+    pub clock: Arc<dyn Clock>,
+    // shared by every repository, see db::resilience
+    pub breaker: Arc<CircuitBreaker>,
+}
+
+impl AppState {
+    pub fn new(
+        backend: Backend,
+        mailer: Arc<dyn Mailer>,
+        clock: Arc<dyn Clock>,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            backend,
+            mailer,
+            breaker: Arc::new(CircuitBreaker::new(clock.clone())?),
+            clock,
+        })
+    }
+
+    // the state of the app handling `req`, registered with `App::app_data`
+    pub fn of(req: &HttpRequest) -> actix_web::Result<&Self> {
+        req.app_data::<web::Data<AppState>>()
+            .map(|state| state.get_ref())
+            .ok_or_else(|| ErrorInternalServerError("the app state is missing"))
+    }
+
+    pub fn auth_users(&self) -> AuthDatabase {
+        AuthDatabase::new(&self.backend, &self.breaker)
+    }
+
+    pub fn auth_tokens(&self) -> TokenDatabase {
+        TokenDatabase::new(&self.backend, &self.breaker)
+    }
+
+    pub fn tokens(&self) -> TokenHandler {
+        TokenHandler::new(self.auth_tokens(), self.clock.clone())
+    }
+
+    pub fn users(&self) -> UserDatabase {
+        UserDatabase::new(&self.backend, &self.breaker)
+    }
+
+    pub fn cloudthemes(&self) -> CloudThemeDatabase {
+        CloudThemeDatabase::new(&self.backend, &self.breaker)
+    }
+
+    pub fn cloudthemes_status(&self) -> CloudThemeStatusDatabase {
+        CloudThemeStatusDatabase::new(&self.backend, &self.breaker)
+    }
+
+    pub fn invites(&self) -> InviteDatabase {
+        InviteDatabase::new(&self.backend, &self.breaker)
+    }
+
+    pub fn outbox(&self) -> OutboxDatabase {
+        OutboxDatabase::new(&self.backend, &self.breaker)
+    }
+
+    pub fn notification_preferences(&self) -> NotificationPreferencesDatabase {
+        NotificationPreferencesDatabase::new(&self.backend, &self.breaker)
+    }
+
+    pub fn notifications(&self) -> NotificationDatabase {
+        NotificationDatabase::new(&self.backend, &self.breaker)
+    }
+
+    pub fn announcements(&self) -> AnnouncementDatabase {
+        AnnouncementDatabase::new(&self.backend, &self.breaker)
+    }
+
+    pub fn signup_cleanup(&self) -> SignupCleanupDatabase {
+        SignupCleanupDatabase::new(&self.backend, &self.breaker)
+    }
+
+    pub fn theme_gallery(&self) -> ThemeGalleryDatabase {
+        ThemeGalleryDatabase::new(&self.backend, &self.breaker)
+    }
+}
+
+// lets handlers take the repositories as arguments, e.g. `users: UserDatabase`
+macro_rules! extract_from_state {
+    ($($repository:ty => $accessor:ident),* $(,)?) => {
+        $(
+            impl FromRequest for $repository {
+                type Error = actix_web::Error;
+                type Future = Ready<Result<Self, Self::Error>>;
+
+                fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+                    ready(AppState::of(req).map(|state| state.$accessor()))
+                }
+            }
+        )*
+    };
+}
+
+extract_from_state! {
+    AuthDatabase => auth_users,
+    TokenHandler => tokens,
+    UserDatabase => users,
+    CloudThemeDatabase => cloudthemes,
+    CloudThemeStatusDatabase => cloudthemes_status,
+    InviteDatabase => invites,
+    OutboxDatabase => outbox,
+    NotificationPreferencesDatabase => notification_preferences,
+    NotificationDatabase => notifications,
+    AnnouncementDatabase => announcements,
+    SignupCleanupDatabase => signup_cleanup,
+    ThemeGalleryDatabase => theme_gallery,
+}
@@ -0,0 +1,79 @@
+use actix_web::{http::StatusCode, test};
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{json, Value};
+
+use super::{authorized, get, post, send, TestApp};
+use crate::{app::build_app, util::clock::Clock};
+
+const EMAIL: &str = "timestamps@example.com";
+const PASSWORD: &str = "Timestamps-password1";
+
+fn time(value: &Value) -> Option<DateTime<Utc>> {
+    value.as_str().map(|time| time.parse().unwrap())
+}
+
+#[actix_web::test]
+async fn accounts_keep_when_they_were_created_changed_verified_and_used() {
+    let harness = TestApp::new();
+    let app = test::init_service(build_app(harness.data())).await;
+
+    let registered_at = harness.clock.now();
+
+    let (status, body) = send!(
+        app,
+        post(
+            "/auth/register",
+            json!({ "username": "timestamps", "password": PASSWORD, "email": EMAIL })
+        )
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let token = body["token"].as_str().unwrap().to_string();
+
+    harness.clock.advance(Duration::hours(1));
+    let verified_at = harness.clock.now();
+
+    let (status, _) = send!(
+        app,
+        post("/auth/send_verification_email", json!({ "token": token }))
+    );
+    assert_eq!(status, StatusCode::OK);
+
+    harness.deliver_emails().await;
+    let code = harness.code_sent_to(EMAIL);
+
+    let (status, body) = send!(
+        app,
+        post(
+            "/auth/verify_email",
+            json!({ "token": token, "code": code })
+        )
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let token = body["token"].as_str().unwrap().to_string();
+
+    // /api/me only opens up once the email is verified
+    let (_, body) = send!(app, authorized(get("/api/me"), &token));
+    assert_eq!(time(&body["created_at"]), Some(registered_at));
+    assert_eq!(time(&body["updated_at"]), Some(verified_at));
+    assert_eq!(time(&body["email_verified_at"]), Some(verified_at));
+    assert_eq!(body["last_login_at"], Value::Null);
+
+    harness.clock.advance(Duration::hours(1));
+    let logged_in_at = harness.clock.now();
+
+    let (status, body) = send!(
+        app,
+        post(
+            "/auth/login",
+            json!({ "username_or_email": EMAIL, "password": PASSWORD })
+        )
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    // a login isn't a change of the account
+    let (_, body) = send!(app, authorized(get("/api/me"), &token));
+    assert_eq!(time(&body["created_at"]), Some(registered_at));
+    assert_eq!(time(&body["updated_at"]), Some(verified_at));
+    assert_eq!(time(&body["email_verified_at"]), Some(verified_at));
+    assert_eq!(time(&body["last_login_at"]), Some(logged_in_at));
+}
@@ -0,0 +1,94 @@
+use actix_web::{http::StatusCode, test};
+use serde_json::json;
+
+use super::{authorized, get, override_secret, post, send, verified_user, TestApp};
+use crate::{app::build_app, scheduler::announcements};
+
+const SUBJECT: &str = "Big news";
+
+fn announcement_emails(harness: &TestApp) -> Vec<String> {
+    harness
+        .mailer
+        .sent()
+        .into_iter()
+        .filter(|email| email.subject == SUBJECT)
+        .map(|email| email.to)
+        .collect()
+}
+
+#[actix_web::test]
+async fn announcement_emails_go_out_a_batch_per_run() {
+    let harness = TestApp::new();
+    let app = test::init_service(build_app(harness.data())).await;
+
+    let _batch_size = override_secret("ANNOUNCEMENT_BATCH_SIZE", "2");
+
+    let owner = verified_user!(harness, app, "announcer", "announcer@example.com");
+    harness.make_owner("announcer").await;
+    verified_user!(harness, app, "reader_a", "reader.a@example.com");
+    verified_user!(harness, app, "reader_b", "reader.b@example.com");
+    let quiet = verified_user!(harness, app, "quiet", "quiet@example.com");
+
+    let (status, body) = send!(
+        app,
+        authorized(
+            post("/api/me/notifications", json!({ "announcements": false })),
+            &quiet
+        )
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let (status, body) = send!(
+        app,
+        authorized(
+            post(
+                "/admin/announcements",
+                json!({ "subject": SUBJECT, "body": "first paragraph\n\nsecond paragraph" })
+            ),
+            &owner
+        )
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["recipients"], 4);
+
+    // the accounts are sent to in the order they registered
+    let batches = [
+        vec!["announcer@example.com", "reader.a@example.com"],
+        vec![
+            "announcer@example.com",
+            "reader.a@example.com",
+            "reader.b@example.com",
+        ],
+    ];
+
+    for sent in batches {
+        announcements::send_emails(harness.state.clone())
+            .await
+            .unwrap();
+        harness.deliver_emails().await;
+
+        assert_eq!(announcement_emails(&harness), sent);
+    }
+
+    let (_, body) = send!(app, authorized(get("/admin/announcements"), &owner));
+    assert_eq!(body[0]["email_done"], false);
+
+    // a run which finds fewer accounts than the batch size finishes the announcement
+    announcements::send_emails(harness.state.clone())
+        .await
+        .unwrap();
+
+    let (_, body) = send!(app, authorized(get("/admin/announcements"), &owner));
+    assert_eq!(body[0]["email_done"], true);
+
+    announcements::send_emails(harness.state.clone())
+        .await
+        .unwrap();
+    harness.deliver_emails().await;
+    assert_eq!(announcement_emails(&harness).len(), 3);
+
+    // no email for quiet, the inbox has it all the same
+    let (status, body) = send!(app, authorized(get("/api/notifications"), &quiet));
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert!(body.to_string().contains(SUBJECT));
+}
@@ -0,0 +1,146 @@
+use actix_web::{http::StatusCode, test};
+use chrono::Duration;
+use serde_json::json;
+
+use super::{authorized, get, post, send, theme, TestApp};
+use crate::app::build_app;
+
+const EMAIL: &str = "flow@example.com";
+const PASSWORD: &str = "Old-password1";
+const NEW_PASSWORD: &str = "New-password2";
+
+#[actix_web::test]
+async fn in_memory() {
+    register_verify_use_cloudthemes_and_reset_password(TestApp::new()).await;
+}
+
+// the same flow against the sqlite repositories and migrations
+#[actix_web::test]
+async fn on_sqlite() {
+    register_verify_use_cloudthemes_and_reset_password(TestApp::sqlite().await).await;
+}
+
+async fn register_verify_use_cloudthemes_and_reset_password(harness: TestApp) {
+    let app = test::init_service(build_app(harness.data())).await;
+
+    let (status, body) = send!(
+        app,
+        post(
+            "/auth/register",
+            json!({ "username": "flow_user", "password": PASSWORD, "email": EMAIL })
+        )
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let token = body["token"].as_str().unwrap().to_string();
+
+    // the api is locked until the email is verified
+    let (status, _) = send!(app, authorized(get("/api/cloudthemes"), &token));
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, body) = send!(
+        app,
+        post("/auth/send_verification_email", json!({ "token": token }))
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    // handlers only queue emails, nothing is sent before the outbox is delivered
+    assert!(harness.mailer.sent().is_empty());
+    harness.deliver_emails().await;
+    let code = harness.code_sent_to(EMAIL);
+
+    let (status, body) = send!(
+        app,
+        post(
+            "/auth/verify_email",
+            json!({ "token": token, "code": code })
+        )
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let token = body["token"].as_str().unwrap().to_string();
+
+    let (status, body) = send!(app, authorized(post("/api/cloudthemes", theme()), &token));
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let (status, body) = send!(app, authorized(get("/api/cloudthemes"), &token));
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["theme"], theme());
+
+    let (status, body) = send!(
+        app,
+        post("/auth/request_reset_password", json!({ "email": EMAIL }))
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    harness.deliver_emails().await;
+    let code = harness.code_sent_to(EMAIL);
+
+    let (status, body) = send!(
+        app,
+        post(
+            "/auth/reset_password",
+            json!({ "email": EMAIL, "code": code, "new_password": NEW_PASSWORD })
+        )
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    // resetting the password signs out every session
+    let (status, _) = send!(app, authorized(get("/api/me"), &token));
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = send!(
+        app,
+        post(
+            "/auth/login",
+            json!({ "username_or_email": EMAIL, "password": PASSWORD })
+        )
+    );
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, body) = send!(
+        app,
+        post(
+            "/auth/login",
+            json!({ "username_or_email": EMAIL, "password": NEW_PASSWORD })
+        )
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let token = body["token"].as_str().unwrap().to_string();
+
+    let (status, body) = send!(app, authorized(get("/api/me"), &token));
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["username"], "flow_user");
+    assert!(body["created_at"].is_string());
+    assert!(body["email_verified_at"].is_string());
+    assert!(body["last_login_at"].is_string());
+}
+
+#[actix_web::test]
+async fn tokens_expire_after_a_year() {
+    let harness = TestApp::new();
+    let app = test::init_service(build_app(harness.data())).await;
+
+    let (status, body) = send!(
+        app,
+        post(
+            "/auth/register",
+            json!({ "username": "expiring_user", "password": PASSWORD, "email": "expiring@example.com" })
+        )
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let token = body["token"].as_str().unwrap().to_string();
+
+    let (status, _) = send!(
+        app,
+        post("/auth/send_verification_email", json!({ "token": token }))
+    );
+    assert_eq!(status, StatusCode::OK);
+
+    harness.clock.advance(Duration::days(366));
+
+    let (status, body) = send!(
+        app,
+        post("/auth/send_verification_email", json!({ "token": token }))
+    );
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error"], "your token is expired");
+}
@@ -0,0 +1,223 @@
+use actix_web::{http::StatusCode, test};
+use serde_json::json;
+
+use super::{authorized, get, post, send, theme, verified_user, TestApp};
+use crate::app::build_app;
+
+#[actix_web::test]
+async fn in_memory() {
+    create_rename_duplicate_activate_and_delete_themes(TestApp::new()).await;
+}
+
+#[actix_web::test]
+async fn on_sqlite() {
+    create_rename_duplicate_activate_and_delete_themes(TestApp::sqlite().await).await;
+}
+
+async fn create_rename_duplicate_activate_and_delete_themes(harness: TestApp) {
+    let app = test::init_service(build_app(harness.data())).await;
+    let token = verified_user!(harness, app, "theme_user", "themes@example.com");
+
+    let (status, _) = send!(app, authorized(get("/api/cloudthemes"), &token));
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // the first theme is active without asking for it
+    let (status, work) = send!(
+        app,
+        authorized(
+            post(
+                "/api/cloudthemes/themes",
+                json!({ "name": "Work", "theme": theme() })
+            ),
+            &token
+        )
+    );
+    assert_eq!(status, StatusCode::OK, "{}", work);
+    assert_eq!(work["active"], true);
+
+    let mut night_theme = theme();
+    night_theme["background_color_primary"] = json!("#050505");
+    let (status, night) = send!(
+        app,
+        authorized(
+            post(
+                "/api/cloudthemes/themes",
+                json!({ "name": "Night", "theme": night_theme })
+            ),
+            &token
+        )
+    );
+    assert_eq!(status, StatusCode::OK, "{}", night);
+    assert_eq!(night["active"], false);
+
+    let (status, _) = send!(
+        app,
+        authorized(
+            post(
+                "/api/cloudthemes/themes",
+                json!({ "name": " Work ", "theme": theme() })
+            ),
+            &token
+        )
+    );
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, body) = send!(app, authorized(get("/api/cloudthemes"), &token));
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["name"], "Work");
+
+    let night_path = format!("/api/cloudthemes/themes/{}", night["id"]);
+
+    let (status, _) = send!(
+        app,
+        authorized(post(&format!("{}/activate", night_path), json!({})), &token)
+    );
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send!(
+        app,
+        authorized(
+            post(
+                &format!("{}/rename", night_path),
+                json!({ "name": "Late night" })
+            ),
+            &token
+        )
+    );
+    assert_eq!(status, StatusCode::OK);
+
+    // the active theme, still what the old endpoint returns
+    let (_, body) = send!(app, authorized(get("/api/cloudthemes"), &token));
+    assert_eq!(body["name"], "Late night");
+    assert_eq!(body["theme"], night_theme);
+
+    let (status, copy) = send!(
+        app,
+        authorized(
+            post(
+                &format!("/api/cloudthemes/themes/{}/duplicate", work["id"]),
+                json!({ "name": "Work copy" })
+            ),
+            &token
+        )
+    );
+    assert_eq!(status, StatusCode::OK, "{}", copy);
+    assert_eq!(copy["active"], false);
+    assert_eq!(copy["theme"], theme());
+
+    let (status, _) = send!(
+        app,
+        authorized(
+            post(
+                &format!("/api/cloudthemes/themes/{}/rename", copy["id"]),
+                json!({ "name": "Work" })
+            ),
+            &token
+        )
+    );
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (_, themes) = send!(app, authorized(get("/api/cloudthemes/themes"), &token));
+    let names: Vec<&str> = themes
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|theme| theme["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["Work", "Late night", "Work copy"]);
+
+    // saving through the old endpoint changes the active theme
+    let (status, _) = send!(app, authorized(post("/api/cloudthemes", theme()), &token));
+    assert_eq!(status, StatusCode::OK);
+    let (_, body) = send!(app, authorized(get(&night_path), &token));
+    assert_eq!(body["theme"], theme());
+
+    let (status, _) = send!(
+        app,
+        authorized(test::TestRequest::delete().uri(&night_path), &token)
+    );
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send!(app, authorized(get(&night_path), &token));
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // another theme takes over
+    let (status, body) = send!(app, authorized(get("/api/cloudthemes"), &token));
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["active"], true);
+
+    // nobody sees the themes of someone else
+    let other = verified_user!(harness, app, "other_theme_user", "other.themes@example.com");
+    let (status, _) = send!(
+        app,
+        authorized(
+            get(&format!("/api/cloudthemes/themes/{}", work["id"])),
+            &other
+        )
+    );
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn theme_values_are_validated_and_normalized() {
+    let harness = TestApp::new();
+    let app = test::init_service(build_app(harness.data())).await;
+    let token = verified_user!(
+        harness,
+        app,
+        "strict_theme_user",
+        "strict.themes@example.com"
+    );
+
+    let mut invalid = theme();
+    invalid["primary_color"] = json!("red;} body { display: none");
+    invalid["secondary_color"] = json!("rgb(300, 0, 0)");
+    invalid["font_size"] = json!("900px");
+    invalid["transparency_value"] = json!(1.5);
+    invalid["transparency_blur"] = json!("calc(100vh)");
+
+    let (status, body) = send!(app, authorized(post("/api/cloudthemes", invalid), &token));
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    let mut fields: Vec<&String> = body["fields"].as_object().unwrap().keys().collect();
+    fields.sort();
+    assert_eq!(
+        fields,
+        [
+            "font_size",
+            "primary_color",
+            "secondary_color",
+            "transparency_blur",
+            "transparency_value"
+        ]
+    );
+
+    // nothing was stored
+    let (status, _) = send!(app, authorized(get("/api/cloudthemes"), &token));
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let mut messy = theme();
+    messy["primary_color_text"] = json!(" #FFF ");
+    messy["primary_color"] = json!("RGBA(255 0 128 / 50%)");
+    messy["secondary_color"] = json!("hsl(480deg,50%,25.0%)");
+    messy["primary_grey"] = json!("Gray");
+    messy["font_size"] = json!("1.50REM");
+    messy["transparency_blur"] = json!("0");
+
+    let (status, body) = send!(
+        app,
+        authorized(
+            post(
+                "/api/cloudthemes/themes",
+                json!({ "name": "Messy", "theme": messy })
+            ),
+            &token
+        )
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["theme"]["primary_color_text"], "#ffffff");
+    assert_eq!(body["theme"]["primary_color"], "rgba(255, 0, 128, 0.5)");
+    assert_eq!(body["theme"]["secondary_color"], "hsl(120, 50%, 25%)");
+    assert_eq!(body["theme"]["primary_grey"], "gray");
+    assert_eq!(body["theme"]["font_size"], "1.5rem");
+    assert_eq!(body["theme"]["transparency_blur"], "0px");
+}
@@ -0,0 +1,104 @@
+use actix_web::{http::StatusCode, test};
+use serde_json::json;
+
+use super::{override_secret, post, send, verified_user, TestApp};
+use crate::app::build_app;
+
+const EXISTING: &str = "hardened@example.com";
+const UNKNOWN: &str = "nobody@example.com";
+
+#[actix_web::test]
+async fn unknown_and_existing_accounts_get_the_same_answers() {
+    let harness = TestApp::new();
+    let app = test::init_service(build_app(harness.data())).await;
+
+    verified_user!(harness, app, "hardened_user", EXISTING);
+
+    let _hardened = override_secret("HARDENED_AUTH", "true");
+
+    // the same request once for the existing account and once for an email nobody registered
+    let pairs = [
+        (
+            post(
+                "/auth/register",
+                json!({ "username": "hardened_a", "password": "Hardened-password1", "email": EXISTING }),
+            ),
+            post(
+                "/auth/register",
+                json!({ "username": "hardened_b", "password": "Hardened-password1", "email": "newcomer@example.com" }),
+            ),
+        ),
+        (
+            post(
+                "/auth/login",
+                json!({ "username_or_email": EXISTING, "password": "Wrong-password1" }),
+            ),
+            post(
+                "/auth/login",
+                json!({ "username_or_email": UNKNOWN, "password": "Wrong-password1" }),
+            ),
+        ),
+        (
+            post("/auth/request_reset_password", json!({ "email": EXISTING })),
+            post("/auth/request_reset_password", json!({ "email": UNKNOWN })),
+        ),
+        (
+            post(
+                "/auth/reset_password",
+                json!({ "email": EXISTING, "code": 1, "new_password": "Hardened-password2" }),
+            ),
+            post(
+                "/auth/reset_password",
+                json!({ "email": UNKNOWN, "code": 1, "new_password": "Hardened-password2" }),
+            ),
+        ),
+        (
+            post(
+                "/auth/reset_password",
+                json!({ "email": EXISTING, "new_password": "Hardened-password2" }),
+            ),
+            post(
+                "/auth/reset_password",
+                json!({ "email": UNKNOWN, "new_password": "Hardened-password2" }),
+            ),
+        ),
+        (
+            post("/auth/magic_link", json!({ "email": EXISTING })),
+            post("/auth/magic_link", json!({ "email": UNKNOWN })),
+        ),
+    ];
+
+    for (existing, unknown) in pairs {
+        let existing = send!(app, existing);
+        let unknown = send!(app, unknown);
+        assert_eq!(existing, unknown);
+    }
+
+    // the owner of the email hears about the second registration, the caller can't tell
+    harness.deliver_emails().await;
+    assert!(harness
+        .mailer
+        .sent()
+        .iter()
+        .any(|email| email.to == EXISTING
+            && email.subject == "Someone tried to register with your email on acid4sigmas"));
+
+    let _mode = override_secret("REGISTRATION_MODE", "invite_only");
+
+    let existing = send!(
+        app,
+        post(
+            "/auth/register",
+            json!({ "username": "hardened_c", "password": "Hardened-password1", "email": EXISTING, "invite_code": "made_up" })
+        )
+    );
+    let unknown = send!(
+        app,
+        post(
+            "/auth/register",
+            json!({ "username": "hardened_d", "password": "Hardened-password1", "email": "invitee@example.com", "invite_code": "made_up" })
+        )
+    );
+    assert_eq!(existing.0, StatusCode::FORBIDDEN);
+    assert_eq!(existing, unknown);
+}
@@ -0,0 +1,116 @@
+use actix_web::{http::StatusCode, test};
+use serde_json::{json, Value};
+
+use super::{authorized, get, override_secret, post, send, verified_user, TestApp};
+use crate::app::build_app;
+
+fn registration(username: &str, invite_code: Option<&str>) -> Value {
+    json!({
+        "username": username,
+        "password": "Invited-password1",
+        "email": format!("{}@example.com", username.replace('_', ".")),
+        "invite_code": invite_code,
+    })
+}
+
+#[actix_web::test]
+async fn an_invite_is_only_used_up_by_a_registration_which_went_through() {
+    let harness = TestApp::sqlite().await;
+    let app = test::init_service(build_app(harness.data())).await;
+
+    let owner = verified_user!(harness, app, "invite_owner", "invite.owner@example.com");
+    harness.make_owner("invite_owner").await;
+
+    let _mode = override_secret("REGISTRATION_MODE", "invite_only");
+
+    let (status, body) = send!(app, post("/auth/register", registration("uninvited", None)));
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error"], "an invite code is required to register.");
+
+    let (status, body) = send!(app, authorized(post("/api/invites", json!({})), &owner));
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let code = body["code"].as_str().unwrap().to_string();
+
+    // the account can't be stored, the use of the invite is given back
+    harness
+        .execute(
+            "CREATE TRIGGER refuse_accounts BEFORE INSERT ON accounts
+            BEGIN SELECT RAISE(ABORT, 'the disk is full'); END",
+        )
+        .await;
+    let (status, _) = send!(
+        app,
+        post("/auth/register", registration("unlucky", Some(&code)))
+    );
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    harness.execute("DROP TRIGGER refuse_accounts").await;
+
+    let (status, body) = send!(
+        app,
+        post("/auth/register", registration("invited", Some(&code)))
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let (status, body) = send!(
+        app,
+        post("/auth/register", registration("latecomer", Some(&code)))
+    );
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(
+        body["error"],
+        "this invite code is invalid, used up or expired."
+    );
+}
+
+#[actix_web::test]
+async fn the_quota_is_given_back_when_the_invite_cant_be_stored() {
+    let harness = TestApp::sqlite().await;
+    let app = test::init_service(build_app(harness.data())).await;
+
+    let owner = verified_user!(harness, app, "quota_owner", "quota.owner@example.com");
+    harness.make_owner("quota_owner").await;
+    let user = verified_user!(harness, app, "quota_user", "quota.user@example.com");
+
+    let (_, body) = send!(app, authorized(get("/api/me"), &user));
+    let uid = body["uid"].as_i64().unwrap();
+
+    let (status, body) = send!(
+        app,
+        authorized(
+            post("/api/invites/quota", json!({ "uid": uid, "remaining": 1 })),
+            &owner
+        )
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    // only owners hand out invites with more than one use
+    let (status, _) = send!(
+        app,
+        authorized(post("/api/invites", json!({ "max_uses": 2 })), &user)
+    );
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    harness
+        .execute(
+            "CREATE TRIGGER refuse_invites BEFORE INSERT ON invites
+            BEGIN SELECT RAISE(ABORT, 'the disk is full'); END",
+        )
+        .await;
+    let (status, _) = send!(app, authorized(post("/api/invites", json!({})), &user));
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    harness.execute("DROP TRIGGER refuse_invites").await;
+
+    let (_, body) = send!(app, authorized(get("/api/invites/quota"), &user));
+    assert_eq!(body["remaining"], 1);
+
+    let (status, body) = send!(app, authorized(post("/api/invites", json!({})), &user));
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["max_uses"], 1);
+
+    let (_, body) = send!(app, authorized(get("/api/invites/quota"), &user));
+    assert_eq!(body["remaining"], 0);
+
+    let (status, body) = send!(app, authorized(post("/api/invites", json!({})), &user));
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error"], "you have no invites left");
+}
@@ -0,0 +1,79 @@
+use actix_web::{http::StatusCode, test};
+use chrono::Duration;
+use regex::Regex;
+use serde_json::json;
+
+use super::{authorized, get, override_secret, post, send, TestApp};
+use crate::app::build_app;
+
+const EMAIL: &str = "magic@example.com";
+
+// the token of the link in the last email sent to `EMAIL`
+fn link_token(harness: &TestApp) -> String {
+    let email = harness.mailer.last_to(EMAIL).unwrap();
+    let token = Regex::new(r"magic_link\?token=([\w.-]+)").unwrap();
+
+    token.captures(&email.html).unwrap()[1].to_string()
+}
+
+#[actix_web::test]
+async fn a_magic_link_signs_in_once_and_verifies_the_email() {
+    let harness = TestApp::new();
+    let app = test::init_service(build_app(harness.data())).await;
+
+    let (status, body) = send!(
+        app,
+        post(
+            "/auth/register",
+            json!({ "username": "magic_user", "password": "Magic-password1", "email": EMAIL })
+        )
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let (status, body) = send!(app, post("/auth/magic_link", json!({ "email": EMAIL })));
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    harness.deliver_emails().await;
+    let link = link_token(&harness);
+
+    let (status, body) = send!(
+        app,
+        post("/auth/magic_link/login", json!({ "token": link }))
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let token = body["token"].as_str().unwrap().to_string();
+
+    // only the owner of the inbox could open the link
+    let (status, body) = send!(app, authorized(get("/api/me"), &token));
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["email_verified"], true);
+
+    let (status, body) = send!(
+        app,
+        post("/auth/magic_link/login", json!({ "token": link }))
+    );
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error"], "this link is invalid or expired.");
+
+    // a link is valid for 10 minutes
+    let (status, _) = send!(app, post("/auth/magic_link", json!({ "email": EMAIL })));
+    assert_eq!(status, StatusCode::OK);
+
+    harness.deliver_emails().await;
+    let link = link_token(&harness);
+
+    harness.clock.advance(Duration::minutes(11));
+
+    let (status, body) = send!(
+        app,
+        post("/auth/magic_link/login", json!({ "token": link }))
+    );
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error"], "this link is invalid or expired.");
+
+    let _disabled = override_secret("MAGIC_LINK_LOGIN", "false");
+
+    let (status, body) = send!(app, post("/auth/magic_link", json!({ "email": EMAIL })));
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error"], "magic link login is disabled.");
+}
@@ -0,0 +1,269 @@
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use actix_web::{http::header::AUTHORIZATION, test::TestRequest, web};
+use chrono::{DateTime, Duration, Utc};
+use regex::Regex;
+use serde_json::{json, Value};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+
+use crate::{
+    db::{memory::MemoryStore, migrations, Backend},
+    mailer::{capture::CaptureMailer, queue::deliver_due},
+    secrets::OVERRIDES,
+    state::AppState,
+    util::clock::Clock,
+};
+
+mod accounts;
+mod announcements;
+mod auth_flow;
+mod cloudthemes;
+mod hardened;
+mod invites;
+mod magic_link;
+mod outage;
+mod outbox;
+mod pow;
+mod scheduler;
+mod schema_migrations;
+mod signup_cleanup;
+mod snowflake;
+mod theme_gallery;
+mod unsubscribe;
+
+// stands still until a test advances it
+pub struct ManualClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl ManualClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(now),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
+
+// the app state of one test, every test gets its own store, mailer and clock
+pub struct TestApp {
+    pub state: AppState,
+    pub mailer: Arc<CaptureMailer>,
+    pub clock: Arc<ManualClock>,
+}
+
+impl TestApp {
+    pub fn new() -> Self {
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+
+        Self::with_backend(Backend::Memory(MemoryStore::new(clock.clone())), clock)
+    }
+
+    // a fresh sqlite database which only lives in memory, migrated like a real one
+    pub async fn sqlite() -> Self {
+        let options = SqliteConnectOptions::from_str("sqlite::memory:")
+            .unwrap()
+            .foreign_keys(true);
+        // every connection to sqlite::memory: opens its own empty database
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .unwrap();
+
+        let backend = Backend::Sqlite(pool);
+        migrations::run(&backend).await.unwrap();
+
+        Self::with_backend(backend, Arc::new(ManualClock::new(Utc::now())))
+    }
+
+    fn with_backend(backend: Backend, clock: Arc<ManualClock>) -> Self {
+        let mailer = Arc::new(CaptureMailer::new());
+
+        let state = AppState::new(backend, mailer.clone(), clock.clone()).unwrap();
+
+        Self {
+            state,
+            mailer,
+            clock,
+        }
+    }
+
+    pub fn data(&self) -> web::Data<AppState> {
+        web::Data::new(self.state.clone())
+    }
+
+    // what the deliver_email_outbox job does every few seconds
+    pub async fn deliver_emails(&self) {
+        deliver_due(self.state.clone()).await.unwrap();
+    }
+
+    // owners are made by hand or through an invite, a test does it by hand
+    pub async fn make_owner(&self, username: &str) {
+        match &self.state.backend {
+            Backend::Memory(store) => {
+                let mut tables = store.tables();
+                let account = tables
+                    .accounts
+                    .values_mut()
+                    .find(|account| account.username == username)
+                    .unwrap_or_else(|| panic!("there is no account called {}", username));
+                account.owner = true;
+            }
+            Backend::Sqlite(pool) => {
+                sqlx::query("UPDATE accounts SET owner = TRUE WHERE username = $1")
+                    .bind(username)
+                    .execute(pool)
+                    .await
+                    .unwrap();
+            }
+            Backend::Postgres(pool) => {
+                sqlx::query("UPDATE accounts SET owner = TRUE WHERE username = $1")
+                    .bind(username)
+                    .execute(pool)
+                    .await
+                    .unwrap();
+            }
+        }
+    }
+
+    // runs sql on the database of a sqlite test, e.g. a trigger which makes a write fail
+    pub async fn execute(&self, sql: &str) {
+        match &self.state.backend {
+            Backend::Sqlite(pool) => {
+                sqlx::raw_sql(sql).execute(pool).await.unwrap();
+            }
+            _ => panic!("only the sqlite tests can run sql"),
+        }
+    }
+
+    // the code in the last email sent to `to`
+    pub fn code_sent_to(&self, to: &str) -> u64 {
+        let email = self
+            .mailer
+            .last_to(to)
+            .unwrap_or_else(|| panic!("no email was sent to {}", to));
+
+        let code = Regex::new(r"<strong>(\d+)</strong>").unwrap();
+
+        code.captures(&email.html)
+            .unwrap_or_else(|| panic!("the email '{}' has no code", email.subject))[1]
+            .parse()
+            .unwrap()
+    }
+}
+
+// changes a value of Secrets.toml for one test until it is dropped. the actix test runtime
+// handles the requests on the thread of the test, the other tests keep the file's value
+pub struct Override(&'static str);
+
+pub fn override_secret(key: &'static str, value: &str) -> Override {
+    OVERRIDES.with(|overrides| {
+        overrides
+            .borrow_mut()
+            .insert(key.to_string(), value.to_string())
+    });
+
+    Override(key)
+}
+
+impl Drop for Override {
+    fn drop(&mut self) {
+        OVERRIDES.with(|overrides| overrides.borrow_mut().remove(self.0));
+    }
+}
+
+// a theme which passes every check
+pub fn theme() -> Value {
+    json!({
+        "primary_color_text": "#ffffff",
+        "primary_color": "#ff00ff",
+        "secondary_color": "#00ffff",
+        "background_color_primary": "#000000",
+        "background_color_secondary": "#111111",
+        "background_color_tertiary": "#222222",
+        "primary_grey": "#888888",
+        "secondary_grey": "#aaaaaa",
+        "font_size": "16px",
+        "transparency": true,
+        "transparency_value": 0.5,
+        "transparency_blur": "4px",
+    })
+}
+
+pub fn post(path: &str, body: Value) -> TestRequest {
+    TestRequest::post().uri(path).set_json(body)
+}
+
+pub fn get(path: &str) -> TestRequest {
+    TestRequest::get().uri(path)
+}
+
+pub fn authorized(request: TestRequest, token: &str) -> TestRequest {
+    request.insert_header((AUTHORIZATION, token))
+}
+
+// calls the app and returns the status with the json body, `Value::Null` for empty bodies
+macro_rules! send {
+    ($app:expr, $request:expr) => {{
+        let response = actix_web::test::call_service(&$app, $request.to_request()).await;
+        let status = response.status();
+        let body = actix_web::test::read_body(response).await;
+
+        (
+            status,
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap_or(serde_json::Value::Null),
+        )
+    }};
+}
+
+pub(crate) use send;
+
+// registers and verifies an account, evaluates to the token of the verified account
+macro_rules! verified_user {
+    ($harness:expr, $app:expr, $username:expr, $email:expr) => {{
+        let (status, body) = $crate::tests::send!(
+            $app,
+            $crate::tests::post(
+                "/auth/register",
+                serde_json::json!({ "username": $username, "password": "Verified-password1", "email": $email })
+            )
+        );
+        assert_eq!(status, actix_web::http::StatusCode::OK, "{}", body);
+        let token = body["token"].as_str().unwrap().to_string();
+
+        let (status, body) = $crate::tests::send!(
+            $app,
+            $crate::tests::post("/auth/send_verification_email", serde_json::json!({ "token": token }))
+        );
+        assert_eq!(status, actix_web::http::StatusCode::OK, "{}", body);
+
+        $harness.deliver_emails().await;
+        let code = $harness.code_sent_to($email);
+
+        let (status, body) = $crate::tests::send!(
+            $app,
+            $crate::tests::post(
+                "/auth/verify_email",
+                serde_json::json!({ "token": token, "code": code })
+            )
+        );
+        assert_eq!(status, actix_web::http::StatusCode::OK, "{}", body);
+
+        body["token"].as_str().unwrap().to_string()
+    }};
+}
+
+pub(crate) use verified_user;
@@ -0,0 +1,104 @@
+use actix_web::{http::header::RETRY_AFTER, http::StatusCode, test};
+use chrono::Duration;
+use serde_json::json;
+
+use super::{authorized, get, post, send, TestApp};
+use crate::{app::build_app, db::Backend};
+
+#[actix_web::test]
+async fn requests_fail_fast_while_the_database_is_down() {
+    let harness = TestApp::sqlite().await;
+    let app = test::init_service(build_app(harness.data())).await;
+
+    let (status, body) = send!(
+        app,
+        post(
+            "/auth/register",
+            json!({ "username": "outage_user", "password": "Outage-password1", "email": "outage@example.com" })
+        )
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let token = body["token"].as_str().unwrap().to_string();
+
+    let (_, body) = send!(app, get("/health"));
+    assert_eq!(body["database"]["state"], "closed");
+
+    if let Backend::Sqlite(pool) = &harness.state.backend {
+        pool.close().await;
+    }
+
+    // every request fails after its retries until the breaker opens
+    for _ in 0..4 {
+        let (status, _) = send!(app, authorized(get("/api/me"), &token));
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    let (status, _) = send!(app, authorized(get("/api/me"), &token));
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+    let response = test::call_service(
+        &app,
+        post(
+            "/auth/login",
+            json!({ "username_or_email": "outage_user", "password": "Outage-password1" }),
+        )
+        .to_request(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers().get(RETRY_AFTER).unwrap(), "30");
+
+    // the public routes which need the database too
+    for path in ["/pub_api/themes", "/pub_api/unsubscribe?token=abc"] {
+        let response = test::call_service(&app, get(path).to_request()).await;
+        assert_eq!(
+            response.status(),
+            StatusCode::SERVICE_UNAVAILABLE,
+            "{}",
+            path
+        );
+        assert_eq!(response.headers().get(RETRY_AFTER).unwrap(), "30");
+    }
+
+    let (status, body) = send!(app, get("/health"));
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["database"]["state"], "open");
+    assert_eq!(body["database"]["consecutive_failures"], 5);
+
+    let (status, _) = send!(app, get("/health/ready"));
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+    harness.clock.advance(Duration::seconds(31));
+
+    let (_, body) = send!(app, get("/health"));
+    assert_eq!(body["database"]["state"], "half_open");
+
+    // the database is still gone, the first call after the cooldown opens the breaker again
+    let (status, _) = send!(app, get("/health/ready"));
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+    let (_, body) = send!(app, get("/health"));
+    assert_eq!(body["database"]["state"], "open");
+}
+
+#[actix_web::test]
+async fn the_breaker_closes_once_the_database_answers_again() {
+    let harness = TestApp::new();
+    let app = test::init_service(build_app(harness.data())).await;
+
+    for _ in 0..5 {
+        harness
+            .state
+            .breaker
+            .record::<()>(&Err(sqlx::Error::PoolTimedOut.into()));
+    }
+
+    let (status, _) = send!(app, get("/health/ready"));
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+    harness.clock.advance(Duration::seconds(31));
+
+    let (status, body) = send!(app, get("/health/ready"));
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["database"]["state"], "closed");
+    assert_eq!(body["database"]["consecutive_failures"], 0);
+}
@@ -0,0 +1,93 @@
+use actix_web::{http::StatusCode, test};
+use chrono::Duration;
+use serde_json::json;
+
+use super::{authorized, get, post, send, verified_user, TestApp};
+use crate::{
+    app::build_app,
+    mailer::{queue, Email},
+};
+
+#[actix_web::test]
+async fn an_undeliverable_email_is_retried_and_then_given_up() {
+    let harness = TestApp::new();
+    let app = test::init_service(build_app(harness.data())).await;
+
+    let owner = verified_user!(harness, app, "outbox_owner", "outbox.owner@example.com");
+    harness.make_owner("outbox_owner").await;
+
+    // the capture mailer fails on addresses smtp would refuse as well
+    queue::enqueue(
+        &harness.state,
+        Email {
+            to: String::from("not an address"),
+            subject: String::from("Undeliverable"),
+            html: String::from("<p>the secret code is <strong>123456</strong></p>"),
+            text: String::from("the secret code is 123456"),
+            unsubscribe_url: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    harness.deliver_emails().await;
+
+    let (status, body) = send!(app, authorized(get("/admin/emails?status=failed"), &owner));
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body.as_array().unwrap().len(), 1);
+    assert_eq!(body[0]["attempts"], 1);
+    assert!(body[0]["last_error"].is_string());
+    // owners see who an email went to, not what it said
+    assert!(body[0].get("html").is_none());
+    assert!(body[0].get("text").is_none());
+    let id = body[0]["id"].as_i64().unwrap();
+
+    // the next attempt waits for the retry delay
+    harness.deliver_emails().await;
+    let (_, body) = send!(app, authorized(get("/admin/emails?status=failed"), &owner));
+    assert_eq!(body[0]["attempts"], 1);
+
+    for _ in 2..=8 {
+        harness.clock.advance(Duration::hours(6));
+        harness.deliver_emails().await;
+    }
+
+    let (_, body) = send!(app, authorized(get("/admin/emails?status=failed"), &owner));
+    assert_eq!(body.as_array().unwrap().len(), 0);
+
+    let (status, body) = send!(app, authorized(get("/admin/emails"), &owner));
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body[0]["id"], id);
+    assert_eq!(body[0]["status"], "dead");
+    assert_eq!(body[0]["attempts"], 8);
+
+    // given up for good until an owner asks for another attempt
+    harness.clock.advance(Duration::hours(6));
+    harness.deliver_emails().await;
+    let (_, body) = send!(app, authorized(get("/admin/emails"), &owner));
+    assert_eq!(body[0]["attempts"], 8);
+
+    let (status, body) = send!(
+        app,
+        authorized(
+            post(&format!("/admin/emails/{}/retry", id), json!({})),
+            &owner
+        )
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    harness.deliver_emails().await;
+    let (_, body) = send!(app, authorized(get("/admin/emails?status=failed"), &owner));
+    assert_eq!(body[0]["id"], id);
+    assert_eq!(body[0]["attempts"], 1);
+
+    let (status, _) = send!(app, authorized(get("/admin/emails?status=sent"), &owner));
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // none of the attempts reached the mailer
+    assert!(harness
+        .mailer
+        .sent()
+        .iter()
+        .all(|email| email.to != "not an address"));
+}
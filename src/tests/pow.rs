@@ -0,0 +1,97 @@
+use actix_web::{http::StatusCode, test};
+use chrono::Duration;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use super::{get, override_secret, post, send, TestApp};
+use crate::app::build_app;
+
+// the first solution which does (or on purpose doesn't) start with 8 zero bits
+fn solve(challenge: &str, correct: bool) -> String {
+    (0u64..)
+        .map(|solution| solution.to_string())
+        .find(|solution| {
+            let hash = Sha256::digest(format!("{}:{}", challenge, solution).as_bytes());
+            (hash[0] == 0) == correct
+        })
+        .unwrap()
+}
+
+fn registration(username: &str, pow: Value) -> Value {
+    json!({
+        "username": username,
+        "password": "Solved-password1",
+        "email": format!("{}@example.com", username.replace('_', ".")),
+        "pow": pow,
+    })
+}
+
+#[actix_web::test]
+async fn registering_takes_a_solved_challenge() {
+    let harness = TestApp::new();
+    let app = test::init_service(build_app(harness.data())).await;
+
+    let _difficulty = override_secret("POW_DIFFICULTY", "8");
+
+    let (status, body) = send!(
+        app,
+        post("/auth/register", registration("pow_user", Value::Null))
+    );
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(
+        body["error"],
+        "a solved challenge from /auth/challenge is required for this request"
+    );
+
+    let (status, body) = send!(app, get("/auth/challenge"));
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["difficulty"], 8);
+    let challenge = body["challenge"].as_str().unwrap().to_string();
+
+    let (status, body) = send!(
+        app,
+        post(
+            "/auth/register",
+            registration(
+                "pow_user",
+                json!({ "challenge": challenge, "solution": solve(&challenge, false) })
+            )
+        )
+    );
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error"], "the challenge solution is wrong");
+
+    let solved = json!({ "challenge": challenge, "solution": solve(&challenge, true) });
+
+    let (status, body) = send!(
+        app,
+        post("/auth/register", registration("pow_user", solved.clone()))
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let (status, body) = send!(
+        app,
+        post("/auth/register", registration("pow_again", solved))
+    );
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error"], "this challenge was already used");
+
+    // a challenge is valid for 5 minutes
+    let (_, body) = send!(app, get("/auth/challenge"));
+    let challenge = body["challenge"].as_str().unwrap().to_string();
+
+    harness.clock.advance(Duration::minutes(6));
+
+    let (status, body) = send!(
+        app,
+        post(
+            "/auth/register",
+            registration(
+                "pow_late",
+                json!({ "challenge": challenge, "solution": solve(&challenge, true) })
+            )
+        )
+    );
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error"], "the challenge is invalid or expired");
+}
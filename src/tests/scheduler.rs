@@ -0,0 +1,70 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::scheduler::runner::{Job, Scheduler, JOB_METRICS};
+
+// these run on the real clock, the numbers leave a lot of room for a slow machine
+
+#[actix_web::test]
+async fn a_run_which_takes_longer_than_the_interval_skips_ticks() {
+    let running = Arc::new(AtomicUsize::new(0));
+    let most_running = Arc::new(AtomicUsize::new(0));
+
+    let job = {
+        let running = running.clone();
+        let most_running = most_running.clone();
+
+        Job::every("overlap_test", Duration::from_millis(10), move || {
+            let running = running.clone();
+            let most_running = most_running.clone();
+
+            async move {
+                let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                most_running.fetch_max(now_running, Ordering::SeqCst);
+
+                tokio::time::sleep(Duration::from_millis(50)).await;
+
+                running.fetch_sub(1, Ordering::SeqCst);
+                Ok(())
+            }
+        })
+    };
+
+    Scheduler::new().add(job).start();
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    assert_eq!(most_running.load(Ordering::SeqCst), 1);
+
+    let metrics = JOB_METRICS.lock().unwrap()["overlap_test"].clone();
+    assert!(metrics.runs >= 1, "{:?}", metrics);
+    assert!(metrics.skipped >= 1, "{:?}", metrics);
+}
+
+async fn broken_job() -> anyhow::Result<()> {
+    panic!("the job broke");
+}
+
+#[actix_web::test]
+async fn a_panicking_job_runs_again_on_the_next_tick() {
+    Scheduler::new()
+        .add(Job::every(
+            "panicking_test",
+            Duration::from_millis(10),
+            broken_job,
+        ))
+        .start();
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let metrics = JOB_METRICS.lock().unwrap()["panicking_test"].clone();
+    assert!(metrics.failures >= 2, "{:?}", metrics);
+    assert!(metrics
+        .last_error
+        .is_some_and(|error| error.contains("job panicked")));
+}
@@ -0,0 +1,49 @@
+use crate::db::migrations::{self, MIGRATIONS};
+
+use super::TestApp;
+
+#[actix_web::test]
+async fn a_migrated_database_has_nothing_pending() {
+    let harness = TestApp::sqlite().await;
+
+    assert!(migrations::run(&harness.state.backend)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let status = migrations::status(&harness.state.backend).await.unwrap();
+    assert_eq!(status.len(), MIGRATIONS.len());
+    assert!(status
+        .iter()
+        .all(|migration| migration.applied_at.is_some()));
+}
+
+#[actix_web::test]
+async fn an_applied_migration_which_was_changed_stops_the_start() {
+    let harness = TestApp::sqlite().await;
+
+    harness
+        .execute("UPDATE schema_migrations SET checksum = 'edited' WHERE version = 2")
+        .await;
+
+    let error = migrations::run(&harness.state.backend).await.unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "migration 2 (0002_accounts) was changed after it was applied"
+    );
+}
+
+#[actix_web::test]
+async fn a_database_migrated_by_a_newer_build_stops_the_start() {
+    let harness = TestApp::sqlite().await;
+
+    harness
+        .execute(
+            "INSERT INTO schema_migrations (version, name, checksum, applied_at, execution_ms)
+            VALUES (999, '0999_from_the_future', 'unknown', '2030-01-01T00:00:00Z', 0)",
+        )
+        .await;
+
+    let error = migrations::run(&harness.state.backend).await.unwrap_err();
+    assert!(error.to_string().contains("migration 999"), "{}", error);
+}
@@ -0,0 +1,148 @@
+use actix_web::{http::StatusCode, test};
+use chrono::{Duration, NaiveTime};
+use serde_json::json;
+
+use super::{post, send, verified_user, TestApp};
+use crate::{
+    app::build_app,
+    db::api::signup_cleanup::SignupCleanupDb,
+    models::api::signup_cleanup::{ACTION_DELETE, ACTION_REMINDER},
+    scheduler::signup_cleanup,
+    util::clock::Clock,
+};
+
+const EMAIL: &str = "forgetful@example.com";
+
+fn registration() -> serde_json::Value {
+    json!({ "username": "forgetful", "password": "Forgetful-password1", "email": EMAIL })
+}
+
+fn emails_to(harness: &TestApp, to: &str) -> usize {
+    harness
+        .mailer
+        .sent()
+        .iter()
+        .filter(|email| email.to == to)
+        .count()
+}
+
+#[actix_web::test]
+async fn unverified_signups_are_reminded_and_deleted_in_the_end() {
+    let harness = TestApp::new();
+    let app = test::init_service(build_app(harness.data())).await;
+
+    verified_user!(harness, app, "verified", "verified@example.com");
+
+    let (status, body) = send!(app, post("/auth/register", registration()));
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    // reminders go out 1 and 3 days after the registration, once each
+    for (wait, reminders) in [
+        (Duration::days(1), 1),
+        (Duration::hours(1), 1),
+        (Duration::days(2), 2),
+        (Duration::hours(1), 2),
+    ] {
+        harness.clock.advance(wait);
+        signup_cleanup::run(harness.state.clone()).await.unwrap();
+        harness.deliver_emails().await;
+
+        assert_eq!(emails_to(&harness, EMAIL), reminders);
+    }
+
+    harness.clock.advance(Duration::days(30));
+    signup_cleanup::run(harness.state.clone()).await.unwrap();
+
+    let log = harness
+        .state
+        .signup_cleanup()
+        .read_recent(10)
+        .await
+        .unwrap();
+    let actions: Vec<(&str, Option<i32>)> = log
+        .iter()
+        .rev()
+        .map(|entry| (entry.action.as_str(), entry.reminder_day))
+        .collect();
+    assert_eq!(
+        actions,
+        [
+            (ACTION_REMINDER, Some(1)),
+            (ACTION_REMINDER, Some(3)),
+            (ACTION_DELETE, None)
+        ]
+    );
+    assert!(log
+        .iter()
+        .all(|entry| entry.username == "forgetful" && !entry.dry_run));
+
+    // the username and email are free again, the verified account is still there
+    let (status, body) = send!(app, post("/auth/register", registration()));
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let (status, body) = send!(
+        app,
+        post(
+            "/auth/login",
+            json!({ "username_or_email": "verified", "password": "Verified-password1" })
+        )
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+}
+
+#[actix_web::test]
+async fn a_dry_run_logs_each_account_once_a_day() {
+    let harness = TestApp::new();
+    let app = test::init_service(build_app(harness.data())).await;
+
+    let (status, body) = send!(app, post("/auth/register", registration()));
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    // to noon a month later, the runs below stay on one day
+    let now = harness.clock.now();
+    let noon = now
+        .date_naive()
+        .and_time(NaiveTime::from_hms_opt(12, 0, 0).unwrap())
+        .and_utc();
+    harness.clock.advance(noon - now + Duration::days(31));
+
+    // the job runs every hour
+    for _ in 0..3 {
+        let report = signup_cleanup::plan(&harness.state, true).await.unwrap();
+        assert_eq!(report.deletions.len(), 1);
+
+        signup_cleanup::apply(&harness.state, &report)
+            .await
+            .unwrap();
+        harness.clock.advance(Duration::hours(1));
+    }
+
+    let log = harness
+        .state
+        .signup_cleanup()
+        .read_recent(10)
+        .await
+        .unwrap();
+    assert_eq!(log.len(), 1);
+    assert!(log[0].dry_run);
+
+    harness.clock.advance(Duration::days(1));
+
+    let report = signup_cleanup::plan(&harness.state, true).await.unwrap();
+    signup_cleanup::apply(&harness.state, &report)
+        .await
+        .unwrap();
+
+    let log = harness
+        .state
+        .signup_cleanup()
+        .read_recent(10)
+        .await
+        .unwrap();
+    assert_eq!(log.len(), 2);
+
+    // nothing was deleted or sent
+    let (status, _) = send!(app, post("/auth/register", registration()));
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(emails_to(&harness, EMAIL), 0);
+}
@@ -0,0 +1,57 @@
+use std::{collections::HashSet, sync::Arc, thread};
+
+use chrono::{Duration, Utc};
+
+use crate::util::snowflake::{uid_timestamp, Snowflake};
+
+const WORKER_ID_MASK: i64 = (1 << 10) - 1;
+
+#[test]
+fn uids_increase_and_carry_the_worker_id() {
+    let generator = Snowflake::new(7);
+
+    let uids: Vec<i64> = (0..10_000).map(|_| generator.generate()).collect();
+
+    // more than 4096 in one millisecond borrow the next one instead of repeating
+    assert!(uids.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(uids.iter().all(|uid| *uid > 0));
+    assert!(uids.iter().all(|uid| (uid >> 12) & WORKER_ID_MASK == 7));
+
+    // two workers in the same millisecond still get different uids
+    let other = Snowflake::new(8).generate();
+    assert_eq!((other >> 12) & WORKER_ID_MASK, 8);
+    assert!(!uids.contains(&other));
+}
+
+#[test]
+fn uids_are_unique_across_threads() {
+    let generator = Arc::new(Snowflake::new(1));
+
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let generator = generator.clone();
+            thread::spawn(move || (0..5_000).map(|_| generator.generate()).collect::<Vec<_>>())
+        })
+        .collect();
+
+    let mut seen = HashSet::new();
+    for handle in handles {
+        let uids = handle.join().unwrap();
+        assert!(uids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(uids.into_iter().all(|uid| seen.insert(uid)));
+    }
+    assert_eq!(seen.len(), 40_000);
+}
+
+#[test]
+fn a_uid_decodes_to_the_time_it_was_generated_at() {
+    let before = Utc::now();
+    let uid = Snowflake::new(0).generate();
+    let after = Utc::now();
+
+    let generated_at = uid_timestamp(uid);
+
+    // the uid only keeps milliseconds
+    assert!(generated_at >= before - Duration::milliseconds(1));
+    assert!(generated_at <= after);
+}
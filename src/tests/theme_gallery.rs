@@ -0,0 +1,232 @@
+use actix_web::{http::StatusCode, test};
+use serde_json::{json, Value};
+
+use super::{authorized, get, post, send, theme, verified_user, TestApp};
+use crate::app::build_app;
+
+#[actix_web::test]
+async fn in_memory() {
+    publish_browse_install_and_moderate_themes(TestApp::new()).await;
+}
+
+#[actix_web::test]
+async fn on_sqlite() {
+    publish_browse_install_and_moderate_themes(TestApp::sqlite().await).await;
+}
+
+fn titles(entries: &Value) -> Vec<&str> {
+    entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["title"].as_str().unwrap())
+        .collect()
+}
+
+async fn publish_browse_install_and_moderate_themes(harness: TestApp) {
+    let app = test::init_service(build_app(harness.data())).await;
+    let author = verified_user!(harness, app, "gallery_author", "gallery.author@example.com");
+    let fan = verified_user!(harness, app, "gallery_fan", "gallery.fan@example.com");
+    let owner = verified_user!(harness, app, "gallery_owner", "gallery.owner@example.com");
+    harness.make_owner("gallery_owner").await;
+
+    // publishes a new theme of the author, evaluates to the gallery entry
+    macro_rules! publish {
+        ($name:expr, $listed:expr) => {{
+            let (status, cloudtheme) = send!(
+                app,
+                authorized(
+                    post(
+                        "/api/cloudthemes/themes",
+                        json!({ "name": $name, "theme": theme() })
+                    ),
+                    &author
+                )
+            );
+            assert_eq!(status, StatusCode::OK, "{}", cloudtheme);
+
+            let (status, entry) = send!(
+                app,
+                authorized(
+                    post(
+                        &format!("/api/cloudthemes/themes/{}/publish", cloudtheme["id"]),
+                        json!({ "title": $name, "description": "a theme", "listed": $listed })
+                    ),
+                    &author
+                )
+            );
+            assert_eq!(status, StatusCode::OK, "{}", entry);
+            entry
+        }};
+    }
+
+    let ocean = publish!("Ocean", true);
+    let secret = publish!("Secret", false);
+    let ocean_code = ocean["share_code"].as_str().unwrap();
+    assert_eq!(ocean_code.len(), 8);
+    assert_eq!(ocean["author"], "gallery_author");
+
+    // browsing needs no account and unlisted entries only show up by their share code
+    let (status, gallery) = send!(app, get("/pub_api/themes"));
+    assert_eq!(status, StatusCode::OK, "{}", gallery);
+    assert_eq!(titles(&gallery), ["Ocean"]);
+    assert_eq!(gallery[0]["preview"].as_array().unwrap().len(), 8);
+    assert!(gallery[0].get("uid").is_none());
+
+    let (status, body) = send!(
+        app,
+        get(&format!(
+            "/pub_api/themes/{}",
+            secret["share_code"].as_str().unwrap().to_lowercase()
+        ))
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["title"], "Secret");
+
+    // the first install is the active theme of the fan, a second one doesn't count again
+    let install_path = format!("/api/cloudthemes/gallery/{}/install", ocean_code);
+    let (status, installed) = send!(app, authorized(post(&install_path, json!({})), &fan));
+    assert_eq!(status, StatusCode::OK, "{}", installed);
+    assert_eq!(installed["name"], "Ocean");
+    assert_eq!(installed["active"], true);
+    assert_eq!(installed["theme"], theme());
+
+    let (status, _) = send!(app, authorized(post(&install_path, json!({})), &fan));
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = send!(
+        app,
+        authorized(post(&install_path, json!({ "name": "Ocean again" })), &fan)
+    );
+    assert_eq!(status, StatusCode::OK);
+
+    let sunset = publish!("Sunset", true);
+    let sunset_code = sunset["share_code"].as_str().unwrap();
+
+    let (_, gallery) = send!(app, get("/pub_api/themes?sort=newest"));
+    assert_eq!(titles(&gallery), ["Sunset", "Ocean"]);
+    let (_, gallery) = send!(app, get("/pub_api/themes?sort=most_installed"));
+    assert_eq!(titles(&gallery), ["Ocean", "Sunset"]);
+    assert_eq!(gallery[0]["installs"], 1);
+
+    // only owners moderate
+    let unpublish_path = format!("/admin/themes/{}/unpublish", ocean_code);
+    let (status, _) = send!(app, authorized(post(&unpublish_path, json!({})), &fan));
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, body) = send!(
+        app,
+        authorized(
+            post(
+                &unpublish_path,
+                json!({ "reason": "copied from someone else" })
+            ),
+            &owner
+        )
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let (status, _) = send!(app, get(&format!("/pub_api/themes/{}", ocean_code)));
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (_, gallery) = send!(app, get("/pub_api/themes"));
+    assert_eq!(titles(&gallery), ["Sunset"]);
+    let (status, _) = send!(
+        app,
+        authorized(post(&install_path, json!({ "name": "Ocean 3" })), &fan)
+    );
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // the author sees why
+    let (_, published) = send!(app, authorized(get("/api/cloudthemes/gallery"), &author));
+    assert_eq!(titles(&published), ["Sunset", "Secret", "Ocean"]);
+    assert_eq!(published[2]["published"], false);
+    assert_eq!(published[2]["unpublish_reason"], "copied from someone else");
+
+    // and can't put it back up, not even with a color changed
+    let mut changed = theme();
+    changed["primary_color"] = json!("#00ff00");
+    let (status, body) = send!(
+        app,
+        authorized(
+            post(
+                &format!("/api/cloudthemes/themes/{}", ocean["source_id"]),
+                changed
+            ),
+            &author
+        )
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let (status, _) = send!(
+        app,
+        authorized(
+            post(
+                &format!("/api/cloudthemes/themes/{}/publish", ocean["source_id"]),
+                json!({ "title": "Totally new ocean" })
+            ),
+            &author
+        )
+    );
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, body) = send!(
+        app,
+        authorized(
+            post(
+                &format!("/admin/themes/{}/republish", ocean_code),
+                json!({})
+            ),
+            &owner
+        )
+    );
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let (_, gallery) = send!(app, get("/pub_api/themes"));
+    assert_eq!(titles(&gallery), ["Sunset", "Ocean"]);
+
+    // what the author takes down themselves an owner can't put back up
+    let (status, _) = send!(
+        app,
+        authorized(
+            test::TestRequest::delete().uri(&format!("/api/cloudthemes/gallery/{}", sunset_code)),
+            &fan
+        )
+    );
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send!(
+        app,
+        authorized(
+            test::TestRequest::delete().uri(&format!("/api/cloudthemes/gallery/{}", sunset_code)),
+            &author
+        )
+    );
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send!(
+        app,
+        authorized(
+            post(
+                &format!("/admin/themes/{}/republish", sunset_code),
+                json!({})
+            ),
+            &owner
+        )
+    );
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, all) = send!(app, authorized(get("/admin/themes"), &owner));
+    assert_eq!(status, StatusCode::OK, "{}", all);
+    assert_eq!(titles(&all), ["Sunset", "Secret", "Ocean"]);
+
+    // Secret and Ocean are up, the same theme can be published more than once up to the limit
+    let publish_secret = format!("/api/cloudthemes/themes/{}/publish", secret["source_id"]);
+    for _ in 2..20 {
+        let (status, body) = send!(
+            app,
+            authorized(post(&publish_secret, json!({ "title": "Secret" })), &author)
+        );
+        assert_eq!(status, StatusCode::OK, "{}", body);
+    }
+    let (status, body) = send!(
+        app,
+        authorized(post(&publish_secret, json!({ "title": "Secret" })), &author)
+    );
+    assert_eq!(status, StatusCode::CONFLICT, "{}", body);
+}
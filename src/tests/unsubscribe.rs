@@ -0,0 +1,80 @@
+use actix_web::{http::StatusCode, test};
+use serde_json::json;
+
+use super::{authorized, get, post, send, verified_user, TestApp};
+use crate::{
+    app::build_app, mailer::notifications::unsubscribe_token,
+    models::api::notification_preferences::NotificationCategory,
+};
+
+const EMAIL: &str = "unsubscriber@example.com";
+
+fn emails_to(harness: &TestApp, to: &str) -> usize {
+    harness
+        .mailer
+        .sent()
+        .iter()
+        .filter(|email| email.to == to)
+        .count()
+}
+
+#[actix_web::test]
+async fn the_link_of_an_optional_email_turns_its_category_off() {
+    let harness = TestApp::new();
+    let app = test::init_service(build_app(harness.data())).await;
+
+    let token = verified_user!(harness, app, "unsubscriber", EMAIL);
+
+    let login = || {
+        post(
+            "/auth/login",
+            json!({ "username_or_email": EMAIL, "password": "Verified-password1" }),
+        )
+    };
+
+    let (status, body) = send!(app, login());
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    harness.deliver_emails().await;
+    let alert = harness.mailer.last_to(EMAIL).unwrap();
+    let url = alert.unsubscribe_url.unwrap();
+    let path = url.strip_prefix("http://localhost:8080").unwrap();
+
+    // opening the link only asks, a link scanner doesn't unsubscribe anyone
+    let response = test::call_service(&app, get(path).to_request()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let (_, body) = send!(app, authorized(get("/api/me/notifications"), &token));
+    assert_eq!(body["login_alerts"], true);
+
+    let response = test::call_service(&app, post(path, json!({})).to_request()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let (_, body) = send!(app, authorized(get("/api/me/notifications"), &token));
+    assert_eq!(body["login_alerts"], false);
+    assert_eq!(body["announcements"], true);
+
+    let sent = emails_to(&harness, EMAIL);
+
+    let (status, _) = send!(app, login());
+    assert_eq!(status, StatusCode::OK);
+
+    harness.deliver_emails().await;
+    assert_eq!(emails_to(&harness, EMAIL), sent);
+
+    // security emails can't be unsubscribed from, not even with a token signed by us
+    let (_, body) = send!(app, authorized(get("/api/me"), &token));
+    let uid = body["uid"].as_i64().unwrap();
+
+    for token in [
+        unsubscribe_token(uid, NotificationCategory::Security),
+        String::from("not.a.token"),
+    ] {
+        let response = test::call_service(
+            &app,
+            post(&format!("/pub_api/unsubscribe?token={}", token), json!({})).to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
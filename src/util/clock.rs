@@ -0,0 +1,15 @@
+use chrono::{DateTime, Utc};
+
+// where expiries, timestamps and the jobs read the time from, so tests can move it forward
+// instead of waiting
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
@@ -39,3 +39,66 @@ pub fn markdown_to_html(markdown_input: &str) -> String {
     println!("coke: {:?}", html_output);
     html_output
 }
+
+// plain text version of an html email, used as the text/plain alternative
+pub fn html_to_text(html: &str) -> String {
+    let document = parse_html().one(html);
+
+    let root = match document.select_first("body") {
+        Ok(body) => body.as_node().clone(),
+        Err(()) => document,
+    };
+
+    let mut text = String::new();
+    push_text(&root, &mut text);
+
+    let lines: Vec<&str> = text.lines().map(str::trim).collect();
+
+    let re = Regex::new(r"\n{3,}").unwrap();
+    re.replace_all(lines.join("\n").trim(), "\n\n").to_string()
+}
+
+fn push_text(node: &kuchiki::NodeRef, text: &mut String) {
+    for child in node.children() {
+        if let Some(content) = child.as_text() {
+            let content = content.borrow();
+            let words: Vec<&str> = content.split_whitespace().collect();
+
+            if words.is_empty() {
+                continue;
+            }
+
+            if content.starts_with(char::is_whitespace) && !text.ends_with(char::is_whitespace) {
+                text.push(' ');
+            }
+
+            text.push_str(&words.join(" "));
+
+            if content.ends_with(char::is_whitespace) {
+                text.push(' ');
+            }
+        } else if let Some(element) = child.as_element() {
+            match &*element.name.local {
+                "head" | "title" | "style" | "script" => {}
+                "br" => text.push('\n'),
+                "li" => {
+                    text.push_str("\n- ");
+                    push_text(&child, text);
+                }
+                "a" => {
+                    push_text(&child, text);
+
+                    if let Some(href) = element.attributes.borrow().get("href") {
+                        text.push_str(&format!(" ({})", href));
+                    }
+                }
+                "p" | "div" | "ul" | "ol" | "h1" | "h2" | "h3" | "h4" | "table" | "tr" => {
+                    text.push_str("\n\n");
+                    push_text(&child, text);
+                    text.push_str("\n\n");
+                }
+                _ => push_text(&child, text),
+            }
+        }
+    }
+}
@@ -1 +1,3 @@
+pub mod clock;
 pub mod html_utils;
+pub mod snowflake;
@@ -0,0 +1,118 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+
+use crate::secrets::SECRETS;
+
+// layout of a uid (from the highest bit): 1 unused sign bit, 41 bits of milliseconds since EPOCH,
+// 10 bits worker id and 12 bits sequence
+const EPOCH: u64 = 1_704_037_200_000;
+
+const WORKER_ID_BITS: u64 = 10;
+const SEQUENCE_BITS: u64 = 12;
+const MAX_WORKER_ID: u64 = (1 << WORKER_ID_BITS) - 1;
+const MAX_SEQUENCE: u64 = (1 << SEQUENCE_BITS) - 1;
+const TIMESTAMP_MASK: u64 = (1 << 41) - 1;
+
+pub struct Snowflake {
+    worker_id: u64,
+    // last used timestamp and sequence packed as `timestamp << SEQUENCE_BITS | sequence`
+    state: AtomicU64,
+}
+
+// main checks it before the first uid is generated
+pub static UID_GENERATOR: Lazy<Snowflake> =
+    Lazy::new(|| Snowflake::new(worker_id().expect("invalid WORKER_ID")));
+
+// WORKER_ID from the secrets, 0 if it isn't set. every instance sharing a database needs its own
+pub fn worker_id() -> anyhow::Result<u64> {
+    let Some(id) = SECRETS.get("WORKER_ID") else {
+        return Ok(0);
+    };
+
+    match id.trim().parse() {
+        Ok(worker_id) if worker_id <= MAX_WORKER_ID => Ok(worker_id),
+        _ => anyhow::bail!(
+            "WORKER_ID must be a number between 0 and {}, got '{}'",
+            MAX_WORKER_ID,
+            id
+        ),
+    }
+}
+
+impl Snowflake {
+    pub fn new(worker_id: u64) -> Self {
+        assert!(
+            worker_id <= MAX_WORKER_ID,
+            "WORKER_ID must be between 0 and {}",
+            MAX_WORKER_ID
+        );
+
+        Snowflake {
+            worker_id,
+            state: AtomicU64::new(0),
+        }
+    }
+
+    fn current_millis() -> u64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+
+        now.saturating_sub(EPOCH)
+    }
+
+    pub fn generate(&self) -> i64 {
+        let mut last = self.state.load(Ordering::Acquire);
+
+        loop {
+            let last_timestamp = last >> SEQUENCE_BITS;
+            let last_sequence = last & MAX_SEQUENCE;
+            let now = Self::current_millis();
+
+            // if the clock went backwards (or we are still in the same millisecond) we keep counting
+            // on the last timestamp, once its sequence is used up we borrow the next millisecond.
+            // this keeps the uids unique and increasing without blocking on the clock.
+            let (timestamp, sequence) = if now > last_timestamp {
+                (now, 0)
+            } else if last_sequence < MAX_SEQUENCE {
+                (last_timestamp, last_sequence + 1)
+            } else {
+                (last_timestamp + 1, 0)
+            };
+
+            let next = (timestamp << SEQUENCE_BITS) | sequence;
+
+            match self
+                .state
+                .compare_exchange_weak(last, next, Ordering::AcqRel, Ordering::Acquire)
+            {
+                Ok(_) => {
+                    let uid = ((timestamp & TIMESTAMP_MASK) << (WORKER_ID_BITS + SEQUENCE_BITS))
+                        | (self.worker_id << SEQUENCE_BITS)
+                        | sequence;
+                    return uid as i64;
+                }
+                Err(actual) => last = actual,
+            }
+        }
+    }
+}
+
+pub fn generate_uid() -> i64 {
+    UID_GENERATOR.generate()
+}
+
+// the time a uid was generated at. accounts keep their own created_at, the uid is only a
+// fallback for things which don't store one
+#[allow(dead_code)]
+pub fn uid_timestamp(uid: i64) -> DateTime<Utc> {
+    let millis = ((uid as u64) >> (WORKER_ID_BITS + SEQUENCE_BITS)) + EPOCH;
+
+    DateTime::from_timestamp_millis(millis as i64).unwrap_or_default()
+}